@@ -62,8 +62,10 @@ describe('MagicBlock PER - Private Payments', () => {
     });
 
     describe('findPrivateReceiptPda', () => {
+        const merchant = new PublicKey(TEST_WALLET);
+
         it('should derive valid PDA for payment ID', () => {
-            const [pda, bump] = findPrivateReceiptPda('pay_test_123');
+            const [pda, bump] = findPrivateReceiptPda(merchant, 'pay_test_123');
             expect(pda).to.be.instanceOf(PublicKey);
             expect(bump).to.be.a('number');
             expect(bump).to.be.lessThanOrEqual(255);
@@ -71,22 +73,28 @@ describe('MagicBlock PER - Private Payments', () => {
 
         it('should be deterministic (same input = same output)', () => {
             const paymentId = 'pay_deterministic_test';
-            const [pda1] = findPrivateReceiptPda(paymentId);
-            const [pda2] = findPrivateReceiptPda(paymentId);
+            const [pda1] = findPrivateReceiptPda(merchant, paymentId);
+            const [pda2] = findPrivateReceiptPda(merchant, paymentId);
             expect(pda1.toBase58()).to.equal(pda2.toBase58());
         });
 
         it('should return different PDAs for different payment IDs', () => {
-            const [pda1] = findPrivateReceiptPda('pay_first');
-            const [pda2] = findPrivateReceiptPda('pay_second');
+            const [pda1] = findPrivateReceiptPda(merchant, 'pay_first');
+            const [pda2] = findPrivateReceiptPda(merchant, 'pay_second');
             expect(pda1.toBase58()).to.not.equal(pda2.toBase58());
         });
 
-        it('should handle long payment IDs (truncation)', () => {
+        it('should handle long payment IDs (hashed seed)', () => {
             const longId = 'a'.repeat(100);
-            const [pda] = findPrivateReceiptPda(longId);
+            const [pda] = findPrivateReceiptPda(merchant, longId);
             expect(pda).to.be.instanceOf(PublicKey);
         });
+
+        it('should return different PDAs for the same payment ID under different merchants', () => {
+            const [pda1] = findPrivateReceiptPda(merchant, 'pay_shared');
+            const [pda2] = findPrivateReceiptPda(PublicKey.default, 'pay_shared');
+            expect(pda1.toBase58()).to.not.equal(pda2.toBase58());
+        });
     });
 
     describe('Delegation PDAs', () => {
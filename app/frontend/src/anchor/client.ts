@@ -1,4 +1,4 @@
-import { Program, AnchorProvider, BN } from "@coral-xyz/anchor";
+import { Program, AnchorProvider, BN, utils } from "@coral-xyz/anchor";
 import { Connection, PublicKey, SystemProgram } from "@solana/web3.js";
 import {
     TOKEN_PROGRAM_ID,
@@ -24,6 +24,11 @@ const MERCHANT_SEED = Buffer.from("merchant");
 const CUSTOMER_SEED = Buffer.from("customer");
 const PAYMENT_SEED = Buffer.from("payment");
 
+// Merchant and payment IDs are hashed on-chain (sha256) to fit the 32-byte seed limit
+function idSeed(id: string): Buffer {
+    return Buffer.from(utils.sha256.hash(id), "hex");
+}
+
 // Derive PDAs
 export function getPlatformConfigPDA(): [PublicKey, number] {
     return PublicKey.findProgramAddressSync([PLATFORM_CONFIG_SEED], PROGRAM_ID);
@@ -35,7 +40,7 @@ export function getPlatformTreasuryPDA(): [PublicKey, number] {
 
 export function getMerchantPDA(merchantId: string): [PublicKey, number] {
     return PublicKey.findProgramAddressSync(
-        [MERCHANT_SEED, Buffer.from(merchantId)],
+        [MERCHANT_SEED, idSeed(merchantId)],
         PROGRAM_ID
     );
 }
@@ -47,9 +52,11 @@ export function getCustomerPDA(customerPubkey: PublicKey): [PublicKey, number] {
     );
 }
 
-export function getPaymentPDA(paymentId: string): [PublicKey, number] {
+// Payment IDs are scoped per merchant
+export function getPaymentPDA(merchantId: string, paymentId: string): [PublicKey, number] {
+    const [merchantPDA] = getMerchantPDA(merchantId);
     return PublicKey.findProgramAddressSync(
-        [PAYMENT_SEED, Buffer.from(paymentId)],
+        [PAYMENT_SEED, merchantPDA.toBuffer(), idSeed(paymentId)],
         PROGRAM_ID
     );
 }
@@ -70,7 +77,7 @@ export interface PlatformConfig {
 export interface Merchant {
     merchantId: string;
     settlementWallet: PublicKey;
-    feeBps: number;
+    fee: number | null;
     isActive: boolean;
     volume: BN;
     transactionCount: BN;
@@ -93,7 +100,8 @@ export interface Payment {
     merchant: PublicKey;
     amount: BN;
     fee: BN;
-    status: { pending: {} } | { completed: {} } | { refunded: {} };
+    refundedAmount: BN;
+    status: { pending: {} } | { completed: {} } | { partiallyRefunded: {} } | { refunded: {} };
     createdAt: BN;
     bump: number;
 }
@@ -144,9 +152,9 @@ export class X402PaymentClient {
     }
 
     // Fetch payment
-    async getPayment(paymentId: string): Promise<Payment | null> {
+    async getPayment(merchantId: string, paymentId: string): Promise<Payment | null> {
         try {
-            const [paymentPDA] = getPaymentPDA(paymentId);
+            const [paymentPDA] = getPaymentPDA(merchantId, paymentId);
             const account = await this.program.account.payment.fetch(paymentPDA);
             return account as unknown as Payment;
         } catch {
@@ -163,7 +171,7 @@ export class X402PaymentClient {
         const [platformConfigPDA] = getPlatformConfigPDA();
         const [platformTreasuryPDA] = getPlatformTreasuryPDA();
         const [merchantPDA] = getMerchantPDA(merchantId);
-        const [paymentPDA] = getPaymentPDA(paymentId);
+        const [paymentPDA] = getPaymentPDA(merchantId, paymentId);
         const payer = this.program.provider.publicKey!;
         const [customerPDA] = getCustomerPDA(payer);
 
@@ -181,9 +189,10 @@ export class X402PaymentClient {
         );
 
         const tx = await this.program.methods
-            .processPayment(paymentId, new BN(amount))
+            .processPayment(paymentId, new BN(amount), null)
             .accounts({
                 payer,
+                customer: payer,
                 platformConfig: platformConfigPDA,
                 paymentAccount: paymentPDA,
                 customerAccount: customerPDA,
@@ -192,6 +201,7 @@ export class X402PaymentClient {
                 customerUsdc,
                 merchantUsdc,
                 platformTreasuryUsdc: platformTreasuryPDA,
+                sponsorUsdc: null,
                 tokenProgram: TOKEN_PROGRAM_ID,
                 associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
                 systemProgram: SystemProgram.programId,
@@ -204,15 +214,14 @@ export class X402PaymentClient {
     // Initialize merchant (for merchant registration)
     async initializeMerchant(
         merchantId: string,
-        settlementWallet: PublicKey,
-        feeBps: number = 0
+        settlementWallet: PublicKey
     ): Promise<string> {
         const [platformConfigPDA] = getPlatformConfigPDA();
         const [merchantPDA] = getMerchantPDA(merchantId);
         const payer = this.program.provider.publicKey!;
 
         const tx = await this.program.methods
-            .initializeMerchant(merchantId, feeBps)
+            .initializeMerchant(merchantId, 0) // fee_bps is deprecated and ignored
             .accounts({
                 payer,
                 merchantAccount: merchantPDA,
@@ -225,13 +234,13 @@ export class X402PaymentClient {
         return tx;
     }
 
-    // Request refund
-    async refundPayment(paymentId: string): Promise<string> {
+    // Request refund; omit refundAmount to refund whatever is left
+    async refundPayment(merchantId: string, paymentId: string, refundAmount?: number): Promise<string> {
         const [platformConfigPDA] = getPlatformConfigPDA();
         const [platformTreasuryPDA] = getPlatformTreasuryPDA();
-        const [paymentPDA] = getPaymentPDA(paymentId);
+        const [paymentPDA] = getPaymentPDA(merchantId, paymentId);
 
-        const payment = await this.getPayment(paymentId);
+        const payment = await this.getPayment(merchantId, paymentId);
         if (!payment) {
             throw new Error("Payment not found");
         }
@@ -246,8 +255,9 @@ export class X402PaymentClient {
         );
         const payerUsdc = getAssociatedTokenAddressSync(USDC_MINT, payment.payer);
 
+        const remaining = payment.amount.sub(payment.refundedAmount);
         const tx = await this.program.methods
-            .refundPayment()
+            .refundPayment(refundAmount === undefined ? remaining : new BN(refundAmount))
             .accounts({
                 merchantAuthority: this.program.provider.publicKey!,
                 platformConfig: platformConfigPDA,
@@ -266,10 +276,11 @@ export class X402PaymentClient {
         return tx;
     }
 
-    // Calculate fee for an amount
-    async calculateFee(amount: number): Promise<{ fee: number; merchantAmount: number }> {
+    // Calculate fee for an amount (merchant override first, then platform default)
+    async calculateFee(amount: number, merchantId?: string): Promise<{ fee: number; merchantAmount: number }> {
+        const merchant = merchantId ? await this.getMerchant(merchantId) : null;
         const platformConfig = await this.getPlatformConfig();
-        const feeBps = platformConfig?.feeBps || 100; // Default 1%
+        const feeBps = merchant?.fee ?? (platformConfig ? Number(platformConfig.feeBps) : 100); // Default 1%
 
         const fee = Math.floor((amount * feeBps) / 10000);
         const merchantAmount = amount - fee;
@@ -114,7 +114,7 @@ export async function POST(request: NextRequest) {
                 }
 
                 // Derive PDAs
-                const [privateReceiptPda] = findPrivateReceiptPda(paymentId);
+                const [privateReceiptPda] = findPrivateReceiptPda(new PublicKey(merchant), paymentId);
                 const [delegationRecordPda] = findDelegationRecordPda(privateReceiptPda);
                 const [delegationMetadataPda] = findDelegationMetadataPda(privateReceiptPda);
 
@@ -273,13 +273,15 @@ export async function POST(request: NextRequest) {
                     }
                 }
 
-                // Not found — derive PDA for on-chain check
-                const [privateReceiptPda] = findPrivateReceiptPda(paymentId);
+                // Not found — derive PDA for on-chain check (receipts are scoped per merchant)
+                const privateReceiptPda = merchant
+                    ? findPrivateReceiptPda(new PublicKey(merchant), paymentId)[0]
+                    : null;
 
                 return NextResponse.json({
                     success: false,
                     exists: false,
-                    privateReceiptPda: privateReceiptPda.toBase58(),
+                    privateReceiptPda: privateReceiptPda?.toBase58() ?? null,
                     message: 'Session not found. Check on-chain PDA.',
                 });
             }
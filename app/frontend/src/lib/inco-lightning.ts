@@ -15,6 +15,7 @@
  */
 
 import { PublicKey, Connection } from '@solana/web3.js';
+import { utils } from '@coral-xyz/anchor';
 
 // MagicBlock Delegation Program
 export const DELEGATION_PROGRAM_ID = new PublicKey('DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh');
@@ -54,19 +55,16 @@ export const SESSION_STATUS_LABELS: Record<SessionStatus, string> = {
 /**
  * Find the Private Receipt PDA for a payment
  * 
+ * @param merchant - Merchant recorded on the receipt (payment IDs are scoped per merchant)
  * @param paymentId - Unique payment identifier (e.g., tx signature)
  * @returns [PDA address, bump seed]
  */
-export function findPrivateReceiptPda(paymentId: string): [PublicKey, number] {
-    let paymentIdSeed: Buffer;
-    if (paymentId.length > 32) {
-        paymentIdSeed = Buffer.from(paymentId.slice(0, 32));
-    } else {
-        paymentIdSeed = Buffer.from(paymentId);
-    }
+export function findPrivateReceiptPda(merchant: PublicKey, paymentId: string): [PublicKey, number] {
+    // The program seeds with sha256(payment_id) so IDs longer than 32 bytes work
+    const paymentIdSeed = Buffer.from(utils.sha256.hash(paymentId), 'hex');
 
     return PublicKey.findProgramAddressSync(
-        [PRIVATE_RECEIPT_SEED, paymentIdSeed],
+        [PRIVATE_RECEIPT_SEED, merchant.toBuffer(), paymentIdSeed],
         OFFBANK_PROGRAM_ID
     );
 }
@@ -36,4 +36,176 @@ pub enum PaymentError {
     MissingAllowanceAccounts,
     #[msg("Insufficient treasury balance for payout.")]
     InsufficientTreasuryBalance,
+    #[msg("No authority transfer is pending.")]
+    NoPendingAuthority,
+    #[msg("Proposed authority is already the current authority.")]
+    AuthorityUnchanged,
+    #[msg("Accrued platform fees are insufficient to cover this payment's fee.")]
+    InsufficientAccruedFees,
+    #[msg("The provided delay is out of range.")]
+    InvalidDelay,
+    #[msg("No settlement wallet change is pending.")]
+    NoPendingSettlementWallet,
+    #[msg("The settlement wallet change is still timelocked.")]
+    SettlementWalletTimelocked,
+    #[msg("Merchant is suspended by the platform.")]
+    MerchantSuspended,
+    #[msg("Merchant is not suspended.")]
+    MerchantNotSuspended,
+    #[msg("Account is not a legacy payment for this payment ID.")]
+    InvalidLegacyPayment,
+    #[msg("Refund amount must be positive and no more than the unrefunded balance.")]
+    InvalidRefundAmount,
+    #[msg("The refund window is out of range.")]
+    InvalidRefundWindow,
+    #[msg("The refund window for this payment has closed.")]
+    RefundWindowClosed,
+    #[msg("The refund window for this payment is still open.")]
+    RefundWindowOpen,
+    #[msg("Payment has already been finalized.")]
+    PaymentAlreadyFinalized,
+    #[msg("Refund request is not open.")]
+    RefundRequestNotOpen,
+    #[msg("The merchant response deadline has passed; the platform must resolve this request.")]
+    RefundRequestEscalated,
+    #[msg("The merchant can still respond to this refund request.")]
+    RefundRequestNotEscalated,
+    #[msg("A refund request on this payment is awaiting a decision.")]
+    RefundRequestPending,
+    #[msg("Dispute is not open.")]
+    DisputeNotOpen,
+    #[msg("A dispute on this payment is awaiting a ruling.")]
+    DisputePending,
+    #[msg("Merchant has open disputes; the reserve is locked.")]
+    MerchantHasOpenDisputes,
+    #[msg("Merchant reserve cannot cover this ruling.")]
+    InsufficientReserve,
+    #[msg("Amount must be greater than zero.")]
+    InvalidAmount,
+    #[msg("Payment is not in a state that allows this action.")]
+    InvalidPaymentStatus,
+    #[msg("The escrow timeout is out of range.")]
+    InvalidEscrowTimeout,
+    #[msg("Payment is not held in escrow.")]
+    PaymentNotEscrowed,
+    #[msg("Payment is still held in escrow.")]
+    PaymentInEscrow,
+    #[msg("Payment escrow was cancelled.")]
+    PaymentCancelled,
+    #[msg("The escrow timeout has not passed yet.")]
+    EscrowTimeoutNotReached,
+    #[msg("Milestones must be non-empty, within the limit and each above zero.")]
+    InvalidMilestones,
+    #[msg("No milestone at this index.")]
+    InvalidMilestoneIndex,
+    #[msg("Milestone has already been released.")]
+    MilestoneAlreadyReleased,
+    #[msg("Milestone needs buyer approval or its release time has not passed.")]
+    MilestoneNotDue,
+    #[msg("Attester is already registered.")]
+    AttesterAlreadyRegistered,
+    #[msg("Attester is not registered.")]
+    AttesterNotFound,
+    #[msg("Attester registry is full.")]
+    AttesterRegistryFull,
+    #[msg("Signer is not an approved attester.")]
+    AttesterNotApproved,
+    #[msg("This escrow only releases on a passing attestation.")]
+    AttestationRequired,
+    #[msg("Payment escrow does not take attestations.")]
+    AttestationNotRequired,
+    #[msg("The attestation passed; funds are not refundable through this path.")]
+    AttestationPassed,
+    #[msg("Due date must be in the future.")]
+    InvalidDueDate,
+    #[msg("Memo is too long.")]
+    InvalidMemo,
+    #[msg("Invoice is not open.")]
+    InvoiceNotOpen,
+    #[msg("Invoice is past its due date.")]
+    InvoiceOverdue,
+    #[msg("Invoice is addressed to a different payer.")]
+    InvoicePayerMismatch,
+    #[msg("Invalid invoice terms: discount must be under 100% and end by the due date, late fee must be non-zero.")]
+    InvalidInvoiceTerms,
+    #[msg("Expiry must be in the future.")]
+    InvalidExpiry,
+    #[msg("A checkout session needs at least one use.")]
+    InvalidMaxUses,
+    #[msg("Checkout session has expired.")]
+    CheckoutSessionExpired,
+    #[msg("Checkout session has no uses left.")]
+    CheckoutSessionUsedUp,
+    #[msg("Checkout session is still usable and can't be closed yet.")]
+    CheckoutSessionActive,
+    #[msg("Subscription interval must be positive.")]
+    InvalidInterval,
+    #[msg("A subscription needs at least one cycle.")]
+    InvalidMaxCycles,
+    #[msg("Subscription is not active.")]
+    SubscriptionNotActive,
+    #[msg("Subscription cycle is not due yet.")]
+    SubscriptionNotDue,
+    #[msg("Authorization TTL must be between 1 second and 30 days.")]
+    InvalidAuthorizationTtl,
+    #[msg("Authorization has already been captured or released.")]
+    AuthorizationNotActive,
+    #[msg("Authorization has expired.")]
+    AuthorizationExpired,
+    #[msg("Authorization has not expired yet.")]
+    AuthorizationNotExpired,
+    #[msg("Capture amount must be non-zero and no more than the authorized amount.")]
+    InvalidCaptureAmount,
+    #[msg("Sponsor is already registered.")]
+    SponsorAlreadyRegistered,
+    #[msg("Sponsor is not registered.")]
+    SponsorNotFound,
+    #[msg("Sponsor registry is full.")]
+    SponsorRegistryFull,
+    #[msg("Sponsor fee exceeds the platform maximum or the payment amount.")]
+    SponsorFeeTooHigh,
+    #[msg("Sponsor fee requires a token account owned by a registered sponsor.")]
+    InvalidSponsorAccount,
+    #[msg("Expected an Ed25519 signature verification instruction before this one.")]
+    MissingIntentSignature,
+    #[msg("Ed25519 instruction does not match the customer or the payment intent.")]
+    InvalidIntentSignature,
+    #[msg("Payment intent has expired.")]
+    IntentExpired,
+    #[msg("Payment intent nonce does not match the customer's next nonce.")]
+    InvalidIntentNonce,
+    #[msg("Payment intent was signed for a different merchant.")]
+    IntentMerchantMismatch,
+    #[msg("Account is not in the legacy layout this migration expects.")]
+    InvalidLegacyAccount,
+    #[msg("Pending platform fees are insufficient to cover this refund.")]
+    InsufficientPendingFees,
+    #[msg("The merchant authority change is still timelocked.")]
+    MerchantAuthorityTimelocked,
+    #[msg("The refund request has expired.")]
+    RefundRequestExpired,
+    #[msg("The refund request has not expired yet.")]
+    RefundRequestNotExpired,
+    #[msg("Dispute window must be between 0 and 180 days.")]
+    InvalidDisputeWindow,
+    #[msg("The dispute window for this payment has closed.")]
+    DisputeWindowClosed,
+    #[msg("The payment can still be disputed.")]
+    DisputeWindowOpen,
+    #[msg("Withdrawal would dip into reserve locked by open disputes.")]
+    ReserveLocked,
+    #[msg("Delivery deadline must be in the future.")]
+    InvalidDeliveryDeadline,
+    #[msg("The order's delivery deadline has not passed yet.")]
+    DeliveryDeadlineNotReached,
+    #[msg("No outstanding milestone can be cancelled.")]
+    NothingToCancel,
+    #[msg("The attestation deadline has passed.")]
+    AttestationDeadlinePassed,
+    #[msg("No failed attestation and the attestation deadline has not passed yet.")]
+    AttestationDeadlineNotReached,
+    #[msg("The merchant still owes part of a dispute ruling; refund it before finalizing.")]
+    DisputeShortfallUnpaid,
+    #[msg("Audited legacy fees don't cover this payment's fee.")]
+    InsufficientLegacyFees,
 }
\ No newline at end of file
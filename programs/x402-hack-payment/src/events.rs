@@ -0,0 +1,710 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{DisputeRuling, FeeMode, FeeSource, InvoiceTerms, PaymentStatus, RefundReason, SuspensionReason};
+
+// ─── Platform ────────────────────────────────────────────────────────
+
+#[event]
+pub struct PlatformConfigured {
+    pub platform_config: Pubkey,
+    pub authority: Pubkey,
+    pub treasury: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub fee_bps: u64,
+    pub min_payment_amount: u64,
+    pub settlement_wallet_delay: i64,
+    pub refund_window: i64,
+    pub arbiter: Pubkey,
+    pub escrow_timeout: i64,
+    pub authorization_ttl: i64,
+    pub max_sponsor_fee: u64,
+    pub dispute_window: i64,
+    pub configured_at: i64,
+}
+
+/// Carries the full config after the update, not just the changed fields.
+#[event]
+pub struct PlatformConfigUpdated {
+    pub platform_config: Pubkey,
+    pub authority: Pubkey,
+    pub fee_bps: u64,
+    pub min_payment_amount: u64,
+    pub is_active: bool,
+    pub settlement_wallet_delay: i64,
+    pub refund_window: i64,
+    pub arbiter: Pubkey,
+    pub escrow_timeout: i64,
+    pub authorization_ttl: i64,
+    pub max_sponsor_fee: u64,
+    pub dispute_window: i64,
+    pub updated_at: i64,
+}
+
+#[event]
+pub struct SponsorAdded {
+    pub sponsor: Pubkey,
+    pub added_at: i64,
+}
+
+#[event]
+pub struct SponsorRemoved {
+    pub sponsor: Pubkey,
+    pub removed_at: i64,
+}
+
+#[event]
+pub struct AuthorityTransferProposed {
+    pub authority: Pubkey,
+    pub proposed_authority: Pubkey,
+    pub proposed_at: i64,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub transferred_at: i64,
+}
+
+#[event]
+pub struct AuthorityTransferCancelled {
+    pub authority: Pubkey,
+    pub cancelled_authority: Pubkey,
+    pub cancelled_at: i64,
+}
+
+#[event]
+pub struct FeesClaimed {
+    pub authority: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub claimed_at: i64,
+}
+
+#[event]
+pub struct PayoutProcessed {
+    pub payout_id: String,
+    pub recipient: Pubkey,
+    pub recipient_usdc: Pubkey,
+    pub amount: u64,
+    pub processed_at: i64,
+}
+
+#[event]
+pub struct PlatformConfigMigrated {
+    pub platform_config: Pubkey,
+    pub authority: Pubkey,
+    /// Audited pre-upgrade fees, released as their payments are migrated
+    pub legacy_fees: u64,
+    /// Rest of the treasury balance, left as payout escrow
+    pub escrow_balance: u64,
+    pub migrated_at: i64,
+}
+
+// ─── Merchants ───────────────────────────────────────────────────────
+
+#[event]
+pub struct MerchantInitialized {
+    pub merchant: Pubkey,
+    pub merchant_id: String,
+    pub authority: Pubkey,
+    pub settlement_wallet: Pubkey,
+    pub created_at: i64,
+}
+
+/// Carries the merchant's state after the update, not just the changed fields.
+#[event]
+pub struct MerchantUpdated {
+    pub merchant: Pubkey,
+    pub authority: Pubkey,
+    pub is_active: bool,
+    pub fee_mode: FeeMode,
+    pub pending_settlement_wallet: Option<Pubkey>,
+    pub settlement_wallet_effective_at: i64,
+    pub pending_authority: Option<Pubkey>,
+    pub authority_effective_at: i64,
+    pub updated_at: i64,
+}
+
+#[event]
+pub struct SettlementWalletChanged {
+    pub merchant: Pubkey,
+    pub old_wallet: Pubkey,
+    pub new_wallet: Pubkey,
+    pub changed_at: i64,
+}
+
+#[event]
+pub struct SettlementWalletChangeCancelled {
+    pub merchant: Pubkey,
+    pub cancelled_wallet: Pubkey,
+    pub cancelled_at: i64,
+}
+
+#[event]
+pub struct MerchantMigrated {
+    pub merchant: Pubkey,
+    pub legacy_merchant: Pubkey,
+    pub merchant_id: String,
+    pub migrated_at: i64,
+}
+
+#[event]
+pub struct MerchantAuthorityTransferred {
+    pub merchant: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub transferred_at: i64,
+}
+
+#[event]
+pub struct MerchantAuthorityTransferCancelled {
+    pub merchant: Pubkey,
+    pub cancelled_authority: Pubkey,
+    pub cancelled_at: i64,
+}
+
+#[event]
+pub struct MerchantFeeUpdated {
+    pub merchant: Pubkey,
+    pub old_fee_bps: Option<u16>,
+    pub new_fee_bps: Option<u16>,
+    pub updated_at: i64,
+}
+
+#[event]
+pub struct MerchantRefundWindowUpdated {
+    pub merchant: Pubkey,
+    pub old_refund_window: Option<i64>,
+    pub new_refund_window: Option<i64>,
+    pub updated_at: i64,
+}
+
+#[event]
+pub struct MerchantReserveFunded {
+    pub merchant: Pubkey,
+    pub reserve: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub funded_at: i64,
+}
+
+#[event]
+pub struct MerchantReserveWithdrawn {
+    pub merchant: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub withdrawn_at: i64,
+}
+
+#[event]
+pub struct MerchantSuspended {
+    pub merchant: Pubkey,
+    pub reason: SuspensionReason,
+    pub suspended_at: i64,
+}
+
+#[event]
+pub struct MerchantReinstated {
+    pub merchant: Pubkey,
+    pub previous_reason: SuspensionReason,
+    pub reinstated_at: i64,
+}
+
+// ─── Payments ────────────────────────────────────────────────────────
+
+/// `amount` is `merchant_amount + fee_amount` and excludes `sponsor_fee`;
+/// the customer paid the sum of the two.
+#[event]
+pub struct PaymentProcessed {
+    pub payment: Pubkey,
+    pub payment_id: String,
+    pub merchant: Pubkey,
+    pub customer: Pubkey,
+    pub amount: u64,
+    pub fee_amount: u64,
+    pub fee_bps: u64,
+    pub fee_source: FeeSource,
+    pub fee_mode: FeeMode,
+    pub merchant_amount: u64,
+    pub sponsor_fee: u64,
+    pub sponsor: Option<Pubkey>,
+    pub created_at: i64,
+    pub refund_deadline: i64,
+}
+
+/// Like `PaymentProcessed`, but nothing has moved to the merchant or the
+/// treasury yet; `fee_amount` is only taken on release.
+#[event]
+pub struct PaymentEscrowed {
+    pub payment: Pubkey,
+    pub payment_id: String,
+    pub merchant: Pubkey,
+    pub customer: Pubkey,
+    pub escrow: Pubkey,
+    pub amount: u64,
+    pub fee_amount: u64,
+    pub fee_bps: u64,
+    pub fee_source: FeeSource,
+    pub fee_mode: FeeMode,
+    pub merchant_amount: u64,
+    /// Timeout auto-release; for attestation-gated escrow, the deadline after
+    /// which it goes back to the customer instead
+    pub release_at: i64,
+    pub requires_attestation: bool,
+    pub created_at: i64,
+}
+
+#[event]
+pub struct EscrowReleased {
+    pub payment: Pubkey,
+    pub payment_id: String,
+    pub merchant: Pubkey,
+    pub released_by: Pubkey,
+    /// True for `confirm_delivery`, false for a timeout release
+    pub confirmed_by_customer: bool,
+    pub merchant_amount: u64,
+    pub fee_amount: u64,
+    pub refund_deadline: i64,
+    pub released_at: i64,
+}
+
+#[event]
+pub struct EscrowCancelled {
+    pub payment: Pubkey,
+    pub payment_id: String,
+    pub merchant: Pubkey,
+    pub customer: Pubkey,
+    pub amount: u64,
+    pub cancelled_at: i64,
+}
+
+/// One refund against a payment. `merchant_amount` and `fee_amount` are the legs
+/// of this refund; `refunded_amount` is the running total.
+#[event]
+pub struct PaymentRefunded {
+    pub payment: Pubkey,
+    pub payment_id: String,
+    pub merchant: Pubkey,
+    pub customer: Pubkey,
+    pub amount: u64,
+    pub refund_amount: u64,
+    pub merchant_amount: u64,
+    pub fee_amount: u64,
+    pub refunded_amount: u64,
+    pub status: PaymentStatus,
+    pub refunded_at: i64,
+}
+
+#[event]
+pub struct PaymentFinalized {
+    pub payment: Pubkey,
+    pub payment_id: String,
+    pub merchant: Pubkey,
+    pub customer: Pubkey,
+    pub amount: u64,
+    pub fee_amount: u64,
+    pub refunded_amount: u64,
+    pub finalized_at: i64,
+}
+
+/// Followed by `PaymentProcessed` for the same payment.
+#[event]
+pub struct PaymentIntentExecuted {
+    pub payment: Pubkey,
+    pub customer: Pubkey,
+    pub submitted_by: Pubkey,
+    pub nonce: u64,
+    pub expiry: i64,
+    pub fee_mode: FeeMode,
+}
+
+#[event]
+pub struct DelegateApproved {
+    pub customer: Pubkey,
+    pub customer_usdc: Pubkey,
+    pub added_amount: u64,
+    /// Total the delegate PDA can now spend, including earlier approvals
+    pub delegated_amount: u64,
+    pub approved_at: i64,
+}
+
+#[event]
+pub struct PaymentMigrated {
+    pub payment: Pubkey,
+    pub legacy_payment: Pubkey,
+    pub payment_id: String,
+    pub merchant: Pubkey,
+    pub migrated_at: i64,
+}
+
+#[event]
+pub struct CustomerMigrated {
+    pub customer_account: Pubkey,
+    pub customer: Pubkey,
+    pub migrated_at: i64,
+}
+
+// ─── Authorizations ──────────────────────────────────────────────────
+
+#[event]
+pub struct PaymentAuthorized {
+    pub authorization: Pubkey,
+    pub authorization_id: String,
+    pub customer: Pubkey,
+    pub merchant: Pubkey,
+    pub hold: Pubkey,
+    pub amount: u64,
+    pub fee_bps: u64,
+    pub fee_source: FeeSource,
+    pub expires_at: i64,
+    pub created_at: i64,
+}
+
+/// Followed by the `PaymentProcessed` for the captured amount.
+#[event]
+pub struct AuthorizationCaptured {
+    pub authorization: Pubkey,
+    pub payment: Pubkey,
+    pub authorized_amount: u64,
+    pub captured_amount: u64,
+    /// Returned to the customer
+    pub released_amount: u64,
+    pub fee_mode: FeeMode,
+    pub captured_at: i64,
+}
+
+#[event]
+pub struct AuthorizationReleased {
+    pub authorization: Pubkey,
+    pub customer: Pubkey,
+    pub amount: u64,
+    /// `true` if voided by the merchant, `false` if released after expiry
+    pub voided: bool,
+    pub released_at: i64,
+}
+
+// ─── Checkout sessions ───────────────────────────────────────────────
+
+#[event]
+pub struct CheckoutSessionCreated {
+    pub checkout_session: Pubkey,
+    pub session_id: String,
+    pub merchant: Pubkey,
+    pub creator: Pubkey,
+    pub amount: u64,
+    pub mint: Pubkey,
+    pub expires_at: i64,
+    pub max_uses: u16,
+    pub created_at: i64,
+}
+
+/// Followed by the `PaymentProcessed` for the payment it created.
+#[event]
+pub struct CheckoutSessionPaid {
+    pub checkout_session: Pubkey,
+    pub session_id: String,
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    pub amount: u64,
+    pub fee_mode: FeeMode,
+    pub use_count: u16,
+    pub max_uses: u16,
+}
+
+#[event]
+pub struct CheckoutSessionClosed {
+    pub checkout_session: Pubkey,
+    pub session_id: String,
+    pub use_count: u16,
+    pub closed_at: i64,
+}
+
+// ─── Subscriptions ───────────────────────────────────────────────────
+
+#[event]
+pub struct SubscriptionCreated {
+    pub subscription: Pubkey,
+    pub subscription_id: String,
+    pub customer: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub interval: i64,
+    pub max_cycles: u32,
+    pub first_due: i64,
+    /// Allowance the customer granted the delegate PDA, across all their subscriptions and intents
+    pub delegated_amount: u64,
+    pub created_at: i64,
+}
+
+/// Followed by the `PaymentProcessed` for the cycle's payment.
+#[event]
+pub struct SubscriptionCharged {
+    pub subscription: Pubkey,
+    pub payment: Pubkey,
+    pub cycle: u32,
+    pub amount: u64,
+    pub fee_mode: FeeMode,
+    pub next_due: i64,
+    pub completed: bool,
+    pub charged_at: i64,
+}
+
+#[event]
+pub struct SubscriptionCancelled {
+    pub subscription: Pubkey,
+    pub cancelled_by: Pubkey,
+    pub cycles_charged: u32,
+    pub cancelled_at: i64,
+}
+
+// ─── Attestations ────────────────────────────────────────────────────
+
+#[event]
+pub struct AttesterAdded {
+    pub attester: Pubkey,
+    pub added_at: i64,
+}
+
+#[event]
+pub struct AttesterRemoved {
+    pub attester: Pubkey,
+    pub removed_at: i64,
+}
+
+/// A passing attestation is followed by `EscrowReleased`.
+#[event]
+pub struct AttestationSubmitted {
+    pub attestation: Pubkey,
+    pub payment: Pubkey,
+    pub attester: Pubkey,
+    pub document_hash: [u8; 32],
+    pub passed: bool,
+    pub attested_at: i64,
+}
+
+#[event]
+pub struct AttestationRefunded {
+    /// `None` when refunded because no attestation arrived in time
+    pub attestation: Option<Pubkey>,
+    pub payment: Pubkey,
+    pub payment_id: String,
+    pub customer: Pubkey,
+    pub amount: u64,
+    pub refunded_at: i64,
+}
+
+// ─── Refund requests ─────────────────────────────────────────────────
+
+#[event]
+pub struct RefundRequestOpened {
+    pub refund_request: Pubkey,
+    pub payment: Pubkey,
+    pub merchant: Pubkey,
+    pub customer: Pubkey,
+    pub amount: u64,
+    pub reason: RefundReason,
+    pub evidence_hash: [u8; 32],
+    pub respond_by: i64,
+    pub expires_at: i64,
+    pub opened_at: i64,
+}
+
+#[event]
+pub struct RefundRequestApproved {
+    pub refund_request: Pubkey,
+    pub payment: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub approved_at: i64,
+}
+
+#[event]
+pub struct RefundRequestDenied {
+    pub refund_request: Pubkey,
+    pub payment: Pubkey,
+    pub merchant: Pubkey,
+    pub response_hash: [u8; 32],
+    pub denied_at: i64,
+}
+
+/// Platform ruling on an escalated request. An approval is paid out in the
+/// same instruction (see the accompanying `PaymentRefunded`).
+#[event]
+pub struct RefundRequestResolved {
+    pub refund_request: Pubkey,
+    pub payment: Pubkey,
+    pub authority: Pubkey,
+    pub approved: bool,
+    pub refund_amount: u64,
+    pub response_hash: [u8; 32],
+    pub resolved_at: i64,
+}
+
+#[event]
+pub struct RefundRequestExpired {
+    pub refund_request: Pubkey,
+    pub payment: Pubkey,
+    pub merchant: Pubkey,
+    pub expired_at: i64,
+}
+
+// ─── Invoices ────────────────────────────────────────────────────────
+
+#[event]
+pub struct InvoiceCreated {
+    pub invoice: Pubkey,
+    pub invoice_id: String,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub payer: Option<Pubkey>,
+    pub due_date: i64,
+    pub memo: String,
+    pub terms: Option<InvoiceTerms>,
+    pub reissued_from: Option<Pubkey>,
+    pub created_at: i64,
+}
+
+/// Followed by the `PaymentProcessed` for the payment that settled it.
+#[event]
+pub struct InvoicePaid {
+    pub invoice: Pubkey,
+    pub invoice_id: String,
+    pub merchant: Pubkey,
+    pub payment: Pubkey,
+    pub payer: Pubkey,
+    /// Amount actually paid: `base_amount - discount_amount + late_fee_amount`
+    pub amount: u64,
+    pub base_amount: u64,
+    pub discount_amount: u64,
+    pub late_fee_amount: u64,
+    pub fee_mode: FeeMode,
+    pub paid_at: i64,
+}
+
+#[event]
+pub struct InvoiceVoided {
+    pub invoice: Pubkey,
+    pub invoice_id: String,
+    pub merchant: Pubkey,
+    /// Set when the invoice was voided by `reissue_invoice`
+    pub reissued_as: Option<Pubkey>,
+    pub voided_at: i64,
+}
+
+// ─── Purchase orders ─────────────────────────────────────────────────
+
+#[event]
+pub struct PurchaseOrderCreated {
+    pub purchase_order: Pubkey,
+    pub order_id: String,
+    pub merchant: Pubkey,
+    pub buyer: Pubkey,
+    pub total_amount: u64,
+    pub milestone_count: u8,
+    pub fee_bps: u64,
+    pub deliver_by: i64,
+    pub created_at: i64,
+}
+
+/// Followed by a `PaymentProcessed` for the `Payment` the milestone created.
+#[event]
+pub struct MilestoneReleased {
+    pub purchase_order: Pubkey,
+    pub milestone_index: u8,
+    pub payment: Pubkey,
+    pub amount: u64,
+    pub approved_by_buyer: bool,
+    pub fee_mode: FeeMode,
+    pub released_amount: u64,
+    pub released_at: i64,
+}
+
+#[event]
+pub struct PurchaseOrderCancelled {
+    pub purchase_order: Pubkey,
+    pub buyer: Pubkey,
+    pub refund_amount: u64,
+    pub cancelled_milestones: u8,
+    pub cancelled_at: i64,
+}
+
+// ─── Disputes ────────────────────────────────────────────────────────
+
+#[event]
+pub struct DisputeOpened {
+    pub dispute: Pubkey,
+    pub payment: Pubkey,
+    pub merchant: Pubkey,
+    pub customer: Pubkey,
+    pub amount: u64,
+    pub locked_amount: u64,
+    pub customer_evidence_hash: [u8; 32],
+    pub opened_at: i64,
+}
+
+#[event]
+pub struct DisputeEvidenceSubmitted {
+    pub dispute: Pubkey,
+    pub payment: Pubkey,
+    pub merchant: Pubkey,
+    pub evidence_hash: [u8; 32],
+    pub submitted_at: i64,
+}
+
+/// `reserve_amount` and `fee_amount` are the legs of `refund_amount` paid from
+/// the merchant reserve and the treasury.
+#[event]
+pub struct DisputeResolved {
+    pub dispute: Pubkey,
+    pub payment: Pubkey,
+    pub arbiter: Pubkey,
+    pub ruling: DisputeRuling,
+    pub refund_amount: u64,
+    pub reserve_amount: u64,
+    pub fee_amount: u64,
+    pub unpaid_amount: u64,
+    pub resolved_at: i64,
+}
+
+// ─── Private receipts (MagicBlock PER) ───────────────────────────────
+
+#[event]
+pub struct PrivateReceiptIssued {
+    pub private_receipt: Pubkey,
+    pub payment_id: String,
+    pub customer: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub fee_amount: u64,
+    pub created_at: i64,
+}
+
+#[event]
+pub struct PrivatePaymentDelegated {
+    pub private_receipt: Pubkey,
+    pub payment_id: String,
+    pub delegated_at: i64,
+}
+
+/// Emitted inside the PER, so it is only visible to permissioned members.
+#[event]
+pub struct PrivatePaymentProcessed {
+    pub private_receipt: Pubkey,
+    pub payment_id: String,
+    pub amount: u64,
+    pub fee_amount: u64,
+    pub processed_at: i64,
+}
+
+#[event]
+pub struct PrivatePaymentSettled {
+    pub private_receipt: Pubkey,
+    pub payment_id: String,
+    pub customer: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub fee_amount: u64,
+    pub settled_at: i64,
+}
@@ -0,0 +1,217 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{close_account, transfer, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Attestation, Merchant, Payment, PaymentStatus, Platform};
+use crate::events::{AttestationRefunded, AttestationSubmitted, EscrowReleased};
+use crate::errors::PaymentError;
+use crate::instructions::escrow::*;
+
+/// An approved attester records its verdict on an attestation-gated escrow
+/// before its deadline. A pass releases the escrow to the merchant in the same
+/// instruction; a fail leaves the funds in escrow for `refund_failed_attestation`.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SubmitAttestation<'info> {
+    /// `release.signer` must be the same key
+    #[account(mut)]
+    pub attester: Signer<'info>,
+
+    #[account(
+        init,
+        payer = attester,
+        space = 8 + Attestation::INIT_SPACE,
+        seeds = [Attestation::SEED, release.payment_account.key().as_ref()],
+        bump,
+    )]
+    pub attestation: Account<'info, Attestation>,
+
+    pub release: ReleaseEscrow<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SubmitAttestation<'info> {
+    pub fn submit_attestation(
+        &mut self,
+        document_hash: [u8; 32],
+        passed: bool,
+        bumps: &SubmitAttestationBumps,
+    ) -> Result<(AttestationSubmitted, Option<EscrowReleased>)> {
+        require!(
+            self.release.platform_config.is_attester(&self.attester.key()),
+            PaymentError::AttesterNotApproved
+        );
+        require_keys_eq!(self.release.signer.key(), self.attester.key(), PaymentError::Unauthorized);
+        require!(self.release.payment_account.requires_attestation, PaymentError::AttestationNotRequired);
+
+        let now = Clock::get()?.unix_timestamp;
+        let deadline = self.release.payment_account.escrow_release_at.ok_or(PaymentError::PaymentNotEscrowed)?;
+        require!(now <= deadline, PaymentError::AttestationDeadlinePassed);
+        self.attestation.set_inner(Attestation {
+            payment: self.release.payment_account.key(),
+            attester: self.attester.key(),
+            document_hash,
+            passed,
+            attested_at: now,
+            bump: bumps.attestation,
+        });
+
+        msg!(
+            "Attestation on payment {}: {}",
+            self.release.payment_account.payment_id,
+            if passed { "passed" } else { "failed" }
+        );
+        let released = if passed {
+            Some(self.release.release(false)?)
+        } else {
+            None
+        };
+
+        Ok((
+            AttestationSubmitted {
+                attestation: self.attestation.key(),
+                payment: self.attestation.payment,
+                attester: self.attester.key(),
+                document_hash,
+                passed,
+                attested_at: now,
+            },
+            released,
+        ))
+    }
+}
+
+/// Return an attestation-gated escrow to the customer after a failed
+/// attestation, or once its deadline has passed without one. Permissionless;
+/// the funds can only go to the customer.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RefundFailedAttestation<'info> {
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), payment_account.address_seed().as_ref()],
+        bump = payment_account.bump,
+        constraint = payment_account.status == PaymentStatus::Escrowed @ PaymentError::PaymentNotEscrowed,
+        constraint = payment_account.requires_attestation @ PaymentError::AttestationNotRequired,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    /// Omit to refund an escrow nobody attested before its deadline
+    #[account(
+        seeds = [Attestation::SEED, payment_account.key().as_ref()],
+        bump = attestation.bump,
+        constraint = !attestation.passed @ PaymentError::AttestationPassed,
+    )]
+    pub attestation: Option<Account<'info, Attestation>>,
+
+    /// CHECK: The customer who paid; receives the funds and the escrow account's rent
+    #[account(
+        mut,
+        constraint = customer.key() == payment_account.customer @ PaymentError::Unauthorized
+    )]
+    pub customer: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [Payment::ESCROW_SEED, payment_account.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> RefundFailedAttestation<'info> {
+    pub fn refund_failed_attestation(&mut self) -> Result<AttestationRefunded> {
+        let now = Clock::get()?.unix_timestamp;
+        if self.attestation.is_none() {
+            let deadline = self.payment_account.escrow_release_at.ok_or(PaymentError::PaymentNotEscrowed)?;
+            require!(now > deadline, PaymentError::AttestationDeadlineNotReached);
+        }
+        let amount = self.escrow.amount;
+
+        let seeds = &[Platform::SEED, &[self.platform_config.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_accounts = Transfer {
+            from: self.escrow.to_account_info(),
+            to: self.customer_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_accounts, signer),
+            amount,
+        )?;
+
+        let close_accounts = CloseAccount {
+            account: self.escrow.to_account_info(),
+            destination: self.customer.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        close_account(CpiContext::new_with_signer(self.token_program.to_account_info(), close_accounts, signer))?;
+
+        // Nothing reached the merchant or the treasury, so this is a plain full refund
+        let payment = &mut self.payment_account;
+        payment.refunded_amount = payment.amount;
+        payment.status = PaymentStatus::Refunded;
+        payment.refunded_at = Some(now);
+
+        msg!(
+            "Payment {} refunded after {}: {} USDC",
+            payment.payment_id,
+            if self.attestation.is_some() { "failed attestation" } else { "attestation deadline" },
+            amount
+        );
+        Ok(AttestationRefunded {
+            attestation: self.attestation.as_ref().map(|attestation| attestation.key()),
+            payment: payment.key(),
+            payment_id: payment.payment_id.clone(),
+            customer: payment.customer,
+            amount,
+            refunded_at: now,
+        })
+    }
+}
+
+pub fn submit_attestation(ctx: Context<SubmitAttestation>, document_hash: [u8; 32], passed: bool) -> Result<()> {
+    let (submitted, released) = ctx.accounts.submit_attestation(document_hash, passed, &ctx.bumps)?;
+    emit_cpi!(submitted);
+    if let Some(released) = released {
+        emit_cpi!(released);
+    }
+    Ok(())
+}
+
+pub fn refund_failed_attestation(ctx: Context<RefundFailedAttestation>) -> Result<()> {
+    let event = ctx.accounts.refund_failed_attestation()?;
+    emit_cpi!(event);
+    Ok(())
+}
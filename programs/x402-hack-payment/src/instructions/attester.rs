@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Platform;
+use crate::events::{AttesterAdded, AttesterRemoved};
+use crate::errors::PaymentError;
+
+/// Add or remove an approved attester. Removal doesn't affect attestations
+/// already submitted.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ManageAttester<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub platform_config: Account<'info, Platform>,
+}
+
+impl<'info> ManageAttester<'info> {
+    pub fn add_attester(&mut self, attester: Pubkey) -> Result<AttesterAdded> {
+        let config = &mut self.platform_config;
+        require!(!config.is_attester(&attester), PaymentError::AttesterAlreadyRegistered);
+        require!(config.attesters.len() < Platform::MAX_ATTESTERS, PaymentError::AttesterRegistryFull);
+
+        config.attesters.push(attester);
+
+        msg!("Attester {} added", attester);
+        Ok(AttesterAdded {
+            attester,
+            added_at: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    pub fn remove_attester(&mut self, attester: Pubkey) -> Result<AttesterRemoved> {
+        let config = &mut self.platform_config;
+        let index = config
+            .attesters
+            .iter()
+            .position(|key| *key == attester)
+            .ok_or(PaymentError::AttesterNotFound)?;
+
+        config.attesters.swap_remove(index);
+
+        msg!("Attester {} removed", attester);
+        Ok(AttesterRemoved {
+            attester,
+            removed_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn add_attester(ctx: Context<ManageAttester>, attester: Pubkey) -> Result<()> {
+    let event = ctx.accounts.add_attester(attester)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn remove_attester(ctx: Context<ManageAttester>, attester: Pubkey) -> Result<()> {
+    let event = ctx.accounts.remove_attester(attester)?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -0,0 +1,551 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{close_account, transfer, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Authorization, AuthorizationStatus, Customer, FeeMode, Merchant, Payment, PaymentStatus, Platform};
+use crate::events::{AuthorizationCaptured, AuthorizationReleased, PaymentAuthorized, PaymentProcessed};
+use crate::errors::PaymentError;
+use crate::instructions::payment::payment_terms;
+
+/// Customer places a hold: the full amount moves into a platform-owned hold
+/// account until the merchant captures or voids it, or the platform
+/// authorization TTL runs out.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(authorization_id: String)]
+pub struct AuthorizePayment<'info> {
+    #[account(mut)]
+    pub customer: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        init,
+        payer = customer,
+        space = 8 + Authorization::INIT_SPACE,
+        seeds = [Authorization::SEED, merchant_account.key().as_ref(), Authorization::id_seed(&authorization_id).as_ref()],
+        bump,
+    )]
+    pub authorization: Box<Account<'info, Authorization>>,
+
+    #[account(
+        init_if_needed,
+        payer = customer,
+        space = 8 + Customer::INIT_SPACE,
+        seeds = [b"customer", customer.key().as_ref()],
+        bump,
+    )]
+    pub customer_account: Box<Account<'info, Customer>>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = customer,
+        seeds = [Authorization::HOLD_SEED, authorization.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub hold: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AuthorizePayment<'info> {
+    pub fn authorize_payment(
+        &mut self,
+        authorization_id: String,
+        amount: u64,
+        bumps: &AuthorizePaymentBumps,
+    ) -> Result<PaymentAuthorized> {
+        // Same checks as a one-off payment; the ID becomes the captured payment's ID
+        let (fee_bps, fee_source, _, _) =
+            payment_terms(&self.platform_config, &self.merchant_account, &authorization_id, amount, FeeMode::Inclusive)?;
+        let now = Clock::get()?.unix_timestamp;
+        let expires_at = now
+            .checked_add(self.platform_config.authorization_ttl)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.customer_account.init_if_new(self.customer.key(), now, bumps.customer_account);
+
+        let transfer_accounts = Transfer {
+            from: self.customer_usdc.to_account_info(),
+            to: self.hold.to_account_info(),
+            authority: self.customer.to_account_info(),
+        };
+        transfer(CpiContext::new(self.token_program.to_account_info(), transfer_accounts), amount)?;
+
+        self.authorization.set_inner(Authorization {
+            authorization_id: authorization_id.clone(),
+            customer: self.customer.key(),
+            merchant: self.merchant_account.key(),
+            amount,
+            captured_amount: 0,
+            fee_bps,
+            fee_source,
+            status: AuthorizationStatus::Authorized,
+            created_at: now,
+            expires_at,
+            resolved_at: None,
+            bump: bumps.authorization,
+        });
+
+        msg!(
+            "Payment {} authorized: {} USDC held for merchant {} until {}",
+            authorization_id,
+            amount,
+            self.merchant_account.merchant_id,
+            expires_at
+        );
+        Ok(PaymentAuthorized {
+            authorization: self.authorization.key(),
+            authorization_id,
+            customer: self.customer.key(),
+            merchant: self.merchant_account.key(),
+            hold: self.hold.key(),
+            amount,
+            fee_bps,
+            fee_source,
+            expires_at,
+            created_at: now,
+        })
+    }
+}
+
+/// Merchant charges up to the authorized amount. The captured amount goes
+/// through the usual fee split and is recorded as a `Payment`; the rest of
+/// the hold goes back to the customer.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CapturePayment<'info> {
+    /// Merchant authority; pays rent for the payment record
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [Authorization::SEED, merchant_account.key().as_ref(), Authorization::id_seed(&authorization.authorization_id).as_ref()],
+        bump = authorization.bump,
+        constraint = authorization.status == AuthorizationStatus::Authorized @ PaymentError::AuthorizationNotActive,
+    )]
+    pub authorization: Box<Account<'info, Authorization>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), Payment::derived_seed(&authorization.key(), 0).as_ref()],
+        bump,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    #[account(
+        mut,
+        seeds = [b"customer", authorization.customer.as_ref()],
+        bump = customer_account.bump,
+    )]
+    pub customer_account: Box<Account<'info, Customer>>,
+
+    /// CHECK: The customer who authorized; receives the remainder and the hold account's rent
+    #[account(
+        mut,
+        constraint = customer.key() == authorization.customer @ PaymentError::Unauthorized
+    )]
+    pub customer: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [Authorization::HOLD_SEED, authorization.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub hold: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = merchant_account.settlement_wallet,
+    )]
+    pub merchant_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [Platform::TREASURY_SEED],
+        bump = platform_config.treasury_bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub platform_treasury_usdc: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CapturePayment<'info> {
+    pub fn capture_payment(
+        &mut self,
+        amount: u64,
+        bumps: &CapturePaymentBumps,
+    ) -> Result<(AuthorizationCaptured, PaymentProcessed)> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(now < self.authorization.expires_at, PaymentError::AuthorizationExpired);
+        require!(
+            amount > 0 && amount <= self.authorization.amount,
+            PaymentError::InvalidCaptureAmount
+        );
+        require!(self.merchant_account.suspension_reason.is_none(), PaymentError::MerchantSuspended);
+
+        let fee_bps = self.authorization.fee_bps;
+        let fee_source = self.authorization.fee_source;
+        let fee = amount
+            .checked_mul(fee_bps)
+            .ok_or(PaymentError::CalculationError)?
+            .checked_div(10_000)
+            .ok_or(PaymentError::CalculationError)?;
+        let merchant_amount = amount
+            .checked_sub(fee)
+            .ok_or(PaymentError::CalculationError)?;
+        let remainder = self.hold.amount
+            .checked_sub(amount)
+            .ok_or(PaymentError::CalculationError)?;
+
+        let seeds = &[Platform::SEED, &[self.platform_config.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_to_merchant_accounts = Transfer {
+            from: self.hold.to_account_info(),
+            to: self.merchant_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_to_merchant_accounts, signer),
+            merchant_amount,
+        )?;
+
+        let transfer_fee_accounts = Transfer {
+            from: self.hold.to_account_info(),
+            to: self.platform_treasury_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_fee_accounts, signer),
+            fee,
+        )?;
+
+        if remainder > 0 {
+            let transfer_remainder_accounts = Transfer {
+                from: self.hold.to_account_info(),
+                to: self.customer_usdc.to_account_info(),
+                authority: self.platform_config.to_account_info(),
+            };
+            transfer(
+                CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_remainder_accounts, signer),
+                remainder,
+            )?;
+        }
+
+        let close_accounts = CloseAccount {
+            account: self.hold.to_account_info(),
+            destination: self.customer.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        close_account(CpiContext::new_with_signer(self.token_program.to_account_info(), close_accounts, signer))?;
+
+        self.platform_config.pending_fees = self.platform_config
+            .pending_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.customer_account.transaction_count = self.customer_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.customer_account.total_spent = self.customer_account
+            .total_spent
+            .checked_add(amount)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.merchant_account.transaction_count = self.merchant_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.volume = self.merchant_account
+            .volume
+            .checked_add(merchant_amount)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.total_fees = self.merchant_account
+            .total_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        let authorization = &mut self.authorization;
+        authorization.status = AuthorizationStatus::Captured;
+        authorization.captured_amount = amount;
+        authorization.resolved_at = Some(now);
+
+        // Record the capture as a regular payment, addressed from the authorization
+        let payment_id = authorization.authorization_id.clone();
+        let payment = &mut self.payment_account;
+        payment.payment_id = payment_id.clone();
+        payment.customer = authorization.customer;
+        payment.merchant = self.merchant_account.key();
+        payment.amount = amount;
+        payment.fee_amount = fee;
+        payment.fee_bps = fee_bps;
+        payment.fee_source = fee_source;
+        payment.fee_mode = FeeMode::Inclusive;
+        payment.merchant_amount = merchant_amount;
+        payment.refunded_amount = 0;
+        payment.status = PaymentStatus::Completed;
+        payment.created_at = now;
+        payment.refund_deadline = now
+            .checked_add(self.merchant_account.refund_window(self.platform_config.refund_window))
+            .ok_or(PaymentError::CalculationError)?;
+        payment.refunded_at = None;
+        payment.refund_request_open = false;
+        payment.dispute_open = false;
+        payment.escrow_release_at = None;
+        payment.requires_attestation = false;
+        payment.invoice_breakdown = None;
+        payment.sponsor_fee = 0;
+        payment.sponsor = None;
+        payment.parent = Some(authorization.key());
+        payment.parent_index = 0;
+        payment.dispute_shortfall = 0;
+        payment.bump = bumps.payment_account;
+
+        msg!(
+            "Payment {} captured: {} of {} USDC authorized, {} USDC fee to platform, {} USDC returned",
+            payment_id,
+            amount,
+            authorization.amount,
+            fee,
+            remainder
+        );
+        Ok((
+            AuthorizationCaptured {
+                authorization: authorization.key(),
+                payment: payment.key(),
+                authorized_amount: authorization.amount,
+                captured_amount: amount,
+                released_amount: remainder,
+                fee_mode: FeeMode::Inclusive,
+                captured_at: now,
+            },
+            PaymentProcessed {
+                payment: payment.key(),
+                payment_id,
+                merchant: self.merchant_account.key(),
+                customer: authorization.customer,
+                amount,
+                fee_amount: fee,
+                fee_bps,
+                fee_source,
+                fee_mode: FeeMode::Inclusive,
+                merchant_amount,
+                sponsor_fee: 0,
+                sponsor: None,
+                created_at: now,
+                refund_deadline: payment.refund_deadline,
+            },
+        ))
+    }
+}
+
+/// Return the whole hold to the customer. The merchant can void at any time
+/// before capture; anyone can release once the authorization has expired.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ReleaseAuthorization<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [Authorization::SEED, merchant_account.key().as_ref(), Authorization::id_seed(&authorization.authorization_id).as_ref()],
+        bump = authorization.bump,
+        constraint = authorization.status == AuthorizationStatus::Authorized @ PaymentError::AuthorizationNotActive,
+    )]
+    pub authorization: Box<Account<'info, Authorization>>,
+
+    /// CHECK: The customer who authorized; receives the funds and the hold account's rent
+    #[account(
+        mut,
+        constraint = customer.key() == authorization.customer @ PaymentError::Unauthorized
+    )]
+    pub customer: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [Authorization::HOLD_SEED, authorization.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub hold: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> ReleaseAuthorization<'info> {
+    pub fn void_authorization(&mut self) -> Result<AuthorizationReleased> {
+        require_keys_eq!(self.signer.key(), self.merchant_account.authority, PaymentError::Unauthorized);
+        self.release(true)
+    }
+
+    pub fn release_expired_authorization(&mut self) -> Result<AuthorizationReleased> {
+        require!(
+            Clock::get()?.unix_timestamp >= self.authorization.expires_at,
+            PaymentError::AuthorizationNotExpired
+        );
+        self.release(false)
+    }
+
+    fn release(&mut self, voided: bool) -> Result<AuthorizationReleased> {
+        let now = Clock::get()?.unix_timestamp;
+        let amount = self.hold.amount;
+
+        let seeds = &[Platform::SEED, &[self.platform_config.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_accounts = Transfer {
+            from: self.hold.to_account_info(),
+            to: self.customer_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_accounts, signer),
+            amount,
+        )?;
+
+        let close_accounts = CloseAccount {
+            account: self.hold.to_account_info(),
+            destination: self.customer.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        close_account(CpiContext::new_with_signer(self.token_program.to_account_info(), close_accounts, signer))?;
+
+        let authorization = &mut self.authorization;
+        authorization.status = if voided {
+            AuthorizationStatus::Voided
+        } else {
+            AuthorizationStatus::Expired
+        };
+        authorization.resolved_at = Some(now);
+
+        msg!(
+            "Authorization {} {}: {} USDC returned",
+            authorization.authorization_id,
+            if voided { "voided" } else { "expired" },
+            amount
+        );
+        Ok(AuthorizationReleased {
+            authorization: authorization.key(),
+            customer: authorization.customer,
+            amount,
+            voided,
+            released_at: now,
+        })
+    }
+}
+
+pub fn authorize_payment(ctx: Context<AuthorizePayment>, authorization_id: String, amount: u64) -> Result<()> {
+    let event = ctx.accounts.authorize_payment(authorization_id, amount, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn capture_payment(ctx: Context<CapturePayment>, amount: u64) -> Result<()> {
+    let (captured, processed) = ctx.accounts.capture_payment(amount, &ctx.bumps)?;
+    emit_cpi!(processed);
+    emit_cpi!(captured);
+    Ok(())
+}
+
+pub fn void_authorization(ctx: Context<ReleaseAuthorization>) -> Result<()> {
+    let event = ctx.accounts.void_authorization()?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn release_expired_authorization(ctx: Context<ReleaseAuthorization>) -> Result<()> {
+    let event = ctx.accounts.release_expired_authorization()?;
+    emit_cpi!(event);
+    Ok(())
+}
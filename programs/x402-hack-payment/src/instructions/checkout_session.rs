@@ -0,0 +1,208 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{CheckoutSession, FeeMode, Merchant, Platform};
+use crate::events::{CheckoutSessionClosed, CheckoutSessionCreated, CheckoutSessionPaid, PaymentProcessed};
+use crate::errors::PaymentError;
+use crate::instructions::payment::*;
+
+/// Merchant (or the platform on its behalf) creates a payment link with a
+/// fixed amount, an expiry and a use limit.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(session_id: String)]
+pub struct CreateCheckoutSession<'info> {
+    /// Merchant authority or platform authority
+    #[account(
+        mut,
+        constraint = creator.key() == merchant_account.authority
+            || creator.key() == platform_config.authority @ PaymentError::Unauthorized,
+    )]
+    pub creator: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Account<'info, Platform>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + CheckoutSession::INIT_SPACE,
+        seeds = [CheckoutSession::SEED, merchant_account.key().as_ref(), CheckoutSession::id_seed(&session_id).as_ref()],
+        bump,
+    )]
+    pub checkout_session: Account<'info, CheckoutSession>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateCheckoutSession<'info> {
+    pub fn create_checkout_session(
+        &mut self,
+        session_id: String,
+        amount: u64,
+        expires_at: i64,
+        max_uses: u16,
+        bumps: &CreateCheckoutSessionBumps,
+    ) -> Result<CheckoutSessionCreated> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            !session_id.is_empty() && session_id.len() <= CheckoutSession::MAX_ID_LEN,
+            PaymentError::InvalidPaymentId
+        );
+        require!(amount >= self.platform_config.min_payment_amount, PaymentError::PaymentBelowMinimum);
+        require!(expires_at > now, PaymentError::InvalidExpiry);
+        require!(max_uses > 0, PaymentError::InvalidMaxUses);
+
+        self.checkout_session.set_inner(CheckoutSession {
+            session_id: session_id.clone(),
+            merchant: self.merchant_account.key(),
+            creator: self.creator.key(),
+            amount,
+            mint: self.platform_config.usdc_mint,
+            expires_at,
+            max_uses,
+            use_count: 0,
+            created_at: now,
+            bump: bumps.checkout_session,
+        });
+
+        msg!("Checkout session {} created: {} USDC, {} use(s), expires {}", session_id, amount, max_uses, expires_at);
+        Ok(CheckoutSessionCreated {
+            checkout_session: self.checkout_session.key(),
+            session_id,
+            merchant: self.merchant_account.key(),
+            creator: self.creator.key(),
+            amount,
+            mint: self.platform_config.usdc_mint,
+            expires_at,
+            max_uses,
+            created_at: now,
+        })
+    }
+}
+
+/// Pay through a checkout session. Runs the regular `process_payment` path
+/// with the session's amount, charged as-is (`FeeMode::Inclusive`);
+/// `payment_id` is chosen by the client per use.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct PayCheckoutSession<'info> {
+    pub payment: ProcessPayment<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            CheckoutSession::SEED,
+            payment.merchant_account.key().as_ref(),
+            CheckoutSession::id_seed(&checkout_session.session_id).as_ref(),
+        ],
+        bump = checkout_session.bump,
+        constraint = checkout_session.mint == payment.usdc_mint.key() @ PaymentError::InvalidTokenMint,
+    )]
+    pub checkout_session: Account<'info, CheckoutSession>,
+}
+
+impl<'info> PayCheckoutSession<'info> {
+    pub fn pay_checkout_session(
+        &mut self,
+        payment_id: String,
+        bumps: &PayCheckoutSessionBumps,
+    ) -> Result<(CheckoutSessionPaid, PaymentProcessed)> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(!self.checkout_session.is_expired(now), PaymentError::CheckoutSessionExpired);
+        require!(!self.checkout_session.is_used_up(), PaymentError::CheckoutSessionUsedUp);
+
+        let amount = self.checkout_session.amount;
+        let processed = self.payment.process_payment(payment_id, amount, 0, FeeMode::Inclusive, &bumps.payment)?;
+
+        let session = &mut self.checkout_session;
+        session.use_count = session.use_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+
+        msg!("Checkout session {} used ({}/{})", session.session_id, session.use_count, session.max_uses);
+        Ok((
+            CheckoutSessionPaid {
+                checkout_session: session.key(),
+                session_id: session.session_id.clone(),
+                payment: processed.payment,
+                payer: processed.customer,
+                amount,
+                fee_mode: processed.fee_mode,
+                use_count: session.use_count,
+                max_uses: session.max_uses,
+            },
+            processed,
+        ))
+    }
+}
+
+/// Close a session that has expired or run out of uses. Permissionless;
+/// the rent always goes back to the creator.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseCheckoutSession<'info> {
+    #[account(
+        mut,
+        close = creator,
+        seeds = [CheckoutSession::SEED, checkout_session.merchant.as_ref(), CheckoutSession::id_seed(&checkout_session.session_id).as_ref()],
+        bump = checkout_session.bump,
+    )]
+    pub checkout_session: Account<'info, CheckoutSession>,
+
+    /// CHECK: Receives the rent; must be the session's creator
+    #[account(
+        mut,
+        constraint = creator.key() == checkout_session.creator @ PaymentError::Unauthorized
+    )]
+    pub creator: UncheckedAccount<'info>,
+}
+
+impl<'info> CloseCheckoutSession<'info> {
+    pub fn close_checkout_session(&mut self) -> Result<CheckoutSessionClosed> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &self.checkout_session;
+        require!(session.is_expired(now) || session.is_used_up(), PaymentError::CheckoutSessionActive);
+
+        msg!("Checkout session {} closed after {} use(s)", session.session_id, session.use_count);
+        Ok(CheckoutSessionClosed {
+            checkout_session: session.key(),
+            session_id: session.session_id.clone(),
+            use_count: session.use_count,
+            closed_at: now,
+        })
+    }
+}
+
+pub fn create_checkout_session(
+    ctx: Context<CreateCheckoutSession>,
+    session_id: String,
+    amount: u64,
+    expires_at: i64,
+    max_uses: u16,
+) -> Result<()> {
+    let event = ctx.accounts.create_checkout_session(session_id, amount, expires_at, max_uses, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn pay_checkout_session(ctx: Context<PayCheckoutSession>, payment_id: String) -> Result<()> {
+    let (paid, processed) = ctx.accounts.pay_checkout_session(payment_id, &ctx.bumps)?;
+    emit_cpi!(processed);
+    emit_cpi!(paid);
+    Ok(())
+}
+
+pub fn close_checkout_session(ctx: Context<CloseCheckoutSession>) -> Result<()> {
+    let event = ctx.accounts.close_checkout_session()?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -1,15 +1,18 @@
 use anchor_lang::prelude::*;
 
 use crate::state::platform::Platform;
+use crate::events::FeesClaimed;
 use crate::errors::PaymentError;
 use anchor_spl::token::{Transfer, transfer};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct ClaimPlatformFees<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
     
     #[account(
+        mut,
         seeds = [b"platform_config"],
         bump = platform_config.bump,
         has_one = authority @ PaymentError::Unauthorized,
@@ -39,9 +42,14 @@ pub struct ClaimPlatformFees<'info> {
 }
 
 impl<'info> ClaimPlatformFees<'info> {
-    pub fn claim_platform_fees(&mut self) -> Result<()> {
-        let treasury_balance = self.platform_treasury_usdc.amount;
-        require!(treasury_balance > 0, PaymentError::NoFeesToClaim);
+    pub fn claim_platform_fees(&mut self) -> Result<FeesClaimed> {
+        // Only accrued fee revenue is claimable; the rest of the treasury is payout escrow
+        let accrued_fees = self.platform_config.accrued_fees;
+        require!(accrued_fees > 0, PaymentError::NoFeesToClaim);
+        require!(
+            self.platform_treasury_usdc.amount >= accrued_fees,
+            PaymentError::InsufficientTreasuryBalance
+        );
 
         let cpi_accounts = Transfer {
             from: self.platform_treasury_usdc.to_account_info(),
@@ -55,13 +63,21 @@ impl<'info> ClaimPlatformFees<'info> {
 
         let cpi_program = self.token_program.to_account_info();
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        transfer(cpi_ctx, treasury_balance)?;
-        msg!("Claimed {} USDC fees to {}", treasury_balance, self.authority.key());
+        transfer(cpi_ctx, accrued_fees)?;
+        self.platform_config.accrued_fees = 0;
+        msg!("Claimed {} USDC fees to {}", accrued_fees, self.authority.key());
 
-        Ok(())
+        Ok(FeesClaimed {
+            authority: self.authority.key(),
+            destination: self.authority_usdc.key(),
+            amount: accrued_fees,
+            claimed_at: Clock::get()?.unix_timestamp,
+        })
     }
 }
 
 pub fn handler(ctx: Context<ClaimPlatformFees>) -> Result<()> {
-    ctx.accounts.claim_platform_fees()
+    let event = ctx.accounts.claim_platform_fees()?;
+    emit_cpi!(event);
+    Ok(())
 }
\ No newline at end of file
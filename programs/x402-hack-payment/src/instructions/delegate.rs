@@ -0,0 +1,99 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_spl::token::{approve, Approve, Mint, Token, TokenAccount};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::Platform;
+use crate::events::DelegateApproved;
+use crate::errors::PaymentError;
+
+/// Approve `Platform::DELEGATE_SEED` for `amount` on top of whatever it can
+/// already spend. Approving replaces a token account's allowance, so the
+/// remainder held for subscriptions and signed intents is carried over.
+/// Returns the new total allowance.
+pub(crate) fn approve_additional<'info>(
+    token_program: &Program<'info, Token>,
+    customer_usdc: &Account<'info, TokenAccount>,
+    delegate: &AccountInfo<'info>,
+    customer: &Signer<'info>,
+    amount: u64,
+) -> Result<u64> {
+    let existing = match customer_usdc.delegate {
+        COption::Some(current) if current == delegate.key() => customer_usdc.delegated_amount,
+        _ => 0,
+    };
+    let delegated_amount = existing
+        .checked_add(amount)
+        .ok_or(PaymentError::CalculationError)?;
+    let approve_accounts = Approve {
+        to: customer_usdc.to_account_info(),
+        delegate: delegate.clone(),
+        authority: customer.to_account_info(),
+    };
+    approve(CpiContext::new(token_program.to_account_info(), approve_accounts), delegated_amount)?;
+    Ok(delegated_amount)
+}
+
+/// Customer raises the program delegate's allowance on their USDC account,
+/// e.g. to fund signed payment intents without touching subscription allowance.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ApproveDelegate<'info> {
+    pub customer: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA approved as delegate on `customer_usdc`; holds no data
+    #[account(
+        seeds = [Platform::DELEGATE_SEED],
+        bump,
+    )]
+    pub delegate: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> ApproveDelegate<'info> {
+    pub fn approve_delegate(&mut self, amount: u64) -> Result<DelegateApproved> {
+        require!(amount > 0, PaymentError::InvalidAmount);
+        let delegated_amount = approve_additional(
+            &self.token_program,
+            &self.customer_usdc,
+            &self.delegate.to_account_info(),
+            &self.customer,
+            amount,
+        )?;
+
+        msg!("Delegate allowance for {} raised by {} to {} USDC", self.customer.key(), amount, delegated_amount);
+        Ok(DelegateApproved {
+            customer: self.customer.key(),
+            customer_usdc: self.customer_usdc.key(),
+            added_amount: amount,
+            delegated_amount,
+            approved_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn approve_delegate(ctx: Context<ApproveDelegate>, amount: u64) -> Result<()> {
+    let event = ctx.accounts.approve_delegate(amount)?;
+    emit_cpi!(event);
+    Ok(())
+}
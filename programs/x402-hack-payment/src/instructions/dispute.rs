@@ -0,0 +1,367 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, TokenAccount, Token};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Dispute, DisputeRuling, DisputeStatus, Merchant, Payment, PaymentStatus, Platform};
+use crate::events::{DisputeEvidenceSubmitted, DisputeOpened, DisputeResolved, PaymentRefunded};
+use crate::errors::PaymentError;
+use crate::instructions::refund::{record_refund, transfer_platform_refund};
+
+/// Customer disputes a completed payment. One dispute per payment; it must be
+/// opened inside the platform's dispute window and blocks finalization until
+/// the arbiter rules. The merchant's share of the refundable amount is locked
+/// in the merchant reserve, as far as the unlocked balance covers it.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    #[account(mut)]
+    pub customer: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        mut,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), payment_account.address_seed().as_ref()],
+        bump = payment_account.bump,
+        has_one = customer @ PaymentError::Unauthorized,
+        constraint = payment_account.status == PaymentStatus::Completed @ PaymentError::InvalidPaymentStatus,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    /// Absent if the merchant never funded a reserve
+    #[account(
+        seeds = [Merchant::RESERVE_SEED, merchant_account.key().as_ref()],
+        bump,
+        token::mint = platform_config.usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub merchant_reserve: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = customer,
+        space = 8 + Dispute::INIT_SPACE,
+        seeds = [Dispute::SEED, payment_account.key().as_ref()],
+        bump,
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> OpenDispute<'info> {
+    pub fn open_dispute(&mut self, evidence_hash: [u8; 32], bumps: &OpenDisputeBumps) -> Result<DisputeOpened> {
+        let now = Clock::get()?.unix_timestamp;
+        let deadline = self.platform_config
+            .dispute_deadline(self.payment_account.created_at)
+            .ok_or(PaymentError::CalculationError)?;
+        require!(now <= deadline, PaymentError::DisputeWindowClosed);
+
+        // Hold back the merchant's share of a full refund so a ruling can be paid
+        let (merchant_share, _) = self.payment_account
+            .refund_split(self.payment_account.refundable_amount())
+            .ok_or(PaymentError::CalculationError)?;
+        let unlocked = self.merchant_reserve
+            .as_ref()
+            .map_or(0, |reserve| reserve.amount.saturating_sub(self.merchant_account.reserve_locked));
+        let locked_amount = merchant_share.min(unlocked);
+        self.merchant_account.reserve_locked = self.merchant_account
+            .reserve_locked
+            .checked_add(locked_amount)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.dispute.set_inner(Dispute {
+            payment: self.payment_account.key(),
+            customer: self.customer.key(),
+            merchant: self.merchant_account.key(),
+            amount: self.payment_account.amount,
+            locked_amount,
+            customer_evidence_hash: evidence_hash,
+            merchant_evidence_hash: None,
+            status: DisputeStatus::Open,
+            ruling: None,
+            refund_amount: 0,
+            unpaid_amount: 0,
+            opened_at: now,
+            merchant_responded_at: None,
+            resolved_at: None,
+            bump: bumps.dispute,
+        });
+        self.payment_account.dispute_open = true;
+        self.merchant_account.open_disputes = self.merchant_account
+            .open_disputes
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+
+        msg!(
+            "Dispute opened on payment {}; {} USDC of reserve locked",
+            self.payment_account.payment_id,
+            locked_amount
+        );
+        Ok(DisputeOpened {
+            dispute: self.dispute.key(),
+            payment: self.payment_account.key(),
+            merchant: self.merchant_account.key(),
+            customer: self.customer.key(),
+            amount: self.payment_account.amount,
+            locked_amount,
+            customer_evidence_hash: evidence_hash,
+            opened_at: now,
+        })
+    }
+}
+
+/// Merchant answers a dispute with its own evidence. Can be resubmitted until
+/// the arbiter rules; the latest hash wins.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RespondToDispute<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [Dispute::SEED, dispute.payment.as_ref()],
+        bump = dispute.bump,
+        constraint = dispute.merchant == merchant_account.key() @ PaymentError::Unauthorized,
+        constraint = dispute.status == DisputeStatus::Open @ PaymentError::DisputeNotOpen,
+    )]
+    pub dispute: Account<'info, Dispute>,
+}
+
+impl<'info> RespondToDispute<'info> {
+    pub fn respond_to_dispute(&mut self, evidence_hash: [u8; 32]) -> Result<DisputeEvidenceSubmitted> {
+        let now = Clock::get()?.unix_timestamp;
+        let dispute = &mut self.dispute;
+        dispute.merchant_evidence_hash = Some(evidence_hash);
+        dispute.merchant_responded_at = Some(now);
+
+        msg!("Merchant {} responded to dispute {}", self.merchant_account.merchant_id, dispute.key());
+        Ok(DisputeEvidenceSubmitted {
+            dispute: dispute.key(),
+            payment: dispute.payment,
+            merchant: dispute.merchant,
+            evidence_hash,
+            submitted_at: now,
+        })
+    }
+}
+
+/// Arbiter rules on a dispute. A refund ruling pulls the merchant's share from
+/// the merchant reserve and the fee share from the treasury, so it settles
+/// whether or not the merchant cooperates. Anything the merchant already
+/// refunded directly is not paid twice. The ruling is always recorded: if the
+/// reserve can't cover the merchant's share, the rest is left as
+/// `unpaid_amount` on the dispute and the lock is released either way.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ResolveDispute<'info> {
+    pub arbiter: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+        has_one = arbiter @ PaymentError::Unauthorized,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), payment_account.address_seed().as_ref()],
+        bump = payment_account.bump,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    #[account(
+        mut,
+        seeds = [Dispute::SEED, payment_account.key().as_ref()],
+        bump = dispute.bump,
+        constraint = dispute.status == DisputeStatus::Open @ PaymentError::DisputeNotOpen,
+    )]
+    pub dispute: Box<Account<'info, Dispute>>,
+
+    /// CHECK: The original customer who made the payment
+    #[account(
+        constraint = customer.key() == payment_account.customer @ PaymentError::RefundNotAuthorized
+    )]
+    pub customer: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::RESERVE_SEED, merchant_account.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub merchant_reserve: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [Platform::TREASURY_SEED],
+        bump = platform_config.treasury_bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub platform_treasury_usdc: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> ResolveDispute<'info> {
+    /// `refund_amount` is only read for `PartialRefund`.
+    pub fn resolve_dispute(
+        &mut self,
+        ruling: DisputeRuling,
+        refund_amount: u64,
+    ) -> Result<(DisputeResolved, Option<PaymentRefunded>)> {
+        let now = Clock::get()?.unix_timestamp;
+        let refundable = self.payment_account.refundable_amount();
+        let refund_amount = match ruling {
+            DisputeRuling::FullRefund => refundable,
+            DisputeRuling::PartialRefund => {
+                require!(
+                    refund_amount > 0 && refund_amount <= refundable,
+                    PaymentError::InvalidRefundAmount
+                );
+                refund_amount
+            }
+            DisputeRuling::Rejected => 0,
+        };
+
+        // This dispute's lock plus whatever no other dispute has claimed
+        self.merchant_account.reserve_locked = self.merchant_account
+            .reserve_locked
+            .saturating_sub(self.dispute.locked_amount);
+        let available = self.merchant_reserve
+            .as_ref()
+            .map_or(0, |reserve| reserve.amount.saturating_sub(self.merchant_account.reserve_locked));
+
+        let mut refunded = None;
+        let (mut reserve_amount, mut fee_amount, mut unpaid_amount) = (0, 0, 0);
+        if refund_amount > 0 {
+            let (merchant_share, fee_share) = self.payment_account
+                .refund_split(refund_amount)
+                .ok_or(PaymentError::CalculationError)?;
+            reserve_amount = merchant_share.min(available);
+            fee_amount = fee_share;
+            unpaid_amount = merchant_share - reserve_amount;
+
+            transfer_platform_refund(
+                &self.token_program,
+                &self.platform_config,
+                self.merchant_reserve.as_ref(),
+                &self.platform_treasury_usdc,
+                &self.customer_usdc,
+                (reserve_amount, fee_amount),
+            )?;
+
+            // Only what reached the customer is booked as refunded. The shortfall
+            // stays refundable on the payment for the merchant to pay through
+            // `refund_payment`, and keeps it from being finalized until then
+            let paid_amount = reserve_amount
+                .checked_add(fee_amount)
+                .ok_or(PaymentError::CalculationError)?;
+            if paid_amount > 0 {
+                refunded = Some(record_refund(
+                    &mut self.platform_config,
+                    &mut self.merchant_account,
+                    &mut self.payment_account,
+                    paid_amount,
+                    (reserve_amount, fee_amount),
+                    now,
+                )?);
+            }
+            self.payment_account.dispute_shortfall = unpaid_amount;
+        }
+
+        let dispute = &mut self.dispute;
+        dispute.status = DisputeStatus::Resolved;
+        dispute.ruling = Some(ruling);
+        dispute.refund_amount = refund_amount;
+        dispute.unpaid_amount = unpaid_amount;
+        dispute.resolved_at = Some(now);
+        self.payment_account.dispute_open = false;
+        self.merchant_account.open_disputes = self.merchant_account.open_disputes.saturating_sub(1);
+
+        msg!(
+            "Dispute on payment {} resolved: {:?}, {} USDC awarded, {} USDC still owed by the merchant",
+            self.payment_account.payment_id,
+            ruling,
+            refund_amount,
+            unpaid_amount
+        );
+        Ok((
+            DisputeResolved {
+                dispute: dispute.key(),
+                payment: dispute.payment,
+                arbiter: self.arbiter.key(),
+                ruling,
+                refund_amount,
+                reserve_amount,
+                fee_amount,
+                unpaid_amount,
+                resolved_at: now,
+            },
+            refunded,
+        ))
+    }
+}
+
+pub fn open_dispute(ctx: Context<OpenDispute>, evidence_hash: [u8; 32]) -> Result<()> {
+    let event = ctx.accounts.open_dispute(evidence_hash, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn respond_to_dispute(ctx: Context<RespondToDispute>, evidence_hash: [u8; 32]) -> Result<()> {
+    let event = ctx.accounts.respond_to_dispute(evidence_hash)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn resolve_dispute(ctx: Context<ResolveDispute>, ruling: DisputeRuling, refund_amount: u64) -> Result<()> {
+    let (resolved, refunded) = ctx.accounts.resolve_dispute(ruling, refund_amount)?;
+    if let Some(refunded) = refunded {
+        emit_cpi!(refunded);
+    }
+    emit_cpi!(resolved);
+    Ok(())
+}
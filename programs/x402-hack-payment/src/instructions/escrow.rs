@@ -0,0 +1,466 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{close_account, transfer, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Customer, Merchant, Payment, PaymentStatus, Platform};
+use crate::events::{EscrowCancelled, EscrowReleased, PaymentEscrowed};
+use crate::errors::PaymentError;
+use crate::instructions::payment::payment_terms;
+
+/// Take a payment into escrow instead of paying the merchant straight away.
+/// The full amount sits in a platform-owned USDC account tied to the payment;
+/// the fee is recorded now but only moves to the treasury on release.
+/// Also backs `process_attested_payment`, whose escrow only releases on a
+/// passing attestation; if none arrives before the escrow timeout, anyone can
+/// return it to the customer.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(payment_id: String)]
+pub struct ProcessEscrowPayment<'info> {
+    /// Pays rent for the new accounts. May be a relayer so the customer
+    /// doesn't need SOL; can be the same key as `customer`.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Owner of `customer_usdc`; signs the USDC transfer
+    pub customer: Signer<'info>,
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), Payment::id_seed(&payment_id).as_ref()],
+        bump,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Customer::INIT_SPACE,
+        seeds = [b"customer", customer.key().as_ref()],
+        bump,
+    )]
+    pub customer_account: Box<Account<'info, Customer>>,
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [Payment::ESCROW_SEED, payment_account.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ProcessEscrowPayment<'info> {
+    pub fn process_escrow_payment(
+        &mut self,
+        payment_id: String,
+        amount: u64,
+        requires_attestation: bool,
+        bumps: &ProcessEscrowPaymentBumps,
+    ) -> Result<PaymentEscrowed> {
+        let fee_mode = self.merchant_account.fee_mode;
+        let (fee_bps, fee_source, fee, merchant_amount) =
+            payment_terms(&self.platform_config, &self.merchant_account, &payment_id, amount, fee_mode)?;
+        // The escrow holds the fee too, so the customer deposits the total charged
+        let charged = merchant_amount
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+        let now = Clock::get()?.unix_timestamp;
+        // Auto-release time, or for attested escrow the attestation deadline
+        let release_at = now
+            .checked_add(self.platform_config.escrow_timeout)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.customer_account.init_if_new(self.customer.key(), now, bumps.customer_account);
+
+        let transfer_accounts = Transfer {
+            from: self.customer_usdc.to_account_info(),
+            to: self.escrow.to_account_info(),
+            authority: self.customer.to_account_info(),
+        };
+        transfer(CpiContext::new(self.token_program.to_account_info(), transfer_accounts), charged)?;
+
+        self.customer_account.transaction_count = self.customer_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.customer_account.total_spent = self.customer_account
+            .total_spent
+            .checked_add(charged)
+            .ok_or(PaymentError::CalculationError)?;
+
+        // Merchant stats and the refund window wait for release
+        let payment = &mut self.payment_account;
+        payment.payment_id = payment_id.clone();
+        payment.customer = self.customer.key();
+        payment.merchant = self.merchant_account.key();
+        payment.amount = charged;
+        payment.fee_amount = fee;
+        payment.fee_bps = fee_bps;
+        payment.fee_source = fee_source;
+        payment.fee_mode = fee_mode;
+        payment.merchant_amount = merchant_amount;
+        payment.refunded_amount = 0;
+        payment.status = PaymentStatus::Escrowed;
+        payment.created_at = now;
+        payment.refund_deadline = 0;
+        payment.refunded_at = None;
+        payment.refund_request_open = false;
+        payment.dispute_open = false;
+        payment.escrow_release_at = Some(release_at);
+        payment.requires_attestation = requires_attestation;
+        payment.invoice_breakdown = None;
+        payment.sponsor_fee = 0;
+        payment.sponsor = None;
+        payment.parent = None;
+        payment.parent_index = 0;
+        payment.dispute_shortfall = 0;
+        payment.bump = bumps.payment_account;
+
+        msg!(
+            "Payment {} escrowed: {} USDC for merchant {} (release at {}, attestation {})",
+            payment_id,
+            charged,
+            self.merchant_account.merchant_id,
+            release_at,
+            requires_attestation
+        );
+        Ok(PaymentEscrowed {
+            payment: payment.key(),
+            payment_id,
+            merchant: self.merchant_account.key(),
+            customer: self.customer.key(),
+            escrow: self.escrow.key(),
+            amount: charged,
+            fee_amount: fee,
+            fee_bps,
+            fee_source,
+            fee_mode,
+            merchant_amount,
+            release_at,
+            requires_attestation,
+            created_at: now,
+        })
+    }
+}
+
+/// Pay an escrowed payment out: merchant share to the settlement wallet, fee
+/// to the treasury. The customer can release at any time by confirming
+/// delivery; anyone can release once the escrow timeout has passed.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ReleaseEscrow<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), payment_account.address_seed().as_ref()],
+        bump = payment_account.bump,
+        constraint = payment_account.status == PaymentStatus::Escrowed @ PaymentError::PaymentNotEscrowed,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    /// CHECK: The customer who paid; receives the escrow account's rent
+    #[account(
+        mut,
+        constraint = customer.key() == payment_account.customer @ PaymentError::Unauthorized
+    )]
+    pub customer: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [Payment::ESCROW_SEED, payment_account.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = merchant_account.settlement_wallet,
+    )]
+    pub merchant_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [Platform::TREASURY_SEED],
+        bump = platform_config.treasury_bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub platform_treasury_usdc: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> ReleaseEscrow<'info> {
+    pub fn confirm_delivery(&mut self) -> Result<EscrowReleased> {
+        require!(!self.payment_account.requires_attestation, PaymentError::AttestationRequired);
+        require_keys_eq!(self.signer.key(), self.payment_account.customer, PaymentError::Unauthorized);
+        self.release(true)
+    }
+
+    pub fn release_escrow(&mut self) -> Result<EscrowReleased> {
+        require!(!self.payment_account.requires_attestation, PaymentError::AttestationRequired);
+        let release_at = self.payment_account.escrow_release_at.ok_or(PaymentError::PaymentNotEscrowed)?;
+        require!(Clock::get()?.unix_timestamp >= release_at, PaymentError::EscrowTimeoutNotReached);
+        self.release(false)
+    }
+
+    pub(crate) fn release(&mut self, confirmed_by_customer: bool) -> Result<EscrowReleased> {
+        let now = Clock::get()?.unix_timestamp;
+        let merchant_amount = self.payment_account.merchant_amount;
+        let fee = self.payment_account.fee_amount;
+
+        let seeds = &[Platform::SEED, &[self.platform_config.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_to_merchant_accounts = Transfer {
+            from: self.escrow.to_account_info(),
+            to: self.merchant_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_to_merchant_accounts, signer),
+            merchant_amount,
+        )?;
+
+        // The fee is only taken now that the merchant is paid
+        let transfer_fee_accounts = Transfer {
+            from: self.escrow.to_account_info(),
+            to: self.platform_treasury_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_fee_accounts, signer),
+            fee,
+        )?;
+
+        let close_accounts = CloseAccount {
+            account: self.escrow.to_account_info(),
+            destination: self.customer.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        close_account(CpiContext::new_with_signer(self.token_program.to_account_info(), close_accounts, signer))?;
+
+        self.platform_config.pending_fees = self.platform_config
+            .pending_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.merchant_account.transaction_count = self.merchant_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.volume = self.merchant_account
+            .volume
+            .checked_add(merchant_amount)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.total_fees = self.merchant_account
+            .total_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        // The refund window starts from release, not from when the customer paid
+        let payment = &mut self.payment_account;
+        payment.status = PaymentStatus::Completed;
+        payment.refund_deadline = now
+            .checked_add(self.merchant_account.refund_window(self.platform_config.refund_window))
+            .ok_or(PaymentError::CalculationError)?;
+
+        msg!(
+            "Payment {} released from escrow: {} USDC to merchant {}, {} USDC fee to platform",
+            payment.payment_id,
+            merchant_amount,
+            self.merchant_account.merchant_id,
+            fee
+        );
+        Ok(EscrowReleased {
+            payment: payment.key(),
+            payment_id: payment.payment_id.clone(),
+            merchant: self.merchant_account.key(),
+            released_by: self.signer.key(),
+            confirmed_by_customer,
+            merchant_amount,
+            fee_amount: fee,
+            refund_deadline: payment.refund_deadline,
+            released_at: now,
+        })
+    }
+}
+
+/// Merchant calls off an escrowed order; the full amount goes back to the
+/// customer and no fee is charged.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelEscrow<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), payment_account.address_seed().as_ref()],
+        bump = payment_account.bump,
+        constraint = payment_account.status == PaymentStatus::Escrowed @ PaymentError::PaymentNotEscrowed,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    /// CHECK: The customer who paid; receives the funds and the escrow account's rent
+    #[account(
+        mut,
+        constraint = customer.key() == payment_account.customer @ PaymentError::Unauthorized
+    )]
+    pub customer: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [Payment::ESCROW_SEED, payment_account.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> CancelEscrow<'info> {
+    pub fn cancel_escrow(&mut self) -> Result<EscrowCancelled> {
+        let now = Clock::get()?.unix_timestamp;
+        let amount = self.escrow.amount;
+
+        let seeds = &[Platform::SEED, &[self.platform_config.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_accounts = Transfer {
+            from: self.escrow.to_account_info(),
+            to: self.customer_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_accounts, signer),
+            amount,
+        )?;
+
+        let close_accounts = CloseAccount {
+            account: self.escrow.to_account_info(),
+            destination: self.customer.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        close_account(CpiContext::new_with_signer(self.token_program.to_account_info(), close_accounts, signer))?;
+
+        let payment = &mut self.payment_account;
+        payment.status = PaymentStatus::Cancelled;
+
+        msg!("Escrowed payment {} cancelled: {} USDC returned", payment.payment_id, amount);
+        Ok(EscrowCancelled {
+            payment: payment.key(),
+            payment_id: payment.payment_id.clone(),
+            merchant: self.merchant_account.key(),
+            customer: payment.customer,
+            amount,
+            cancelled_at: now,
+        })
+    }
+}
+
+pub fn process_escrow_payment(ctx: Context<ProcessEscrowPayment>, payment_id: String, amount: u64) -> Result<()> {
+    let event = ctx.accounts.process_escrow_payment(payment_id, amount, false, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn process_attested_payment(ctx: Context<ProcessEscrowPayment>, payment_id: String, amount: u64) -> Result<()> {
+    let event = ctx.accounts.process_escrow_payment(payment_id, amount, true, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn confirm_delivery(ctx: Context<ReleaseEscrow>) -> Result<()> {
+    let event = ctx.accounts.confirm_delivery()?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn release_escrow(ctx: Context<ReleaseEscrow>) -> Result<()> {
+    let event = ctx.accounts.release_escrow()?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn cancel_escrow(ctx: Context<CancelEscrow>) -> Result<()> {
+    let event = ctx.accounts.cancel_escrow()?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use anchor_lang::prelude::*;
+
+use crate::state::merchant::Merchant;
+use crate::state::payment::{Payment, PaymentStatus};
+use crate::state::platform::Platform;
+use crate::events::PaymentFinalized;
+use crate::errors::PaymentError;
+
+/// Lock in a payment once its refund and dispute windows have closed.
+/// Permissionless — after both deadlines nothing can change the outcome
+/// anyway. The fee the platform kept becomes claimable here.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FinalizePayment<'info> {
+    #[account(
+        mut,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), payment_account.address_seed().as_ref()],
+        bump = payment_account.bump,
+        constraint = payment_account.status != PaymentStatus::Refunded @ PaymentError::PaymentAlreadyRefunded,
+        constraint = payment_account.status != PaymentStatus::Finalized @ PaymentError::PaymentAlreadyFinalized,
+        constraint = payment_account.status != PaymentStatus::Escrowed @ PaymentError::PaymentInEscrow,
+        constraint = payment_account.status != PaymentStatus::Cancelled @ PaymentError::PaymentCancelled,
+    )]
+    pub payment_account: Account<'info, Payment>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Account<'info, Platform>,
+}
+
+impl<'info> FinalizePayment<'info> {
+    pub fn finalize_payment(&mut self) -> Result<PaymentFinalized> {
+        let now = Clock::get()?.unix_timestamp;
+        let payment = &mut self.payment_account;
+        require!(now > payment.refund_deadline, PaymentError::RefundWindowOpen);
+        let dispute_deadline = self.platform_config
+            .dispute_deadline(payment.created_at)
+            .ok_or(PaymentError::CalculationError)?;
+        require!(now > dispute_deadline, PaymentError::DisputeWindowOpen);
+        require!(!payment.refund_request_open, PaymentError::RefundRequestPending);
+        require!(!payment.dispute_open, PaymentError::DisputePending);
+        require!(payment.dispute_shortfall == 0, PaymentError::DisputeShortfallUnpaid);
+
+        payment.status = PaymentStatus::Finalized;
+
+        // The fee can no longer be refunded, so it moves to claimable revenue
+        let retained_fee = payment.retained_fee().ok_or(PaymentError::CalculationError)?;
+        self.platform_config.pending_fees = self.platform_config
+            .pending_fees
+            .checked_sub(retained_fee)
+            .ok_or(PaymentError::InsufficientPendingFees)?;
+        self.platform_config.accrued_fees = self.platform_config
+            .accrued_fees
+            .checked_add(retained_fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        msg!(
+            "Payment {} finalized: {} USDC, {} refunded",
+            payment.payment_id,
+            payment.amount,
+            payment.refunded_amount
+        );
+        Ok(PaymentFinalized {
+            payment: payment.key(),
+            payment_id: payment.payment_id.clone(),
+            merchant: payment.merchant,
+            customer: payment.customer,
+            amount: payment.amount,
+            fee_amount: payment.fee_amount,
+            refunded_amount: payment.refunded_amount,
+            finalized_at: now,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<FinalizePayment>) -> Result<()> {
+    let event = ctx.accounts.finalize_payment()?;
+    emit_cpi!(event);
+    Ok(())
+}
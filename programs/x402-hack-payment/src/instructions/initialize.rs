@@ -1,9 +1,11 @@
 use anchor_lang::prelude::*;
 
-use crate::state::{Merchant, Platform};
+use crate::state::{FeeMode, Merchant, Platform};
+use crate::events::MerchantInitialized;
 use crate::errors::PaymentError;
 // Remove unused: use crate::state::merchant;
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(merchant_id: String)]
 pub struct InitializeMerchant<'info> {
@@ -13,7 +15,7 @@ pub struct InitializeMerchant<'info> {
         init,
         payer = payer,
         space = 8 + Merchant::INIT_SPACE,
-        seeds = [b"merchant", merchant_id.as_bytes()],
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_id).as_ref()],
         bump,
     )]
     pub merchant_account: Account<'info, Merchant>,
@@ -31,31 +33,47 @@ impl<'info> InitializeMerchant<'info> {
     pub fn initialize_merchant(
         &mut self, 
         merchant_id: String, 
-        fee_bps: u16,  // Changed to u16
         bumps: &InitializeMerchantBumps
-    ) -> Result<()> {
+    ) -> Result<MerchantInitialized> {
         let clock = Clock::get()?.unix_timestamp;
         
         // Validations
-        require!(!merchant_id.is_empty() && merchant_id.len() <= 64, PaymentError::InvalidMerchantId);
+        require!(!merchant_id.is_empty() && merchant_id.len() <= Merchant::MAX_ID_LEN, PaymentError::InvalidMerchantId);
         require!(self.platform_config.is_active, PaymentError::PlatformInactive);
-        require!(fee_bps <= 1000, PaymentError::FeeTooHigh); // Max 10% merchant fee
         
         let merchant_account = &mut self.merchant_account;
         
         merchant_account.merchant_id = merchant_id;
         merchant_account.authority = self.payer.key(); // Add authority field
         merchant_account.settlement_wallet = self.settlement_wallet.key();
-        merchant_account.fee = fee_bps; 
+        merchant_account.pending_settlement_wallet = None;
+        merchant_account.settlement_wallet_effective_at = 0;
+        merchant_account.fee = None; // Fee overrides are set by the platform authority
+        merchant_account.refund_window = None;
         merchant_account.volume = 0;
         merchant_account.total_fees = 0; // Track total fees collected
         merchant_account.transaction_count = 0;
         merchant_account.created_at = clock;
         merchant_account.is_active = true;
+        merchant_account.suspension_reason = None;
+        merchant_account.suspended_at = None;
+        merchant_account.open_disputes = 0;
+        merchant_account.fee_mode = FeeMode::Inclusive;
+        merchant_account.pending_authority = None;
+        merchant_account.authority_effective_at = 0;
+        merchant_account.previous_authority = None;
+        merchant_account.authority_changed_at = 0;
+        merchant_account.reserve_locked = 0;
         merchant_account.bump = bumps.merchant_account;
         
-        msg!("Merchant {} initialized with {} bps fee", merchant_account.merchant_id, fee_bps);
-        Ok(())
+        msg!("Merchant {} initialized", merchant_account.merchant_id);
+        Ok(MerchantInitialized {
+            merchant: merchant_account.key(),
+            merchant_id: merchant_account.merchant_id.clone(),
+            authority: merchant_account.authority,
+            settlement_wallet: merchant_account.settlement_wallet,
+            created_at: clock,
+        })
     }
 }
 
@@ -63,7 +81,13 @@ impl<'info> InitializeMerchant<'info> {
 pub fn handler(
     ctx: Context<InitializeMerchant>, 
     merchant_id: String, 
-    fee_bps: u16  // Changed to u16
+    fee_bps: u16,
 ) -> Result<()> {
-    ctx.accounts.initialize_merchant(merchant_id, fee_bps, &ctx.bumps)
+    // Kept in the signature so existing clients keep working for one more release
+    if fee_bps != 0 {
+        msg!("fee_bps {} ignored: merchant fees are set by the platform", fee_bps);
+    }
+    let event = ctx.accounts.initialize_merchant(merchant_id, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,520 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, TokenAccount, Token, Transfer, transfer};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Customer, FeeMode, Invoice, InvoiceStatus, InvoiceTerms, LateFee, Merchant, Payment, PaymentStatus, Platform};
+use crate::events::{InvoiceCreated, InvoicePaid, InvoiceVoided, PaymentProcessed};
+use crate::errors::PaymentError;
+use crate::instructions::payment::payment_terms;
+
+/// Billing terms shared by `create_invoice` and `reissue_invoice`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InvoiceArgs {
+    pub amount: u64,
+    /// Only this wallet may pay; `None` lets anyone pay
+    pub payer: Option<Pubkey>,
+    pub due_date: i64,
+    pub memo: String,
+    /// Early-payment discount and late fee; `None` bills the flat amount
+    pub terms: Option<InvoiceTerms>,
+}
+
+/// The invoice must stay payable while the discount applies, so the discounted
+/// amount can't fall below the platform minimum.
+fn validate_args(invoice_id: &str, args: &InvoiceArgs, now: i64, platform_config: &Platform) -> Result<()> {
+    require!(!invoice_id.is_empty() && invoice_id.len() <= Invoice::MAX_ID_LEN, PaymentError::InvalidPaymentId);
+    require!(args.amount > 0, PaymentError::InvalidAmount);
+    require!(args.due_date > now, PaymentError::InvalidDueDate);
+    require!(args.memo.len() <= Invoice::MAX_MEMO_LEN, PaymentError::InvalidMemo);
+    require!(args.amount >= platform_config.min_payment_amount, PaymentError::PaymentBelowMinimum);
+    if let Some(terms) = &args.terms {
+        require!(terms.discount_bps < 10_000, PaymentError::InvalidInvoiceTerms);
+        require!(
+            terms.discount_bps == 0 || terms.discount_deadline <= args.due_date,
+            PaymentError::InvalidInvoiceTerms
+        );
+        let discount = terms.discount_on(args.amount).ok_or(PaymentError::CalculationError)?;
+        require!(
+            args.amount - discount >= platform_config.min_payment_amount,
+            PaymentError::PaymentBelowMinimum
+        );
+        match terms.late_fee {
+            Some(LateFee::Bps(bps)) => require!(bps > 0 && bps <= 10_000, PaymentError::InvalidInvoiceTerms),
+            Some(LateFee::Flat(fee)) => require!(fee > 0, PaymentError::InvalidInvoiceTerms),
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Merchant bills a customer. The invoice ID is recorded as the payment ID once paid.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(invoice_id: String)]
+pub struct CreateInvoice<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Invoice::INIT_SPACE,
+        seeds = [Invoice::SEED, merchant_account.key().as_ref(), Invoice::id_seed(&invoice_id).as_ref()],
+        bump,
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateInvoice<'info> {
+    pub fn create_invoice(&mut self, invoice_id: String, args: InvoiceArgs, bumps: &CreateInvoiceBumps) -> Result<InvoiceCreated> {
+        let now = Clock::get()?.unix_timestamp;
+        validate_args(&invoice_id, &args, now, &self.platform_config)?;
+        let InvoiceArgs { amount, payer, due_date, memo, terms } = args;
+
+        self.invoice.set_inner(Invoice {
+            invoice_id: invoice_id.clone(),
+            merchant: self.merchant_account.key(),
+            amount,
+            payer,
+            due_date,
+            memo: memo.clone(),
+            terms,
+            status: InvoiceStatus::Open,
+            payment: None,
+            reissued_from: None,
+            reissued_as: None,
+            created_at: now,
+            paid_at: None,
+            bump: bumps.invoice,
+        });
+
+        msg!("Invoice {} issued for {} USDC, due {}", invoice_id, amount, due_date);
+        Ok(InvoiceCreated {
+            invoice: self.invoice.key(),
+            invoice_id,
+            merchant: self.merchant_account.key(),
+            amount,
+            payer,
+            due_date,
+            memo,
+            terms,
+            reissued_from: None,
+            created_at: now,
+        })
+    }
+}
+
+/// Pay an open invoice in full with the amount owed at the current time,
+/// after checking the designated payer and due date. The payment is addressed
+/// from the invoice rather than the invoice ID, so it can't be created ahead of
+/// time through `process_payment`; the discount/late fee breakdown is recorded on it.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(invoice_id: String)]
+pub struct PayInvoice<'info> {
+    /// Pays rent for the new accounts; can be the same key as `customer`
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// Owner of `customer_usdc`; signs the USDC transfers
+    pub customer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [Invoice::SEED, merchant_account.key().as_ref(), Invoice::id_seed(&invoice_id).as_ref()],
+        bump = invoice.bump,
+        constraint = invoice.status == InvoiceStatus::Open @ PaymentError::InvoiceNotOpen,
+    )]
+    pub invoice: Box<Account<'info, Invoice>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), Payment::derived_seed(&invoice.key(), 0).as_ref()],
+        bump,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Customer::INIT_SPACE,
+        seeds = [b"customer", customer.key().as_ref()],
+        bump,
+    )]
+    pub customer_account: Box<Account<'info, Customer>>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = merchant_account.settlement_wallet,
+    )]
+    pub merchant_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [Platform::TREASURY_SEED],
+        bump = platform_config.treasury_bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub platform_treasury_usdc: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> PayInvoice<'info> {
+    pub fn pay_invoice(&mut self, invoice_id: String, bumps: &PayInvoiceBumps) -> Result<(InvoicePaid, PaymentProcessed)> {
+        let now = Clock::get()?.unix_timestamp;
+        let payer = self.customer.key();
+        // Overdue invoices stay payable only if the merchant set a late fee
+        let charges_late_fee = self.invoice.terms.is_some_and(|terms| terms.late_fee.is_some());
+        require!(now <= self.invoice.due_date || charges_late_fee, PaymentError::InvoiceOverdue);
+        if let Some(designated) = self.invoice.payer {
+            require_keys_eq!(payer, designated, PaymentError::InvoicePayerMismatch);
+        }
+
+        let breakdown = self.invoice.amount_due(now).ok_or(PaymentError::CalculationError)?;
+        let amount = breakdown.total().ok_or(PaymentError::CalculationError)?;
+        // The customer pays exactly what the invoice says
+        let fee_mode = FeeMode::Inclusive;
+        let (fee_bps, fee_source, fee, merchant_amount) =
+            payment_terms(&self.platform_config, &self.merchant_account, &invoice_id, amount, fee_mode)?;
+        let charged = merchant_amount
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.customer_account.init_if_new(payer, now, bumps.customer_account);
+
+        let transfer_to_merchant_accounts = Transfer {
+            from: self.customer_usdc.to_account_info(),
+            to: self.merchant_usdc.to_account_info(),
+            authority: self.customer.to_account_info(),
+        };
+        transfer(
+            CpiContext::new(self.token_program.to_account_info(), transfer_to_merchant_accounts),
+            merchant_amount,
+        )?;
+
+        let transfer_fee_accounts = Transfer {
+            from: self.customer_usdc.to_account_info(),
+            to: self.platform_treasury_usdc.to_account_info(),
+            authority: self.customer.to_account_info(),
+        };
+        transfer(
+            CpiContext::new(self.token_program.to_account_info(), transfer_fee_accounts),
+            fee,
+        )?;
+
+        self.platform_config.pending_fees = self.platform_config
+            .pending_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.customer_account.transaction_count = self.customer_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.customer_account.total_spent = self.customer_account
+            .total_spent
+            .checked_add(charged)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.merchant_account.transaction_count = self.merchant_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.volume = self.merchant_account
+            .volume
+            .checked_add(merchant_amount)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.total_fees = self.merchant_account
+            .total_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        let payment = &mut self.payment_account;
+        payment.payment_id = invoice_id.clone();
+        payment.customer = payer;
+        payment.merchant = self.merchant_account.key();
+        payment.amount = charged;
+        payment.fee_amount = fee;
+        payment.fee_bps = fee_bps;
+        payment.fee_source = fee_source;
+        payment.fee_mode = fee_mode;
+        payment.merchant_amount = merchant_amount;
+        payment.refunded_amount = 0;
+        payment.status = PaymentStatus::Completed;
+        payment.created_at = now;
+        payment.refund_deadline = now
+            .checked_add(self.merchant_account.refund_window(self.platform_config.refund_window))
+            .ok_or(PaymentError::CalculationError)?;
+        payment.refunded_at = None;
+        payment.refund_request_open = false;
+        payment.dispute_open = false;
+        payment.escrow_release_at = None;
+        payment.requires_attestation = false;
+        payment.invoice_breakdown = Some(breakdown);
+        payment.sponsor_fee = 0;
+        payment.sponsor = None;
+        payment.parent = Some(self.invoice.key());
+        payment.parent_index = 0;
+        payment.dispute_shortfall = 0;
+        payment.bump = bumps.payment_account;
+        let payment_key = payment.key();
+        let refund_deadline = payment.refund_deadline;
+
+        let invoice = &mut self.invoice;
+        invoice.status = InvoiceStatus::Paid;
+        invoice.payment = Some(payment_key);
+        invoice.paid_at = Some(now);
+
+        msg!(
+            "Invoice {} paid by {}: {} USDC to merchant {}, {} USDC fee to platform",
+            invoice_id,
+            payer,
+            merchant_amount,
+            self.merchant_account.merchant_id,
+            fee
+        );
+        Ok((
+            InvoicePaid {
+                invoice: invoice.key(),
+                invoice_id: invoice_id.clone(),
+                merchant: invoice.merchant,
+                payment: payment_key,
+                payer,
+                amount,
+                base_amount: breakdown.base_amount,
+                discount_amount: breakdown.discount_amount,
+                late_fee_amount: breakdown.late_fee_amount,
+                fee_mode,
+                paid_at: now,
+            },
+            PaymentProcessed {
+                payment: payment_key,
+                payment_id: invoice_id,
+                merchant: self.merchant_account.key(),
+                customer: payer,
+                amount: charged,
+                fee_amount: fee,
+                fee_bps,
+                fee_source,
+                fee_mode,
+                merchant_amount,
+                sponsor_fee: 0,
+                sponsor: None,
+                created_at: now,
+                refund_deadline,
+            },
+        ))
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct VoidInvoice<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [Invoice::SEED, merchant_account.key().as_ref(), Invoice::id_seed(&invoice.invoice_id).as_ref()],
+        bump = invoice.bump,
+        constraint = invoice.status == InvoiceStatus::Open @ PaymentError::InvoiceNotOpen,
+    )]
+    pub invoice: Account<'info, Invoice>,
+}
+
+impl<'info> VoidInvoice<'info> {
+    pub fn void_invoice(&mut self) -> Result<InvoiceVoided> {
+        let invoice = &mut self.invoice;
+        invoice.status = InvoiceStatus::Voided;
+
+        msg!("Invoice {} voided", invoice.invoice_id);
+        Ok(InvoiceVoided {
+            invoice: invoice.key(),
+            invoice_id: invoice.invoice_id.clone(),
+            merchant: invoice.merchant,
+            reissued_as: None,
+            voided_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+/// Replace an open invoice with corrected terms. The old invoice is voided
+/// and both sides point at each other so the history stays traceable.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(new_invoice_id: String)]
+pub struct ReissueInvoice<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [Invoice::SEED, merchant_account.key().as_ref(), Invoice::id_seed(&invoice.invoice_id).as_ref()],
+        bump = invoice.bump,
+        constraint = invoice.status == InvoiceStatus::Open @ PaymentError::InvoiceNotOpen,
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Invoice::INIT_SPACE,
+        seeds = [Invoice::SEED, merchant_account.key().as_ref(), Invoice::id_seed(&new_invoice_id).as_ref()],
+        bump,
+    )]
+    pub new_invoice: Account<'info, Invoice>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ReissueInvoice<'info> {
+    pub fn reissue_invoice(
+        &mut self,
+        new_invoice_id: String,
+        args: InvoiceArgs,
+        bumps: &ReissueInvoiceBumps,
+    ) -> Result<(InvoiceVoided, InvoiceCreated)> {
+        let now = Clock::get()?.unix_timestamp;
+        validate_args(&new_invoice_id, &args, now, &self.platform_config)?;
+        let InvoiceArgs { amount, payer, due_date, memo, terms } = args;
+
+        let old_key = self.invoice.key();
+        let new_key = self.new_invoice.key();
+        self.new_invoice.set_inner(Invoice {
+            invoice_id: new_invoice_id.clone(),
+            merchant: self.merchant_account.key(),
+            amount,
+            payer,
+            due_date,
+            memo: memo.clone(),
+            terms,
+            status: InvoiceStatus::Open,
+            payment: None,
+            reissued_from: Some(old_key),
+            reissued_as: None,
+            created_at: now,
+            paid_at: None,
+            bump: bumps.new_invoice,
+        });
+
+        let invoice = &mut self.invoice;
+        invoice.status = InvoiceStatus::Voided;
+        invoice.reissued_as = Some(new_key);
+
+        msg!("Invoice {} reissued as {}", invoice.invoice_id, new_invoice_id);
+        Ok((
+            InvoiceVoided {
+                invoice: old_key,
+                invoice_id: invoice.invoice_id.clone(),
+                merchant: invoice.merchant,
+                reissued_as: Some(new_key),
+                voided_at: now,
+            },
+            InvoiceCreated {
+                invoice: new_key,
+                invoice_id: new_invoice_id,
+                merchant: self.merchant_account.key(),
+                amount,
+                payer,
+                due_date,
+                memo,
+                terms,
+                reissued_from: Some(old_key),
+                created_at: now,
+            },
+        ))
+    }
+}
+
+pub fn create_invoice(ctx: Context<CreateInvoice>, invoice_id: String, args: InvoiceArgs) -> Result<()> {
+    let event = ctx.accounts.create_invoice(invoice_id, args, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn pay_invoice(ctx: Context<PayInvoice>, invoice_id: String) -> Result<()> {
+    let (paid, processed) = ctx.accounts.pay_invoice(invoice_id, &ctx.bumps)?;
+    emit_cpi!(processed);
+    emit_cpi!(paid);
+    Ok(())
+}
+
+pub fn void_invoice(ctx: Context<VoidInvoice>) -> Result<()> {
+    let event = ctx.accounts.void_invoice()?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn reissue_invoice(ctx: Context<ReissueInvoice>, new_invoice_id: String, args: InvoiceArgs) -> Result<()> {
+    let (voided, created) = ctx.accounts.reissue_invoice(new_invoice_id, args, &ctx.bumps)?;
+    emit_cpi!(voided);
+    emit_cpi!(created);
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Merchant, Platform};
+use crate::events::MerchantFeeUpdated;
+use crate::errors::PaymentError;
+
+/// Set or clear a merchant's negotiated fee override.
+/// Only the platform authority can change pricing.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetMerchantFee<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub platform_config: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+}
+
+impl<'info> SetMerchantFee<'info> {
+    pub fn set_merchant_fee(&mut self, fee_bps: Option<u16>) -> Result<MerchantFeeUpdated> {
+        if let Some(fee_bps) = fee_bps {
+            require!(fee_bps as u64 <= Platform::MAX_FEE_BPS, PaymentError::FeeTooHigh);
+        }
+
+        let old_fee = self.merchant_account.fee;
+        self.merchant_account.fee = fee_bps;
+
+        msg!(
+            "Merchant {} fee override changed from {:?} to {:?} bps",
+            self.merchant_account.merchant_id,
+            old_fee,
+            fee_bps
+        );
+        Ok(MerchantFeeUpdated {
+            merchant: self.merchant_account.key(),
+            old_fee_bps: old_fee,
+            new_fee_bps: fee_bps,
+            updated_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<SetMerchantFee>, fee_bps: Option<u16>) -> Result<()> {
+    let event = ctx.accounts.set_merchant_fee(fee_bps)?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token::TokenAccount;
+
+use crate::state::customer::Customer;
+use crate::state::platform::{LegacyPlatform, Platform};
+use crate::events::{CustomerMigrated, PlatformConfigMigrated};
+use crate::errors::PaymentError;
+
+/// Grow an account in place to `new_len`, topping up rent from `payer` first.
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    new_len: usize,
+) -> Result<()> {
+    let shortfall = Rent::get()?.minimum_balance(new_len).saturating_sub(account.lamports());
+    if shortfall > 0 {
+        transfer(
+            CpiContext::new(system_program.clone(), Transfer { from: payer.clone(), to: account.clone() }),
+            shortfall,
+        )?;
+    }
+    account.resize(new_len)?;
+    Ok(())
+}
+
+/// Extend the deployed platform config to the current layout. Existing fields
+/// keep their bytes; appended fields get the same defaults `set_platform_config` uses.
+/// The treasury has held both fees and payout escrow, so the authority passes
+/// the audited fee total; the rest of the balance stays payout escrow. Those
+/// fees are held as `legacy_fees` and released payment by payment as
+/// `migrate_payment` moves the pre-upgrade payments.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigratePlatformConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Still in the legacy layout, so it can't be loaded as `Platform`; checked in the handler
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump,
+        owner = crate::ID @ PaymentError::InvalidLegacyAccount,
+    )]
+    pub platform_config: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [Platform::TREASURY_SEED],
+        bump,
+        token::authority = platform_config,
+    )]
+    pub platform_treasury_usdc: Account<'info, TokenAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigratePlatformConfig<'info> {
+    pub fn migrate_platform_config(&mut self, legacy_fees: u64) -> Result<PlatformConfigMigrated> {
+        let legacy = {
+            let data = self.platform_config.try_borrow_data()?;
+            require!(
+                data.len() == LegacyPlatform::LEN && data.starts_with(Platform::DISCRIMINATOR),
+                PaymentError::InvalidLegacyAccount
+            );
+            LegacyPlatform::deserialize(&mut &data[Platform::DISCRIMINATOR.len()..])
+                .map_err(|_| error!(PaymentError::InvalidLegacyAccount))?
+        };
+        require_keys_eq!(legacy.authority, self.authority.key(), PaymentError::Unauthorized);
+        require_keys_eq!(legacy.treasury, self.platform_treasury_usdc.key(), PaymentError::InvalidLegacyAccount);
+
+        // Kept out of `accrued_fees` so claims can't spend the fee of a payment
+        // that may still be refunded before it has been migrated
+        let treasury_balance = self.platform_treasury_usdc.amount;
+        require!(legacy_fees <= treasury_balance, PaymentError::InsufficientTreasuryBalance);
+
+        let platform = Platform {
+            authority: legacy.authority,
+            treasury: legacy.treasury,
+            usdc_mint: legacy.usdc_mint,
+            min_payment_amount: legacy.min_payment_amount,
+            fee_bps: legacy.fee_bps,
+            is_active: legacy.is_active,
+            bump: legacy.bump,
+            treasury_bump: legacy.treasury_bump,
+            pending_authority: None,
+            accrued_fees: 0,
+            settlement_wallet_delay: Platform::DEFAULT_SETTLEMENT_WALLET_DELAY,
+            refund_window: Platform::DEFAULT_REFUND_WINDOW,
+            arbiter: legacy.authority,
+            escrow_timeout: Platform::DEFAULT_ESCROW_TIMEOUT,
+            attesters: Vec::new(),
+            authorization_ttl: Platform::DEFAULT_AUTHORIZATION_TTL,
+            max_sponsor_fee: 0,
+            sponsors: Vec::new(),
+            pending_fees: 0,
+            dispute_window: Platform::DEFAULT_DISPUTE_WINDOW,
+            legacy_fees,
+        };
+
+        let account = self.platform_config.to_account_info();
+        grow_account(
+            &account,
+            &self.authority.to_account_info(),
+            &self.system_program.to_account_info(),
+            8 + Platform::INIT_SPACE,
+        )?;
+        platform.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+
+        let escrow_balance = platform.escrow_balance(treasury_balance);
+        msg!(
+            "Platform config migrated; {} USDC of legacy fees and {} USDC of payout escrow carried over",
+            legacy_fees,
+            escrow_balance
+        );
+        Ok(PlatformConfigMigrated {
+            platform_config: account.key(),
+            authority: legacy.authority,
+            legacy_fees,
+            escrow_balance,
+            migrated_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn migrate_platform_config(ctx: Context<MigratePlatformConfig>, legacy_fees: u64) -> Result<()> {
+    let event = ctx.accounts.migrate_platform_config(legacy_fees)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+/// Extend a customer account created before `next_intent_nonce` existed.
+/// Permissionless — the payer covers the extra rent. The appended nonce is
+/// zero-filled, which is where a customer's intent nonces start.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateCustomer<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: The customer wallet the account belongs to
+    pub customer: UncheckedAccount<'info>,
+
+    /// CHECK: Still in the legacy layout, so it can't be loaded as `Customer`; checked in the handler
+    #[account(
+        mut,
+        seeds = [b"customer", customer.key().as_ref()],
+        bump,
+        owner = crate::ID @ PaymentError::InvalidLegacyAccount,
+    )]
+    pub customer_account: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateCustomer<'info> {
+    pub fn migrate_customer(&mut self) -> Result<CustomerMigrated> {
+        {
+            let data = self.customer_account.try_borrow_data()?;
+            require!(
+                data.len() == Customer::LEGACY_LEN && data.starts_with(Customer::DISCRIMINATOR),
+                PaymentError::InvalidLegacyAccount
+            );
+        }
+
+        let account = self.customer_account.to_account_info();
+        grow_account(
+            &account,
+            &self.payer.to_account_info(),
+            &self.system_program.to_account_info(),
+            8 + Customer::INIT_SPACE,
+        )?;
+        account.try_borrow_mut_data()?[Customer::LEGACY_LEN..].fill(0);
+
+        msg!("Customer account for {} migrated", self.customer.key());
+        Ok(CustomerMigrated {
+            customer_account: account.key(),
+            customer: self.customer.key(),
+            migrated_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn migrate_customer(ctx: Context<MigrateCustomer>) -> Result<()> {
+    let event = ctx.accounts.migrate_customer()?;
+    emit_cpi!(event);
+    Ok(())
+}
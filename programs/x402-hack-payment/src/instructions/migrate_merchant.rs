@@ -0,0 +1,123 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::state::merchant::{LegacyMerchant, Merchant};
+use crate::state::payment::FeeMode;
+use crate::events::MerchantMigrated;
+use crate::errors::PaymentError;
+
+/// Move a merchant from its raw-ID address (`[b"merchant", merchant_id]`) to
+/// the hashed-ID address every instruction now expects. Permissionless — the
+/// record is copied as-is and the old account's rent goes back to the
+/// merchant authority who paid for it. Run this before `migrate_payment`
+/// for any of the merchant's payments.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(merchant_id: String)]
+pub struct MigrateMerchant<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Owner is checked here; discriminator, layout and PDA are checked in the handler
+    #[account(mut, owner = crate::ID @ PaymentError::InvalidLegacyAccount)]
+    pub legacy_merchant: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Merchant::INIT_SPACE,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_id).as_ref()],
+        bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    /// CHECK: Must match the authority recorded on the legacy merchant; receives its rent
+    #[account(mut)]
+    pub authority: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateMerchant<'info> {
+    pub fn migrate_merchant(&mut self, merchant_id: String, bumps: &MigrateMerchantBumps) -> Result<MerchantMigrated> {
+        let legacy = self.read_legacy_merchant()?;
+
+        require!(legacy.merchant_id == merchant_id, PaymentError::InvalidLegacyAccount);
+        require_keys_eq!(legacy.authority, self.authority.key(), PaymentError::InvalidLegacyAccount);
+
+        // Legacy IDs were used as raw seeds, so anything over 32 bytes can't be one
+        let legacy_address = Pubkey::create_program_address(
+            &[Merchant::SEED, merchant_id.as_bytes(), &[legacy.bump]],
+            &crate::ID,
+        )
+        .map_err(|_| error!(PaymentError::InvalidLegacyAccount))?;
+        require_keys_eq!(legacy_address, self.legacy_merchant.key(), PaymentError::InvalidLegacyAccount);
+
+        let merchant = &mut self.merchant_account;
+        merchant.merchant_id = legacy.merchant_id;
+        merchant.authority = legacy.authority;
+        merchant.settlement_wallet = legacy.settlement_wallet;
+        // The registration fee was never charged; the platform rate still applies
+        merchant.fee = None;
+        merchant.volume = legacy.volume;
+        merchant.total_fees = legacy.total_fees;
+        merchant.transaction_count = legacy.transaction_count;
+        merchant.created_at = legacy.created_at;
+        merchant.is_active = legacy.is_active;
+        merchant.bump = bumps.merchant_account;
+        merchant.pending_settlement_wallet = None;
+        merchant.settlement_wallet_effective_at = 0;
+        merchant.refund_window = None;
+        merchant.suspension_reason = None;
+        merchant.suspended_at = None;
+        merchant.open_disputes = 0;
+        merchant.fee_mode = FeeMode::Inclusive;
+        merchant.pending_authority = None;
+        merchant.authority_effective_at = 0;
+        merchant.previous_authority = None;
+        merchant.authority_changed_at = 0;
+        merchant.reserve_locked = 0;
+
+        self.close_legacy_merchant()?;
+
+        msg!("Merchant {} migrated to {}", merchant_id, self.merchant_account.key());
+        Ok(MerchantMigrated {
+            merchant: self.merchant_account.key(),
+            legacy_merchant: self.legacy_merchant.key(),
+            merchant_id,
+            migrated_at: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    fn read_legacy_merchant(&self) -> Result<LegacyMerchant> {
+        let data = self.legacy_merchant.try_borrow_data()?;
+        require!(
+            data.len() > Merchant::DISCRIMINATOR.len() && data.starts_with(Merchant::DISCRIMINATOR),
+            PaymentError::InvalidLegacyAccount
+        );
+        LegacyMerchant::deserialize(&mut &data[Merchant::DISCRIMINATOR.len()..])
+            .map_err(|_| error!(PaymentError::InvalidLegacyAccount))
+    }
+
+    fn close_legacy_merchant(&self) -> Result<()> {
+        let legacy = self.legacy_merchant.to_account_info();
+        let authority = self.authority.to_account_info();
+
+        let lamports = legacy.lamports();
+        **authority.try_borrow_mut_lamports()? = authority
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(PaymentError::CalculationError)?;
+        **legacy.try_borrow_mut_lamports()? = 0;
+
+        legacy.assign(&system_program::ID);
+        legacy.resize(0)?;
+        Ok(())
+    }
+}
+
+pub fn handler(ctx: Context<MigrateMerchant>, merchant_id: String) -> Result<()> {
+    let event = ctx.accounts.migrate_merchant(merchant_id, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::state::merchant::Merchant;
+use crate::state::platform::Platform;
+use crate::state::payment::{FeeMode, FeeSource, LegacyPayment, Payment, PaymentStatus};
+use crate::events::PaymentMigrated;
+use crate::errors::PaymentError;
+
+/// Move a payment from the old global namespace (`[b"payment", payment_id]`)
+/// to its merchant-scoped address so it can be refunded again.
+/// Permissionless — the record is copied as-is and the old account's rent
+/// goes back to the customer who paid for it. The merchant must already have
+/// been moved with `migrate_merchant`.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(payment_id: String)]
+pub struct MigratePayment<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Owner is checked here; discriminator, layout and PDA are checked in the handler
+    #[account(mut, owner = crate::ID @ PaymentError::InvalidLegacyPayment)]
+    pub legacy_payment: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), Payment::id_seed(&payment_id).as_ref()],
+        bump,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    /// CHECK: Must match the customer recorded on the legacy payment; receives its rent
+    #[account(mut)]
+    pub customer: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigratePayment<'info> {
+    pub fn migrate_payment(&mut self, payment_id: String, bumps: &MigratePaymentBumps) -> Result<PaymentMigrated> {
+        let now = Clock::get()?.unix_timestamp;
+        let legacy = self.read_legacy_payment()?;
+
+        require!(legacy.payment_id == payment_id, PaymentError::InvalidLegacyPayment);
+        // The legacy record points at the merchant's pre-migration address
+        let legacy_merchant = Merchant::legacy_address(&self.merchant_account.merchant_id)
+            .ok_or(PaymentError::InvalidLegacyPayment)?;
+        require_keys_eq!(legacy.merchant, legacy_merchant, PaymentError::InvalidLegacyPayment);
+        require_keys_eq!(legacy.customer, self.customer.key(), PaymentError::InvalidLegacyPayment);
+
+        // Legacy IDs were used as raw seeds, so anything over 32 bytes can't be one
+        let legacy_address = Pubkey::create_program_address(
+            &[Payment::SEED, payment_id.as_bytes(), &[legacy.bump]],
+            &crate::ID,
+        )
+        .map_err(|_| error!(PaymentError::InvalidLegacyPayment))?;
+        require_keys_eq!(legacy_address, self.legacy_payment.key(), PaymentError::InvalidLegacyPayment);
+
+        // Overrides didn't exist yet, so the fee was always the platform rate.
+        // The rate itself wasn't stored; recover it from the recorded amounts.
+        let fee_bps = legacy.fee_amount
+            .checked_mul(10_000)
+            .ok_or(PaymentError::CalculationError)?
+            .checked_div(legacy.amount)
+            .unwrap_or(0);
+
+        let payment = &mut self.payment_account;
+        payment.payment_id = legacy.payment_id;
+        payment.customer = legacy.customer;
+        payment.merchant = self.merchant_account.key();
+        payment.amount = legacy.amount;
+        payment.fee_amount = legacy.fee_amount;
+        payment.fee_bps = fee_bps;
+        payment.fee_source = FeeSource::Platform;
+        payment.fee_mode = FeeMode::Inclusive;
+        payment.merchant_amount = legacy.merchant_amount;
+        // Legacy refunds were always full reversals
+        payment.refunded_amount = if legacy.status == PaymentStatus::Refunded { legacy.amount } else { 0 };
+        payment.status = legacy.status;
+        payment.created_at = legacy.created_at;
+        // Legacy payments had no window; apply the merchant's current one from the original payment time
+        payment.refund_deadline = legacy.created_at
+            .checked_add(self.merchant_account.refund_window(self.platform_config.refund_window))
+            .ok_or(PaymentError::CalculationError)?;
+        payment.refunded_at = legacy.refunded_at;
+        payment.refund_request_open = false;
+        payment.dispute_open = false;
+        payment.escrow_release_at = None;
+        payment.requires_attestation = false;
+        payment.invoice_breakdown = None;
+        payment.sponsor_fee = 0;
+        payment.sponsor = None;
+        payment.parent = None;
+        payment.parent_index = 0;
+        payment.dispute_shortfall = 0;
+        payment.bump = bumps.payment_account;
+
+        // Release the payment's fee from the audited legacy bucket. A payment
+        // whose refund and dispute windows have already closed is final as it
+        // stands, so its fee becomes claimable, up to what the audit left. One
+        // that can still be refunded or disputed moves its fee to the refundable
+        // bucket, which must be fully funded. Claims never touch `legacy_fees`,
+        // so they can't block this
+        let dispute_deadline = self.platform_config
+            .dispute_deadline(payment.created_at)
+            .ok_or(PaymentError::CalculationError)?;
+        let retained_fee = payment.retained_fee().ok_or(PaymentError::CalculationError)?;
+        let platform = &mut self.platform_config;
+        if payment.status == PaymentStatus::Completed
+            && now > payment.refund_deadline
+            && now > dispute_deadline
+        {
+            payment.status = PaymentStatus::Finalized;
+            let released = retained_fee.min(platform.legacy_fees);
+            platform.legacy_fees -= released;
+            platform.accrued_fees = platform.accrued_fees
+                .checked_add(released)
+                .ok_or(PaymentError::CalculationError)?;
+        } else {
+            platform.legacy_fees = platform.legacy_fees
+                .checked_sub(retained_fee)
+                .ok_or(PaymentError::InsufficientLegacyFees)?;
+            platform.pending_fees = platform.pending_fees
+                .checked_add(retained_fee)
+                .ok_or(PaymentError::CalculationError)?;
+        }
+
+        self.close_legacy_payment()?;
+
+        msg!(
+            "Payment {} migrated to merchant {}",
+            payment_id,
+            self.merchant_account.merchant_id
+        );
+        Ok(PaymentMigrated {
+            payment: self.payment_account.key(),
+            legacy_payment: self.legacy_payment.key(),
+            payment_id,
+            merchant: self.merchant_account.key(),
+            migrated_at: now,
+        })
+    }
+
+    fn read_legacy_payment(&self) -> Result<LegacyPayment> {
+        let data = self.legacy_payment.try_borrow_data()?;
+        require!(
+            data.len() > Payment::DISCRIMINATOR.len() && data.starts_with(Payment::DISCRIMINATOR),
+            PaymentError::InvalidLegacyPayment
+        );
+        LegacyPayment::deserialize(&mut &data[Payment::DISCRIMINATOR.len()..])
+            .map_err(|_| error!(PaymentError::InvalidLegacyPayment))
+    }
+
+    fn close_legacy_payment(&self) -> Result<()> {
+        let legacy = self.legacy_payment.to_account_info();
+        let customer = self.customer.to_account_info();
+
+        let lamports = legacy.lamports();
+        **customer.try_borrow_mut_lamports()? = customer
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(PaymentError::CalculationError)?;
+        **legacy.try_borrow_mut_lamports()? = 0;
+
+        legacy.assign(&system_program::ID);
+        legacy.resize(0)?;
+        Ok(())
+    }
+}
+
+pub fn handler(ctx: Context<MigratePayment>, payment_id: String) -> Result<()> {
+    let event = ctx.accounts.migrate_payment(payment_id, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
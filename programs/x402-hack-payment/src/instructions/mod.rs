@@ -1,17 +1,63 @@
+pub mod attestation;
+pub mod attester;
+pub mod authorization;
+pub mod checkout_session;
 pub mod claim;
+pub mod delegate;
+pub mod dispute;
+pub mod escrow;
+pub mod finalize;
 pub mod initialize;
+pub mod invoice;
+pub mod merchant_fee;
+pub mod migrate_layout;
+pub mod migrate_merchant;
+pub mod migrate_payment;
 pub mod payment;
 pub mod payout;
 pub mod platform;
 pub mod private_receipt;
+pub mod purchase_order;
 pub mod refund;
+pub mod refund_request;
+pub mod refund_window;
+pub mod reserve;
+pub mod signed_payment;
+pub mod sponsor;
+pub mod subscription;
+pub mod suspend;
 pub mod transfer;
+pub mod update_merchant;
+pub mod update_platform;
 
+pub use attestation::*;
+pub use attester::*;
+pub use authorization::*;
+pub use checkout_session::*;
 pub use claim::*;
+pub use delegate::*;
+pub use dispute::*;
+pub use escrow::*;
+pub use finalize::*;
 pub use initialize::*;
+pub use invoice::*;
+pub use merchant_fee::*;
+pub use migrate_layout::*;
+pub use migrate_merchant::*;
+pub use migrate_payment::*;
 pub use payment::*;
 pub use payout::*;
 pub use platform::*;
 pub use private_receipt::*;
+pub use purchase_order::*;
 pub use refund::*;
+pub use refund_request::*;
+pub use refund_window::*;
+pub use reserve::*;
+pub use signed_payment::*;
+pub use sponsor::*;
+pub use subscription::*;
+pub use suspend::*;
 pub use transfer::*;
+pub use update_merchant::*;
+pub use update_platform::*;
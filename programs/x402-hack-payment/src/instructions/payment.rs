@@ -5,24 +5,38 @@ use anchor_spl::associated_token::AssociatedToken;
 use crate::state::Platform;
 use crate::state::merchant::Merchant;
 use crate::state::customer::Customer;
-use crate::state::payment::{Payment, PaymentStatus};
+use crate::state::payment::{FeeMode, FeeSource, Payment, PaymentStatus};
+use crate::events::PaymentProcessed;
 use crate::errors::PaymentError;
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(payment_id: String)]
 pub struct ProcessPayment<'info> {
+    /// Pays rent for the new accounts. May be a relayer (e.g. Kora) so the
+    /// customer doesn't need SOL; can be the same key as `customer`.
     #[account(mut)]
     pub payer: Signer<'info>,
+    /// Owner of `customer_usdc`; signs the USDC transfers
+    pub customer: Signer<'info>,
     #[account(
+        mut,
         seeds = [Platform::SEED],
         bump = platform_config.bump,
     )]
     pub platform_config: Box<Account<'info, Platform>>,
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+    /// Payment IDs are scoped to the merchant so another merchant can't squat on them
     #[account(
         init,
         payer = payer,
         space = 8 + Payment::INIT_SPACE,
-        seeds = [Payment::SEED, payment_id.as_bytes()],
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), Payment::id_seed(&payment_id).as_ref()],
         bump,
     )]
     pub payment_account: Box<Account<'info, Payment>>,
@@ -30,16 +44,10 @@ pub struct ProcessPayment<'info> {
         init_if_needed,
         payer = payer,
         space = 8 + Customer::INIT_SPACE,
-        seeds = [b"customer", payer.key().as_ref()],
+        seeds = [b"customer", customer.key().as_ref()],
         bump,
     )]
     pub customer_account: Box<Account<'info, Customer>>,
-    #[account(
-        mut,
-        seeds = [b"merchant", merchant_account.merchant_id.as_bytes()],
-        bump = merchant_account.bump,
-    )]
-    pub merchant_account: Box<Account<'info, Merchant>>,
     #[account(
         constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
     )]
@@ -47,7 +55,7 @@ pub struct ProcessPayment<'info> {
     #[account(
         mut,
         associated_token::mint = usdc_mint,
-        associated_token::authority = payer,
+        associated_token::authority = customer,
     )]
     pub customer_usdc: Account<'info, TokenAccount>,
     #[account(
@@ -64,6 +72,13 @@ pub struct ProcessPayment<'info> {
         token::authority = platform_config,
     )]
     pub platform_treasury_usdc: Account<'info, TokenAccount>,
+    /// Receives the sponsor fee; must be owned by a registered sponsor.
+    /// Only required when a sponsor fee is charged.
+    #[account(
+        mut,
+        token::mint = usdc_mint,
+    )]
+    pub sponsor_usdc: Option<Box<Account<'info, TokenAccount>>>,
     
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
@@ -71,37 +86,36 @@ pub struct ProcessPayment<'info> {
 }
 
 impl<'info> ProcessPayment<'info> {
-    pub fn process_payment(&mut self, payment_id: String, amount: u64, bumps: &ProcessPaymentBumps) -> Result<()> {
-        require!(self.platform_config.is_active, PaymentError::PlatformInactive);
-        require!(self.merchant_account.is_active, PaymentError::MerchantInactive);
-        require!(amount >= self.platform_config.min_payment_amount, PaymentError::PaymentBelowMinimum);
-        require!(!payment_id.is_empty() && payment_id.len() <= 64, PaymentError::InvalidPaymentId);
-
-        // Calculate fee and merchant amount
-        let fee = amount
-            .checked_mul(self.platform_config.fee_bps as u64)
-            .ok_or(PaymentError::CalculationError)?
-            .checked_div(10_000)
-            .ok_or(PaymentError::CalculationError)?;
-
-        let merchant_amount = amount
-            .checked_sub(fee)
+    /// `amount` is the merchant's price including any `sponsor_fee`. The sponsor
+    /// fee is carved out first, so the platform fee and merchant share are
+    /// computed on the remainder. Under `FeeMode::Additive` the customer also
+    /// pays the platform fee on top.
+    pub fn process_payment(
+        &mut self,
+        payment_id: String,
+        amount: u64,
+        sponsor_fee: u64,
+        fee_mode: FeeMode,
+        bumps: &ProcessPaymentBumps,
+    ) -> Result<PaymentProcessed> {
+        require!(
+            sponsor_fee <= self.platform_config.max_sponsor_fee && sponsor_fee < amount,
+            PaymentError::SponsorFeeTooHigh
+        );
+        let net_amount = amount - sponsor_fee;
+        let (fee_bps, fee_source, fee, merchant_amount) =
+            payment_terms(&self.platform_config, &self.merchant_account, &payment_id, net_amount, fee_mode)?;
+        let charged = merchant_amount
+            .checked_add(fee)
             .ok_or(PaymentError::CalculationError)?;
 
-        // Initialize customer account if this is their first payment
-        if self.customer_account.transaction_count == 0 {
-            self.customer_account.customer = self.payer.key();
-            self.customer_account.total_spent = 0;
-            self.customer_account.transaction_count = 0;
-            self.customer_account.created_at = Clock::get()?.unix_timestamp;
-            self.customer_account.bump = bumps.customer_account;
-        }
+        self.customer_account.init_if_new(self.customer.key(), Clock::get()?.unix_timestamp, bumps.customer_account);
 
         // Transfer merchant amount from customer to merchant
         let transfer_to_merchant_accounts = Transfer {
             from: self.customer_usdc.to_account_info(),
             to: self.merchant_usdc.to_account_info(),
-            authority: self.payer.to_account_info(),
+            authority: self.customer.to_account_info(),
         };
         let transfer_to_merchant_ctx = CpiContext::new(
             self.token_program.to_account_info(),
@@ -113,7 +127,7 @@ impl<'info> ProcessPayment<'info> {
         let transfer_fee_accounts = Transfer {
             from: self.customer_usdc.to_account_info(),
             to: self.platform_treasury_usdc.to_account_info(),
-            authority: self.payer.to_account_info(),
+            authority: self.customer.to_account_info(),
         };
         let transfer_fee_ctx = CpiContext::new(
             self.token_program.to_account_info(),
@@ -121,6 +135,38 @@ impl<'info> ProcessPayment<'info> {
         );
         transfer(transfer_fee_ctx, fee)?;
 
+        // Reimburse the sponsor out of the customer's transfer
+        let sponsor = if sponsor_fee > 0 {
+            let sponsor_usdc = self.sponsor_usdc
+                .as_ref()
+                .ok_or(PaymentError::InvalidSponsorAccount)?;
+            require!(
+                self.platform_config.is_sponsor(&sponsor_usdc.owner),
+                PaymentError::InvalidSponsorAccount
+            );
+
+            let transfer_sponsor_accounts = Transfer {
+                from: self.customer_usdc.to_account_info(),
+                to: sponsor_usdc.to_account_info(),
+                authority: self.customer.to_account_info(),
+            };
+            let transfer_sponsor_ctx = CpiContext::new(
+                self.token_program.to_account_info(),
+                transfer_sponsor_accounts,
+            );
+            transfer(transfer_sponsor_ctx, sponsor_fee)?;
+
+            msg!("Sponsor {} reimbursed {} USDC", sponsor_usdc.owner, sponsor_fee);
+            Some(sponsor_usdc.owner)
+        } else {
+            None
+        };
+
+        self.platform_config.pending_fees = self.platform_config
+            .pending_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
         // Update customer stats
         self.customer_account.transaction_count = self.customer_account
             .transaction_count
@@ -129,7 +175,8 @@ impl<'info> ProcessPayment<'info> {
         
         self.customer_account.total_spent = self.customer_account
             .total_spent
-            .checked_add(amount)
+            .checked_add(charged)
+            .and_then(|total| total.checked_add(sponsor_fee))
             .ok_or(PaymentError::CalculationError)?;
 
         // Update merchant stats
@@ -150,14 +197,33 @@ impl<'info> ProcessPayment<'info> {
 
         // Create payment record
         self.payment_account.payment_id = payment_id.clone();
-        self.payment_account.customer = self.payer.key();
+        self.payment_account.customer = self.customer.key();
         self.payment_account.merchant = self.merchant_account.key();
-        self.payment_account.amount = amount;
+        self.payment_account.amount = charged;
         self.payment_account.fee_amount = fee;
+        self.payment_account.fee_bps = fee_bps;
+        self.payment_account.fee_source = fee_source;
+        self.payment_account.fee_mode = fee_mode;
         self.payment_account.merchant_amount = merchant_amount;
+        self.payment_account.refunded_amount = 0;
         self.payment_account.status = PaymentStatus::Completed;
         self.payment_account.created_at = Clock::get()?.unix_timestamp;
+        // Snapshot the window so later config changes don't affect this payment
+        self.payment_account.refund_deadline = self.payment_account
+            .created_at
+            .checked_add(self.merchant_account.refund_window(self.platform_config.refund_window))
+            .ok_or(PaymentError::CalculationError)?;
         self.payment_account.refunded_at = None;
+        self.payment_account.refund_request_open = false;
+        self.payment_account.dispute_open = false;
+        self.payment_account.escrow_release_at = None;
+        self.payment_account.requires_attestation = false;
+        self.payment_account.invoice_breakdown = None;
+        self.payment_account.sponsor_fee = sponsor_fee;
+        self.payment_account.sponsor = sponsor;
+        self.payment_account.parent = None;
+        self.payment_account.parent_index = 0;
+        self.payment_account.dispute_shortfall = 0;
         self.payment_account.bump = bumps.payment_account;
 
         msg!(
@@ -168,14 +234,68 @@ impl<'info> ProcessPayment<'info> {
             fee
         );
 
-        Ok(())
+        Ok(PaymentProcessed {
+            payment: self.payment_account.key(),
+            payment_id,
+            merchant: self.merchant_account.key(),
+            customer: self.customer.key(),
+            amount: charged,
+            fee_amount: fee,
+            fee_bps,
+            fee_source,
+            fee_mode,
+            merchant_amount,
+            sponsor_fee,
+            sponsor,
+            created_at: self.payment_account.created_at,
+            refund_deadline: self.payment_account.refund_deadline,
+        })
     }
 }
 
+/// Validate a new payment and split `amount` into its fee and merchant shares.
+/// Returns `(fee_bps, fee_source, fee, merchant_amount)`. With `FeeMode::Additive`
+/// the merchant keeps all of `amount` and the customer is charged the fee on top,
+/// so the total charged is always `merchant_amount + fee`. Shared with escrowed payments.
+pub(crate) fn payment_terms(
+    platform_config: &Platform,
+    merchant_account: &Merchant,
+    payment_id: &str,
+    amount: u64,
+    fee_mode: FeeMode,
+) -> Result<(u64, FeeSource, u64, u64)> {
+    require!(platform_config.is_active, PaymentError::PlatformInactive);
+    require!(merchant_account.suspension_reason.is_none(), PaymentError::MerchantSuspended);
+    require!(merchant_account.is_active, PaymentError::MerchantInactive);
+    require!(amount >= platform_config.min_payment_amount, PaymentError::PaymentBelowMinimum);
+    require!(!payment_id.is_empty() && payment_id.len() <= Payment::MAX_ID_LEN, PaymentError::InvalidPaymentId);
+
+    // Calculate fee and merchant amount: merchant override first, then platform default
+    let (fee_bps, fee_source) = merchant_account.fee_terms(platform_config.fee_bps);
+    let fee = amount
+        .checked_mul(fee_bps)
+        .ok_or(PaymentError::CalculationError)?
+        .checked_div(10_000)
+        .ok_or(PaymentError::CalculationError)?;
+
+    let merchant_amount = match fee_mode {
+        FeeMode::Inclusive => amount
+            .checked_sub(fee)
+            .ok_or(PaymentError::CalculationError)?,
+        FeeMode::Additive => amount,
+    };
+
+    Ok((fee_bps, fee_source, fee, merchant_amount))
+}
+
 pub fn handler(
     ctx: Context<ProcessPayment>, 
     payment_id: String,
-    amount: u64
+    amount: u64,
+    sponsor_fee: Option<u64>,
 ) -> Result<()> {
-    ctx.accounts.process_payment(payment_id, amount, &ctx.bumps)
+    let fee_mode = ctx.accounts.merchant_account.fee_mode;
+    let event = ctx.accounts.process_payment(payment_id, amount, sponsor_fee.unwrap_or(0), fee_mode, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
 }
\ No newline at end of file
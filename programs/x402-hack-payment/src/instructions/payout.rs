@@ -3,12 +3,15 @@ use anchor_spl::token::{Token, TokenAccount, Mint, Transfer, transfer};
 use anchor_spl::associated_token::AssociatedToken;
 
 use crate::state::platform::Platform;
+use crate::state::payment::Payment;
+use crate::events::PayoutProcessed;
 use crate::errors::PaymentError;
 
 /// Process a payout from the platform treasury to any recipient wallet.
 /// Only the platform authority can call this instruction.
 /// Used by the Offbank Payout API — platforms deposit USDC to treasury,
 /// then call this to release funds to recipients who have claimed via email.
+#[event_cpi]
 #[derive(Accounts)]
 pub struct ProcessPayout<'info> {
     /// Platform authority — must sign. Can be a wallet or Squads vault.
@@ -53,13 +56,14 @@ pub struct ProcessPayout<'info> {
 }
 
 impl<'info> ProcessPayout<'info> {
-    pub fn process_payout(&mut self, amount: u64, payout_id: String) -> Result<()> {
+    pub fn process_payout(&mut self, amount: u64, payout_id: String) -> Result<PayoutProcessed> {
         require!(self.platform_config.is_active, PaymentError::PlatformInactive);
         require!(amount > 0, PaymentError::PaymentBelowMinimum);
-        require!(!payout_id.is_empty() && payout_id.len() <= 64, PaymentError::InvalidPaymentId);
+        require!(!payout_id.is_empty() && payout_id.len() <= Payment::MAX_ID_LEN, PaymentError::InvalidPaymentId);
 
-        let treasury_balance = self.platform_treasury_usdc.amount;
-        require!(treasury_balance >= amount, PaymentError::InsufficientTreasuryBalance);
+        // Payouts may only spend escrowed deposits, never accrued fee revenue
+        let escrow_balance = self.platform_config.escrow_balance(self.platform_treasury_usdc.amount);
+        require!(escrow_balance >= amount, PaymentError::InsufficientTreasuryBalance);
 
         // Transfer from treasury PDA to recipient ATA
         let cpi_accounts = Transfer {
@@ -83,10 +87,18 @@ impl<'info> ProcessPayout<'info> {
             self.recipient.key()
         );
 
-        Ok(())
+        Ok(PayoutProcessed {
+            payout_id,
+            recipient: self.recipient.key(),
+            recipient_usdc: self.recipient_usdc.key(),
+            amount,
+            processed_at: Clock::get()?.unix_timestamp,
+        })
     }
 }
 
 pub fn handler(ctx: Context<ProcessPayout>, amount: u64, payout_id: String) -> Result<()> {
-    ctx.accounts.process_payout(amount, payout_id)
+    let event = ctx.accounts.process_payout(amount, payout_id)?;
+    emit_cpi!(event);
+    Ok(())
 }
@@ -2,8 +2,10 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::{Mint, TokenAccount, Token};
 
 use crate::state::platform::Platform;
+use crate::events::PlatformConfigured;
 use crate::errors::PaymentError;
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct InitializePlatform<'info> {
     #[account(mut)]
@@ -35,17 +37,30 @@ pub struct InitializePlatform<'info> {
 }
 
 impl<'info> InitializePlatform<'info> {
-    pub fn set_platform_config(&mut self, fee_bps: u64, min_payment_amount: u64, platform_bump: u8, treasury_bump: u8) -> Result<()> {
-        require!(fee_bps <= 1000, PaymentError::InvalidFeeBps);
+    pub fn set_platform_config(&mut self, fee_bps: u64, min_payment_amount: u64, platform_bump: u8, treasury_bump: u8) -> Result<PlatformConfigured> {
+        require!(fee_bps <= Platform::MAX_FEE_BPS, PaymentError::InvalidFeeBps);
         require!(min_payment_amount > 0, PaymentError::InvalidMinPaymentAmount);
         require!(self.usdc_mint.decimals == 6, PaymentError::InvalidUsdcMint);
 
         self.platform_config.authority = self.authority.key();
+        self.platform_config.pending_authority = None;
         self.platform_config.treasury = self.platform_treasury.key();
         self.platform_config.usdc_mint = self.usdc_mint.key();
         self.platform_config.fee_bps = fee_bps;
         self.platform_config.min_payment_amount = min_payment_amount;
         self.platform_config.is_active = true;
+        self.platform_config.accrued_fees = 0;
+        self.platform_config.settlement_wallet_delay = Platform::DEFAULT_SETTLEMENT_WALLET_DELAY;
+        self.platform_config.refund_window = Platform::DEFAULT_REFUND_WINDOW;
+        self.platform_config.arbiter = self.authority.key();
+        self.platform_config.escrow_timeout = Platform::DEFAULT_ESCROW_TIMEOUT;
+        self.platform_config.attesters = Vec::new();
+        self.platform_config.authorization_ttl = Platform::DEFAULT_AUTHORIZATION_TTL;
+        self.platform_config.max_sponsor_fee = 0;
+        self.platform_config.sponsors = Vec::new();
+        self.platform_config.pending_fees = 0;
+        self.platform_config.dispute_window = Platform::DEFAULT_DISPUTE_WINDOW;
+        self.platform_config.legacy_fees = 0;
         self.platform_config.bump = platform_bump;
         self.platform_config.treasury_bump = treasury_bump;
 
@@ -54,12 +69,29 @@ impl<'info> InitializePlatform<'info> {
         msg!("USDC mint set to {}", self.usdc_mint.key());
         msg!("Treasury token account created at {}", self.platform_treasury.key());
         
-        Ok(())
+        Ok(PlatformConfigured {
+            platform_config: self.platform_config.key(),
+            authority: self.platform_config.authority,
+            treasury: self.platform_config.treasury,
+            usdc_mint: self.platform_config.usdc_mint,
+            fee_bps,
+            min_payment_amount,
+            settlement_wallet_delay: self.platform_config.settlement_wallet_delay,
+            refund_window: self.platform_config.refund_window,
+            arbiter: self.platform_config.arbiter,
+            escrow_timeout: self.platform_config.escrow_timeout,
+            authorization_ttl: self.platform_config.authorization_ttl,
+            max_sponsor_fee: self.platform_config.max_sponsor_fee,
+            dispute_window: self.platform_config.dispute_window,
+            configured_at: Clock::get()?.unix_timestamp,
+        })
     } 
 }
 
 pub fn handler(ctx: Context<InitializePlatform>, fee_bps: u64, min_payment_amount: u64) -> Result<()> {
     let platform_bump = ctx.bumps.platform_config;
     let treasury_bump = ctx.bumps.platform_treasury;
-    ctx.accounts.set_platform_config(fee_bps, min_payment_amount, platform_bump, treasury_bump)
+    let event = ctx.accounts.set_platform_config(fee_bps, min_payment_amount, platform_bump, treasury_bump)?;
+    emit_cpi!(event);
+    Ok(())
 }
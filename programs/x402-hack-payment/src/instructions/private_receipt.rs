@@ -2,18 +2,25 @@ use anchor_lang::prelude::*;
 use ephemeral_rollups_sdk::cpi::{delegate_account, DelegateAccounts, DelegateConfig};
 use ephemeral_rollups_sdk::anchor::delegate;
 
-use crate::state::{PrivateReceipt, SessionStatus};
+use crate::state::{Payment, PrivateReceipt, SessionStatus};
+use crate::events::{
+    PrivatePaymentDelegated, PrivatePaymentProcessed, PrivatePaymentSettled, PrivateReceiptIssued,
+};
 use crate::errors::PaymentError;
 
 /// ──────────────────────────────────────────────────────────────────────
 /// 1. Create a private payment session (on base layer)
 ///    Initialises the account that will later be delegated to a PER.
 /// ──────────────────────────────────────────────────────────────────────
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(payment_id: String)]
 pub struct IssuePrivateReceipt<'info> {
-    /// The customer who made the payment (pays for account creation)
+    /// Pays for account creation. May be a relayer; can be the same key as `customer`.
     #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The customer who made the payment
     pub customer: Signer<'info>,
 
     /// The merchant who received the payment
@@ -23,9 +30,9 @@ pub struct IssuePrivateReceipt<'info> {
     /// The private receipt account to create
     #[account(
         init,
-        payer = customer,
+        payer = payer,
         space = 8 + PrivateReceipt::INIT_SPACE,
-        seeds = [PrivateReceipt::SEED, payment_id.as_bytes()],
+        seeds = [PrivateReceipt::SEED, merchant.key().as_ref(), Payment::id_seed(&payment_id).as_ref()],
         bump,
     )]
     pub private_receipt: Account<'info, PrivateReceipt>,
@@ -42,7 +49,7 @@ impl<'info> IssuePrivateReceipt<'info> {
         memo: String,
     ) -> Result<()> {
         require!(
-            !payment_id.is_empty() && payment_id.len() <= 64,
+            !payment_id.is_empty() && payment_id.len() <= Payment::MAX_ID_LEN,
             PaymentError::InvalidPaymentId
         );
 
@@ -64,6 +71,16 @@ impl<'info> IssuePrivateReceipt<'info> {
         msg!("   Customer: {}", ctx.accounts.customer.key());
         msg!("   Merchant: {}", ctx.accounts.merchant.key());
 
+        emit_cpi!(PrivateReceiptIssued {
+            private_receipt: ctx.accounts.private_receipt.key(),
+            payment_id,
+            customer: ctx.accounts.customer.key(),
+            merchant: ctx.accounts.merchant.key(),
+            amount,
+            fee_amount,
+            created_at: ctx.accounts.private_receipt.created_at,
+        });
+
         Ok(())
     }
 }
@@ -76,6 +93,7 @@ impl<'info> IssuePrivateReceipt<'info> {
 ///    DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh
 /// ──────────────────────────────────────────────────────────────────────
 #[delegate]
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(payment_id: String)]
 pub struct DelegatePrivatePayment<'info> {
@@ -86,7 +104,7 @@ pub struct DelegatePrivatePayment<'info> {
     /// The private receipt PDA to delegate
     #[account(
         mut,
-        seeds = [PrivateReceipt::SEED, payment_id.as_bytes()],
+        seeds = [PrivateReceipt::SEED, private_receipt.merchant.as_ref(), Payment::id_seed(&payment_id).as_ref()],
         bump,
     )]
     pub private_receipt: Account<'info, PrivateReceipt>,
@@ -116,7 +134,9 @@ pub fn delegate_private_payment(
     ctx: Context<DelegatePrivatePayment>,
     payment_id: String,
 ) -> Result<()> {
-    let pda_seeds: &[&[u8]] = &[PrivateReceipt::SEED, payment_id.as_bytes()];
+    let merchant = ctx.accounts.private_receipt.merchant;
+    let id_seed = Payment::id_seed(&payment_id);
+    let pda_seeds: &[&[u8]] = &[PrivateReceipt::SEED, merchant.as_ref(), &id_seed];
 
     delegate_account(
         DelegateAccounts {
@@ -143,6 +163,12 @@ pub fn delegate_private_payment(
     msg!("📡 Private payment {} delegated to PER (TEE)", payment_id);
     msg!("   Account data now hidden inside Intel TDX enclave");
 
+    emit_cpi!(PrivatePaymentDelegated {
+        private_receipt: ctx.accounts.private_receipt.key(),
+        payment_id,
+        delegated_at: Clock::get()?.unix_timestamp,
+    });
+
     Ok(())
 }
 
@@ -151,6 +177,7 @@ pub fn delegate_private_payment(
 ///    This runs inside the Trusted Execution Environment — hidden from
 ///    base-layer observers. Only permissioned members see the data.
 /// ──────────────────────────────────────────────────────────────────────
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(payment_id: String)]
 pub struct ProcessPrivatePayment<'info> {
@@ -159,7 +186,7 @@ pub struct ProcessPrivatePayment<'info> {
 
     #[account(
         mut,
-        seeds = [PrivateReceipt::SEED, payment_id.as_bytes()],
+        seeds = [PrivateReceipt::SEED, private_receipt.merchant.as_ref(), Payment::id_seed(&payment_id).as_ref()],
         bump,
     )]
     pub private_receipt: Account<'info, PrivateReceipt>,
@@ -167,7 +194,7 @@ pub struct ProcessPrivatePayment<'info> {
 
 pub fn process_private_payment(
     ctx: Context<ProcessPrivatePayment>,
-    _payment_id: String,
+    payment_id: String,
 ) -> Result<()> {
     let receipt = &mut ctx.accounts.private_receipt;
     require!(
@@ -181,6 +208,14 @@ pub fn process_private_payment(
     msg!("✅ Private payment processed inside TEE");
     msg!("   Amount: {} (hidden from base-layer observers)", receipt.amount);
 
+    emit_cpi!(PrivatePaymentProcessed {
+        private_receipt: ctx.accounts.private_receipt.key(),
+        payment_id,
+        amount: ctx.accounts.private_receipt.amount,
+        fee_amount: ctx.accounts.private_receipt.fee_amount,
+        processed_at: Clock::get()?.unix_timestamp,
+    });
+
     Ok(())
 }
 
@@ -188,6 +223,7 @@ pub fn process_private_payment(
 /// 4. Settle — commit state back to base layer and undelegate
 ///    After this, the final state is visible on-chain.
 /// ──────────────────────────────────────────────────────────────────────
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(payment_id: String)]
 pub struct SettlePrivatePayment<'info> {
@@ -196,7 +232,7 @@ pub struct SettlePrivatePayment<'info> {
 
     #[account(
         mut,
-        seeds = [PrivateReceipt::SEED, payment_id.as_bytes()],
+        seeds = [PrivateReceipt::SEED, private_receipt.merchant.as_ref(), Payment::id_seed(&payment_id).as_ref()],
         bump,
     )]
     pub private_receipt: Account<'info, PrivateReceipt>,
@@ -204,7 +240,7 @@ pub struct SettlePrivatePayment<'info> {
 
 pub fn settle_private_payment(
     ctx: Context<SettlePrivatePayment>,
-    _payment_id: String,
+    payment_id: String,
 ) -> Result<()> {
     let receipt = &mut ctx.accounts.private_receipt;
 
@@ -216,5 +252,15 @@ pub fn settle_private_payment(
     msg!("   Final amount: {} USDC lamports", receipt.amount);
     msg!("   Fee: {} USDC lamports", receipt.fee_amount);
 
+    emit_cpi!(PrivatePaymentSettled {
+        private_receipt: receipt.key(),
+        payment_id,
+        customer: receipt.customer,
+        merchant: receipt.merchant,
+        amount: receipt.amount,
+        fee_amount: receipt.fee_amount,
+        settled_at: receipt.settled_at,
+    });
+
     Ok(())
 }
@@ -0,0 +1,563 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{close_account, transfer, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{
+    Customer, FeeMode, Merchant, Milestone, MilestoneStatus, Payment, PaymentStatus, Platform,
+    PurchaseOrder, PurchaseOrderStatus,
+};
+use crate::events::{MilestoneReleased, PaymentProcessed, PurchaseOrderCancelled, PurchaseOrderCreated};
+use crate::errors::PaymentError;
+use crate::instructions::payment::payment_terms;
+
+/// Milestone terms supplied when creating an order.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct MilestoneArgs {
+    pub amount: u64,
+    /// Anyone may release after this time; `None` means buyer approval only
+    pub release_after: Option<i64>,
+}
+
+/// Buyer opens a purchase order and funds every milestone up front. Goes
+/// through the same checks as `process_payment`, applied to the order total.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(order_id: String)]
+pub struct CreatePurchaseOrder<'info> {
+    /// Pays rent for the new accounts. May be a relayer so the buyer doesn't
+    /// need SOL; can be the same key as `buyer`.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Owner of `customer_usdc`; signs the funding transfer
+    pub buyer: Signer<'info>,
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PurchaseOrder::INIT_SPACE,
+        seeds = [PurchaseOrder::SEED, merchant_account.key().as_ref(), PurchaseOrder::id_seed(&order_id).as_ref()],
+        bump,
+    )]
+    pub purchase_order: Box<Account<'info, PurchaseOrder>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Customer::INIT_SPACE,
+        seeds = [b"customer", buyer.key().as_ref()],
+        bump,
+    )]
+    pub customer_account: Box<Account<'info, Customer>>,
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = buyer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [PurchaseOrder::ESCROW_SEED, purchase_order.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreatePurchaseOrder<'info> {
+    pub fn create_purchase_order(
+        &mut self,
+        order_id: String,
+        milestones: Vec<MilestoneArgs>,
+        deliver_by: i64,
+        bumps: &CreatePurchaseOrderBumps,
+    ) -> Result<PurchaseOrderCreated> {
+        require!(order_id.len() <= PurchaseOrder::MAX_ID_LEN, PaymentError::InvalidPaymentId);
+        require!(
+            !milestones.is_empty()
+                && milestones.len() <= PurchaseOrder::MAX_MILESTONES
+                && milestones.iter().all(|m| m.amount > 0),
+            PaymentError::InvalidMilestones
+        );
+        let total_amount = milestones
+            .iter()
+            .try_fold(0u64, |total, m| total.checked_add(m.amount))
+            .ok_or(PaymentError::CalculationError)?;
+        let (fee_bps, fee_source, _, _) =
+            payment_terms(&self.platform_config, &self.merchant_account, &order_id, total_amount, FeeMode::Inclusive)?;
+        let now = Clock::get()?.unix_timestamp;
+        require!(deliver_by > now, PaymentError::InvalidDeliveryDeadline);
+
+        self.customer_account.init_if_new(self.buyer.key(), now, bumps.customer_account);
+
+        let transfer_accounts = Transfer {
+            from: self.customer_usdc.to_account_info(),
+            to: self.escrow.to_account_info(),
+            authority: self.buyer.to_account_info(),
+        };
+        transfer(CpiContext::new(self.token_program.to_account_info(), transfer_accounts), total_amount)?;
+
+        self.customer_account.transaction_count = self.customer_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.customer_account.total_spent = self.customer_account
+            .total_spent
+            .checked_add(total_amount)
+            .ok_or(PaymentError::CalculationError)?;
+
+        let milestone_count = milestones.len() as u8;
+        self.purchase_order.set_inner(PurchaseOrder {
+            order_id: order_id.clone(),
+            buyer: self.buyer.key(),
+            merchant: self.merchant_account.key(),
+            total_amount,
+            released_amount: 0,
+            released_count: 0,
+            cancelled_amount: 0,
+            deliver_by,
+            fee_bps,
+            fee_source,
+            milestones: milestones
+                .into_iter()
+                .map(|m| Milestone {
+                    amount: m.amount,
+                    release_after: m.release_after,
+                    status: MilestoneStatus::Pending,
+                    released_at: None,
+                })
+                .collect(),
+            status: PurchaseOrderStatus::Open,
+            created_at: now,
+            bump: bumps.purchase_order,
+        });
+
+        msg!(
+            "Purchase order {} funded: {} USDC over {} milestones",
+            order_id,
+            total_amount,
+            milestone_count
+        );
+        Ok(PurchaseOrderCreated {
+            purchase_order: self.purchase_order.key(),
+            order_id,
+            merchant: self.merchant_account.key(),
+            buyer: self.buyer.key(),
+            total_amount,
+            milestone_count,
+            fee_bps,
+            deliver_by,
+            created_at: now,
+        })
+    }
+}
+
+/// Release one milestone to the merchant, taking the fee on that milestone
+/// only. The buyer can release any milestone; anyone can release one whose
+/// `release_after` has passed. Each release records a `Payment` labelled
+/// `<order_id>-m<index>` and addressed from the order and milestone index, so
+/// no one can create it ahead of time. Refunds and reporting treat it like any
+/// other payment.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(milestone_index: u8)]
+pub struct ReleaseMilestone<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [PurchaseOrder::SEED, merchant_account.key().as_ref(), PurchaseOrder::id_seed(&purchase_order.order_id).as_ref()],
+        bump = purchase_order.bump,
+    )]
+    pub purchase_order: Box<Account<'info, PurchaseOrder>>,
+
+    #[account(
+        init,
+        payer = signer,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [
+            Payment::SEED,
+            merchant_account.key().as_ref(),
+            Payment::derived_seed(&purchase_order.key(), milestone_index as u64).as_ref(),
+        ],
+        bump,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    /// CHECK: The buyer; receives the escrow account's rent once the order completes
+    #[account(
+        mut,
+        constraint = buyer.key() == purchase_order.buyer @ PaymentError::Unauthorized
+    )]
+    pub buyer: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [PurchaseOrder::ESCROW_SEED, purchase_order.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = merchant_account.settlement_wallet,
+    )]
+    pub merchant_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [Platform::TREASURY_SEED],
+        bump = platform_config.treasury_bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub platform_treasury_usdc: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ReleaseMilestone<'info> {
+    pub fn release_milestone(
+        &mut self,
+        milestone_index: u8,
+        bumps: &ReleaseMilestoneBumps,
+    ) -> Result<(MilestoneReleased, PaymentProcessed)> {
+        let now = Clock::get()?.unix_timestamp;
+        let approved_by_buyer = self.signer.key() == self.purchase_order.buyer;
+
+        let milestone = self
+            .purchase_order
+            .milestones
+            .get(milestone_index as usize)
+            .ok_or(PaymentError::InvalidMilestoneIndex)?;
+        require!(milestone.status == MilestoneStatus::Pending, PaymentError::MilestoneAlreadyReleased);
+        require!(
+            approved_by_buyer || milestone.release_after.is_some_and(|t| now >= t),
+            PaymentError::MilestoneNotDue
+        );
+
+        let amount = milestone.amount;
+        let fee_bps = self.purchase_order.fee_bps;
+        let fee = amount
+            .checked_mul(fee_bps)
+            .ok_or(PaymentError::CalculationError)?
+            .checked_div(10_000)
+            .ok_or(PaymentError::CalculationError)?;
+        let merchant_amount = amount
+            .checked_sub(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        let seeds = &[Platform::SEED, &[self.platform_config.bump]];
+        let signer = &[&seeds[..]];
+
+        let transfer_to_merchant_accounts = Transfer {
+            from: self.escrow.to_account_info(),
+            to: self.merchant_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_to_merchant_accounts, signer),
+            merchant_amount,
+        )?;
+
+        let transfer_fee_accounts = Transfer {
+            from: self.escrow.to_account_info(),
+            to: self.platform_treasury_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_fee_accounts, signer),
+            fee,
+        )?;
+
+        self.platform_config.pending_fees = self.platform_config
+            .pending_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.merchant_account.transaction_count = self.merchant_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.volume = self.merchant_account
+            .volume
+            .checked_add(merchant_amount)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.total_fees = self.merchant_account
+            .total_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        // Update the order
+        let order = &mut self.purchase_order;
+        let milestone = &mut order.milestones[milestone_index as usize];
+        milestone.status = MilestoneStatus::Released;
+        milestone.released_at = Some(now);
+        order.released_amount = order.released_amount
+            .checked_add(amount)
+            .ok_or(PaymentError::CalculationError)?;
+        order.released_count += 1;
+        let settled = order.is_settled();
+        if settled && order.status == PurchaseOrderStatus::Open {
+            order.status = PurchaseOrderStatus::Completed;
+        }
+
+        // Record the milestone as a regular payment
+        let payment_id = order.milestone_payment_id(milestone_index);
+        let fee_source = order.fee_source;
+        let payment = &mut self.payment_account;
+        payment.payment_id = payment_id.clone();
+        payment.customer = order.buyer;
+        payment.merchant = self.merchant_account.key();
+        payment.amount = amount;
+        payment.fee_amount = fee;
+        payment.fee_bps = fee_bps;
+        payment.fee_source = fee_source;
+        payment.fee_mode = FeeMode::Inclusive;
+        payment.merchant_amount = merchant_amount;
+        payment.refunded_amount = 0;
+        payment.status = PaymentStatus::Completed;
+        payment.created_at = now;
+        payment.refund_deadline = now
+            .checked_add(self.merchant_account.refund_window(self.platform_config.refund_window))
+            .ok_or(PaymentError::CalculationError)?;
+        payment.refunded_at = None;
+        payment.refund_request_open = false;
+        payment.dispute_open = false;
+        payment.escrow_release_at = None;
+        payment.requires_attestation = false;
+        payment.invoice_breakdown = None;
+        payment.sponsor_fee = 0;
+        payment.sponsor = None;
+        payment.parent = Some(order.key());
+        payment.parent_index = milestone_index as u64;
+        payment.dispute_shortfall = 0;
+        payment.bump = bumps.payment_account;
+
+        if settled {
+            let close_accounts = CloseAccount {
+                account: self.escrow.to_account_info(),
+                destination: self.buyer.to_account_info(),
+                authority: self.platform_config.to_account_info(),
+            };
+            close_account(CpiContext::new_with_signer(self.token_program.to_account_info(), close_accounts, signer))?;
+        }
+
+        msg!(
+            "Purchase order {} milestone {} released: {} USDC to merchant {}, {} USDC fee to platform",
+            order.order_id,
+            milestone_index,
+            merchant_amount,
+            self.merchant_account.merchant_id,
+            fee
+        );
+        Ok((
+            MilestoneReleased {
+                purchase_order: order.key(),
+                milestone_index,
+                payment: payment.key(),
+                amount,
+                approved_by_buyer,
+                fee_mode: FeeMode::Inclusive,
+                released_amount: order.released_amount,
+                released_at: now,
+            },
+            PaymentProcessed {
+                payment: payment.key(),
+                payment_id,
+                merchant: self.merchant_account.key(),
+                customer: order.buyer,
+                amount,
+                fee_amount: fee,
+                fee_bps,
+                fee_source,
+                fee_mode: FeeMode::Inclusive,
+                merchant_amount,
+                sponsor_fee: 0,
+                sponsor: None,
+                created_at: now,
+                refund_deadline: payment.refund_deadline,
+            },
+        ))
+    }
+}
+
+/// Buyer takes back the milestones still outstanding once `deliver_by` has
+/// passed. Milestones whose `release_after` has already passed were earned by
+/// the merchant and stay releasable.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelPurchaseOrder<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [PurchaseOrder::SEED, merchant_account.key().as_ref(), PurchaseOrder::id_seed(&purchase_order.order_id).as_ref()],
+        bump = purchase_order.bump,
+        has_one = buyer @ PaymentError::Unauthorized,
+        constraint = purchase_order.status == PurchaseOrderStatus::Open @ PaymentError::NothingToCancel,
+    )]
+    pub purchase_order: Box<Account<'info, PurchaseOrder>>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = buyer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [PurchaseOrder::ESCROW_SEED, purchase_order.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub escrow: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> CancelPurchaseOrder<'info> {
+    pub fn cancel_purchase_order(&mut self) -> Result<PurchaseOrderCancelled> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(now > self.purchase_order.deliver_by, PaymentError::DeliveryDeadlineNotReached);
+
+        let order = &mut self.purchase_order;
+        let mut refund_amount = 0u64;
+        let mut cancelled_milestones = 0u8;
+        for milestone in order.milestones.iter_mut() {
+            let due = milestone.release_after.is_some_and(|t| now >= t);
+            if milestone.status == MilestoneStatus::Pending && !due {
+                milestone.status = MilestoneStatus::Cancelled;
+                refund_amount = refund_amount
+                    .checked_add(milestone.amount)
+                    .ok_or(PaymentError::CalculationError)?;
+                cancelled_milestones += 1;
+            }
+        }
+        require!(cancelled_milestones > 0, PaymentError::NothingToCancel);
+        order.cancelled_amount = refund_amount;
+        order.status = PurchaseOrderStatus::Cancelled;
+
+        let seeds = &[Platform::SEED, &[self.platform_config.bump]];
+        let signer = &[&seeds[..]];
+        let refund_accounts = Transfer {
+            from: self.escrow.to_account_info(),
+            to: self.customer_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), refund_accounts, signer),
+            refund_amount,
+        )?;
+
+        if self.purchase_order.is_settled() {
+            let close_accounts = CloseAccount {
+                account: self.escrow.to_account_info(),
+                destination: self.buyer.to_account_info(),
+                authority: self.platform_config.to_account_info(),
+            };
+            close_account(CpiContext::new_with_signer(self.token_program.to_account_info(), close_accounts, signer))?;
+        }
+
+        msg!(
+            "Purchase order {} cancelled: {} USDC over {} milestones back to the buyer",
+            self.purchase_order.order_id,
+            refund_amount,
+            cancelled_milestones
+        );
+        Ok(PurchaseOrderCancelled {
+            purchase_order: self.purchase_order.key(),
+            buyer: self.buyer.key(),
+            refund_amount,
+            cancelled_milestones,
+            cancelled_at: now,
+        })
+    }
+}
+
+pub fn create_purchase_order(
+    ctx: Context<CreatePurchaseOrder>,
+    order_id: String,
+    milestones: Vec<MilestoneArgs>,
+    deliver_by: i64,
+) -> Result<()> {
+    let event = ctx.accounts.create_purchase_order(order_id, milestones, deliver_by, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn release_milestone(ctx: Context<ReleaseMilestone>, milestone_index: u8) -> Result<()> {
+    let (released, processed) = ctx.accounts.release_milestone(milestone_index, &ctx.bumps)?;
+    emit_cpi!(processed);
+    emit_cpi!(released);
+    Ok(())
+}
+
+pub fn cancel_purchase_order(ctx: Context<CancelPurchaseOrder>) -> Result<()> {
+    let event = ctx.accounts.cancel_purchase_order()?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -4,18 +4,24 @@ use crate::state::platform::Platform;
 use crate::state::merchant::Merchant;
 use crate::state::payment::{Payment, PaymentStatus};
 
+use crate::events::PaymentRefunded;
 use crate::errors::PaymentError;
 use anchor_spl::token::{Transfer, transfer, Mint, TokenAccount, Token};
 use anchor_spl::associated_token::AssociatedToken;
 
+/// Return `refund_amount` to the customer. Can be repeated until the full
+/// payment amount has been refunded; each refund pulls a pro-rata share from
+/// the merchant and from the platform fee.
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction()]
 pub struct RefundPayment<'info> {
-    /// The merchant authority who can initiate refunds
-    #[account(mut)]
+    /// The merchant authority who can initiate refunds. Only authorizes; the
+    /// transaction fee can be paid by anyone, e.g. a relayer.
     pub merchant_authority: Signer<'info>,
     
     #[account(
+        mut,
         seeds = [Platform::SEED],
         bump = platform_config.bump,
     )]
@@ -23,16 +29,19 @@ pub struct RefundPayment<'info> {
     
     #[account(
         mut,
-        seeds = [Payment::SEED, payment_account.payment_id.as_bytes()],
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), payment_account.address_seed().as_ref()],
         bump = payment_account.bump,
-        constraint = payment_account.status == PaymentStatus::Completed @ PaymentError::PaymentAlreadyRefunded,
+        constraint = payment_account.status != PaymentStatus::Refunded @ PaymentError::PaymentAlreadyRefunded,
+        constraint = payment_account.status != PaymentStatus::Finalized @ PaymentError::PaymentAlreadyFinalized,
+        constraint = payment_account.status != PaymentStatus::Escrowed @ PaymentError::PaymentInEscrow,
+        constraint = payment_account.status != PaymentStatus::Cancelled @ PaymentError::PaymentCancelled,
         constraint = payment_account.merchant == merchant_account.key() @ PaymentError::RefundNotAuthorized,
     )]
     pub payment_account: Box<Account<'info, Payment>>,
     
     #[account(
         mut,
-        seeds = [b"merchant", merchant_account.merchant_id.as_bytes()],
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
         bump = merchant_account.bump,
         constraint = merchant_account.authority == merchant_authority.key() @ PaymentError::RefundNotAuthorized,
     )]
@@ -78,11 +87,31 @@ pub struct RefundPayment<'info> {
 }
 
 impl<'info> RefundPayment<'info> {
-    pub fn refund(&mut self) -> Result<()> {
-        let merchant_amount = self.payment_account.merchant_amount;
-        let fee_amount = self.payment_account.fee_amount;
+    pub fn refund(&mut self, refund_amount: u64) -> Result<PaymentRefunded> {
+        // A dispute shortfall stays payable after the window closes
+        require!(
+            Clock::get()?.unix_timestamp <= self.payment_account.refund_deadline
+                || refund_amount <= self.payment_account.dispute_shortfall,
+            PaymentError::RefundWindowClosed
+        );
+        self.execute_refund(refund_amount)
+    }
+
+    /// Move `refund_amount` back to the customer and update the books. Also used
+    /// to settle an approved refund request, which may land after the window
+    /// closed because the request was opened inside it.
+    pub fn execute_refund(&mut self, refund_amount: u64) -> Result<PaymentRefunded> {
+        require!(
+            refund_amount > 0 && refund_amount <= self.payment_account.refundable_amount(),
+            PaymentError::InvalidRefundAmount
+        );
+        let now = Clock::get()?.unix_timestamp;
+
+        let (merchant_amount, fee_amount) = self.payment_account
+            .refund_split(refund_amount)
+            .ok_or(PaymentError::CalculationError)?;
         
-        // Transfer merchant amount back to customer
+        // Transfer the merchant's share back to customer
         let transfer_merchant_accounts = Transfer {
             from: self.merchant_usdc.to_account_info(),
             to: self.customer_usdc.to_account_info(),
@@ -94,7 +123,7 @@ impl<'info> RefundPayment<'info> {
         );
         transfer(transfer_merchant_ctx, merchant_amount)?;
         
-        // Transfer fee back from treasury to customer (requires PDA signer)
+        // Transfer the fee share back from treasury to customer (requires PDA signer)
         let seeds = &[Platform::SEED, &[self.platform_config.bump]];
         let signer = &[&seeds[..]];
         
@@ -109,33 +138,127 @@ impl<'info> RefundPayment<'info> {
             signer,
         );
         transfer(transfer_fee_ctx, fee_amount)?;
-        
-        // Update payment status
-        self.payment_account.status = PaymentStatus::Refunded;
-        self.payment_account.refunded_at = Some(Clock::get()?.unix_timestamp);
-        
-        // Update merchant stats
-        self.merchant_account.transaction_count = self.merchant_account
+
+        record_refund(
+            &mut self.platform_config,
+            &mut self.merchant_account,
+            &mut self.payment_account,
+            refund_amount,
+            (merchant_amount, fee_amount),
+            now,
+        )
+    }
+}
+
+/// Book a refund whose tokens have already moved: release the fee leg from
+/// pending fees, advance the payment's refunded total and status, and unwind
+/// the merchant's stats. Shared by merchant refunds and dispute rulings.
+pub(crate) fn record_refund(
+    platform_config: &mut Platform,
+    merchant_account: &mut Account<Merchant>,
+    payment_account: &mut Account<Payment>,
+    refund_amount: u64,
+    (merchant_amount, fee_amount): (u64, u64),
+    now: i64,
+) -> Result<PaymentRefunded> {
+    // The fee leg comes out of the unfinalized fee bucket, never out of
+    // claimable revenue or payout escrow
+    platform_config.pending_fees = platform_config
+        .pending_fees
+        .checked_sub(fee_amount)
+        .ok_or(PaymentError::InsufficientPendingFees)?;
+
+    // Update payment status; refunds pay down a dispute shortfall first
+    payment_account.dispute_shortfall = payment_account.dispute_shortfall.saturating_sub(refund_amount);
+    payment_account.refunded_amount = payment_account.refunded_amount
+        .checked_add(refund_amount)
+        .ok_or(PaymentError::CalculationError)?;
+    let fully_refunded = payment_account.refunded_amount == payment_account.amount;
+    payment_account.status = if fully_refunded {
+        PaymentStatus::Refunded
+    } else {
+        PaymentStatus::PartiallyRefunded
+    };
+    payment_account.refunded_at = Some(now);
+
+    // Update merchant stats; the sale only stops counting once fully refunded
+    if fully_refunded {
+        merchant_account.transaction_count = merchant_account
             .transaction_count
             .saturating_sub(1);
-        self.merchant_account.volume = self.merchant_account
-            .volume
-            .saturating_sub(merchant_amount);
-        self.merchant_account.total_fees = self.merchant_account
-            .total_fees
-            .saturating_sub(fee_amount);
-        
-        msg!(
-            "Payment {} refunded: {} USDC to customer {}",
-            self.payment_account.payment_id,
-            self.payment_account.amount,
-            self.payment_account.customer
-        );
-        
-        Ok(())
     }
+    merchant_account.volume = merchant_account
+        .volume
+        .saturating_sub(merchant_amount);
+    merchant_account.total_fees = merchant_account
+        .total_fees
+        .saturating_sub(fee_amount);
+
+    msg!(
+        "Payment {} refunded: {} USDC to customer {} ({} of {} refunded)",
+        payment_account.payment_id,
+        refund_amount,
+        payment_account.customer,
+        payment_account.refunded_amount,
+        payment_account.amount
+    );
+
+    Ok(PaymentRefunded {
+        payment: payment_account.key(),
+        payment_id: payment_account.payment_id.clone(),
+        merchant: merchant_account.key(),
+        customer: payment_account.customer,
+        amount: payment_account.amount,
+        refund_amount,
+        merchant_amount,
+        fee_amount,
+        refunded_amount: payment_account.refunded_amount,
+        status: payment_account.status.clone(),
+        refunded_at: now,
+    })
+}
+
+/// Pay out a refund the platform ruled on: the merchant's share from the
+/// merchant reserve and the fee share from the treasury. Both are owned by the
+/// platform PDA, so this settles without the merchant's signature. The reserve
+/// is only needed when the merchant's share is non-zero.
+pub(crate) fn transfer_platform_refund<'info>(
+    token_program: &Program<'info, Token>,
+    platform_config: &Account<'info, Platform>,
+    merchant_reserve: Option<&Account<'info, TokenAccount>>,
+    platform_treasury_usdc: &Account<'info, TokenAccount>,
+    customer_usdc: &Account<'info, TokenAccount>,
+    (reserve_amount, fee_amount): (u64, u64),
+) -> Result<()> {
+    let seeds = &[Platform::SEED, &[platform_config.bump]];
+    let signer = &[&seeds[..]];
+
+    if reserve_amount > 0 {
+        let merchant_reserve = merchant_reserve.ok_or(PaymentError::InsufficientReserve)?;
+        let transfer_reserve_accounts = Transfer {
+            from: merchant_reserve.to_account_info(),
+            to: customer_usdc.to_account_info(),
+            authority: platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(token_program.to_account_info(), transfer_reserve_accounts, signer),
+            reserve_amount,
+        )?;
+    }
+
+    let transfer_fee_accounts = Transfer {
+        from: platform_treasury_usdc.to_account_info(),
+        to: customer_usdc.to_account_info(),
+        authority: platform_config.to_account_info(),
+    };
+    transfer(
+        CpiContext::new_with_signer(token_program.to_account_info(), transfer_fee_accounts, signer),
+        fee_amount,
+    )
 }
 
-pub fn handler(ctx: Context<RefundPayment>) -> Result<()> {
-    ctx.accounts.refund()
+pub fn handler(ctx: Context<RefundPayment>, refund_amount: u64) -> Result<()> {
+    let event = ctx.accounts.refund(refund_amount)?;
+    emit_cpi!(event);
+    Ok(())
 }
\ No newline at end of file
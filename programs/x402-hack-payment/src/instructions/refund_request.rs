@@ -0,0 +1,430 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, TokenAccount, Token};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Merchant, Payment, PaymentStatus, Platform, RefundReason, RefundRequest, RefundRequestStatus};
+use crate::events::{
+    PaymentRefunded, RefundRequestApproved, RefundRequestDenied, RefundRequestExpired, RefundRequestOpened,
+    RefundRequestResolved,
+};
+use crate::errors::PaymentError;
+use crate::instructions::refund::*;
+
+/// Customer asks the merchant for money back. One request per payment; it must
+/// be opened inside the payment's refund window.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct OpenRefundRequest<'info> {
+    #[account(mut)]
+    pub customer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Payment::SEED, payment_account.merchant.as_ref(), payment_account.address_seed().as_ref()],
+        bump = payment_account.bump,
+        has_one = customer @ PaymentError::Unauthorized,
+        constraint = payment_account.status != PaymentStatus::Refunded @ PaymentError::PaymentAlreadyRefunded,
+        constraint = payment_account.status != PaymentStatus::Finalized @ PaymentError::PaymentAlreadyFinalized,
+        constraint = payment_account.status != PaymentStatus::Escrowed @ PaymentError::PaymentInEscrow,
+        constraint = payment_account.status != PaymentStatus::Cancelled @ PaymentError::PaymentCancelled,
+    )]
+    pub payment_account: Account<'info, Payment>,
+
+    #[account(
+        init,
+        payer = customer,
+        space = 8 + RefundRequest::INIT_SPACE,
+        seeds = [RefundRequest::SEED, payment_account.key().as_ref()],
+        bump,
+    )]
+    pub refund_request: Account<'info, RefundRequest>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> OpenRefundRequest<'info> {
+    pub fn open_refund_request(
+        &mut self,
+        amount: u64,
+        reason: RefundReason,
+        evidence_hash: [u8; 32],
+        bumps: &OpenRefundRequestBumps,
+    ) -> Result<RefundRequestOpened> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(now <= self.payment_account.refund_deadline, PaymentError::RefundWindowClosed);
+        require!(
+            amount > 0 && amount <= self.payment_account.refundable_amount(),
+            PaymentError::InvalidRefundAmount
+        );
+
+        let respond_by = now
+            .checked_add(RefundRequest::RESPONSE_WINDOW)
+            .ok_or(PaymentError::CalculationError)?;
+        let expires_at = respond_by
+            .checked_add(RefundRequest::REVIEW_WINDOW)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.refund_request.set_inner(RefundRequest {
+            payment: self.payment_account.key(),
+            customer: self.customer.key(),
+            merchant: self.payment_account.merchant,
+            amount,
+            reason,
+            evidence_hash,
+            response_hash: None,
+            status: RefundRequestStatus::Open,
+            opened_at: now,
+            respond_by,
+            expires_at,
+            resolved_at: None,
+            bump: bumps.refund_request,
+        });
+        self.payment_account.refund_request_open = true;
+
+        msg!(
+            "Refund request opened on payment {}: {} USDC ({:?})",
+            self.payment_account.payment_id,
+            amount,
+            reason
+        );
+        Ok(RefundRequestOpened {
+            refund_request: self.refund_request.key(),
+            payment: self.payment_account.key(),
+            merchant: self.payment_account.merchant,
+            customer: self.customer.key(),
+            amount,
+            reason,
+            evidence_hash,
+            respond_by,
+            expires_at,
+            opened_at: now,
+        })
+    }
+}
+
+/// Merchant accepts a request and pays it out.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ApproveRefundRequest<'info> {
+    pub refund: RefundPayment<'info>,
+
+    #[account(
+        mut,
+        seeds = [RefundRequest::SEED, refund.payment_account.key().as_ref()],
+        bump = refund_request.bump,
+        constraint = refund_request.status == RefundRequestStatus::Open @ PaymentError::RefundRequestNotOpen,
+    )]
+    pub refund_request: Account<'info, RefundRequest>,
+}
+
+impl<'info> ApproveRefundRequest<'info> {
+    /// Pays out the requested amount, capped at what is still refundable in
+    /// case the merchant already refunded part of it directly.
+    pub fn approve_refund_request(&mut self) -> Result<(RefundRequestApproved, PaymentRefunded)> {
+        let amount = self
+            .refund_request
+            .amount
+            .min(self.refund.payment_account.refundable_amount());
+        let refunded = self.refund.execute_refund(amount)?;
+
+        let now = refunded.refunded_at;
+        let request = &mut self.refund_request;
+        request.status = RefundRequestStatus::Approved;
+        request.resolved_at = Some(now);
+        self.refund.payment_account.refund_request_open = false;
+
+        msg!("Refund request on payment {} approved", self.refund.payment_account.payment_id);
+        Ok((
+            RefundRequestApproved {
+                refund_request: request.key(),
+                payment: request.payment,
+                merchant: request.merchant,
+                amount,
+                approved_at: now,
+            },
+            refunded,
+        ))
+    }
+}
+
+/// Merchant rejects a request before its response deadline.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DenyRefundRequest<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), payment_account.address_seed().as_ref()],
+        bump = payment_account.bump,
+    )]
+    pub payment_account: Account<'info, Payment>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [RefundRequest::SEED, payment_account.key().as_ref()],
+        bump = refund_request.bump,
+        constraint = refund_request.status == RefundRequestStatus::Open @ PaymentError::RefundRequestNotOpen,
+    )]
+    pub refund_request: Account<'info, RefundRequest>,
+}
+
+impl<'info> DenyRefundRequest<'info> {
+    pub fn deny_refund_request(&mut self, response_hash: [u8; 32]) -> Result<RefundRequestDenied> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(!self.refund_request.is_escalated(now), PaymentError::RefundRequestEscalated);
+
+        let request = &mut self.refund_request;
+        request.status = RefundRequestStatus::Denied;
+        request.response_hash = Some(response_hash);
+        request.resolved_at = Some(now);
+        self.payment_account.refund_request_open = false;
+
+        msg!("Refund request on payment {} denied", self.payment_account.payment_id);
+        Ok(RefundRequestDenied {
+            refund_request: request.key(),
+            payment: request.payment,
+            merchant: request.merchant,
+            response_hash,
+            denied_at: now,
+        })
+    }
+}
+
+/// Platform ruling on a request the merchant left unanswered past `respond_by`.
+/// An approval is paid out here and now: the merchant's share from the
+/// merchant reserve, the fee share from the treasury.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ResolveRefundRequest<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), payment_account.address_seed().as_ref()],
+        bump = payment_account.bump,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    #[account(
+        mut,
+        seeds = [RefundRequest::SEED, payment_account.key().as_ref()],
+        bump = refund_request.bump,
+        constraint = refund_request.status == RefundRequestStatus::Open @ PaymentError::RefundRequestNotOpen,
+    )]
+    pub refund_request: Box<Account<'info, RefundRequest>>,
+
+    /// CHECK: The original customer who made the payment
+    #[account(
+        constraint = customer.key() == payment_account.customer @ PaymentError::RefundNotAuthorized
+    )]
+    pub customer: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    /// Only needed to pay out an approval
+    #[account(
+        mut,
+        seeds = [Merchant::RESERVE_SEED, merchant_account.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub merchant_reserve: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [Platform::TREASURY_SEED],
+        bump = platform_config.treasury_bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub platform_treasury_usdc: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> ResolveRefundRequest<'info> {
+    /// Approval pays out the requested amount, capped at what is still refundable.
+    pub fn resolve_refund_request(
+        &mut self,
+        approve: bool,
+        response_hash: [u8; 32],
+    ) -> Result<(RefundRequestResolved, Option<PaymentRefunded>)> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(self.refund_request.is_escalated(now), PaymentError::RefundRequestNotEscalated);
+        require!(!self.refund_request.is_expired(now), PaymentError::RefundRequestExpired);
+
+        let mut refunded = None;
+        let mut refund_amount = 0;
+        if approve {
+            refund_amount = self
+                .refund_request
+                .amount
+                .min(self.payment_account.refundable_amount());
+            let (reserve_amount, fee_amount) = self.payment_account
+                .refund_split(refund_amount)
+                .ok_or(PaymentError::CalculationError)?;
+            // Reserve locked for open disputes isn't available to refund requests
+            let available = self.merchant_reserve
+                .as_ref()
+                .map_or(0, |reserve| reserve.amount.saturating_sub(self.merchant_account.reserve_locked));
+            require!(available >= reserve_amount, PaymentError::InsufficientReserve);
+
+            transfer_platform_refund(
+                &self.token_program,
+                &self.platform_config,
+                self.merchant_reserve.as_ref(),
+                &self.platform_treasury_usdc,
+                &self.customer_usdc,
+                (reserve_amount, fee_amount),
+            )?;
+            refunded = Some(record_refund(
+                &mut self.platform_config,
+                &mut self.merchant_account,
+                &mut self.payment_account,
+                refund_amount,
+                (reserve_amount, fee_amount),
+                now,
+            )?);
+        }
+
+        let request = &mut self.refund_request;
+        request.response_hash = Some(response_hash);
+        request.status = if approve { RefundRequestStatus::Approved } else { RefundRequestStatus::Denied };
+        request.resolved_at = Some(now);
+        self.payment_account.refund_request_open = false;
+
+        msg!(
+            "Refund request on payment {} resolved by platform: {}",
+            self.payment_account.payment_id,
+            if approve { "approved" } else { "denied" }
+        );
+        Ok((
+            RefundRequestResolved {
+                refund_request: request.key(),
+                payment: request.payment,
+                authority: self.authority.key(),
+                approved: approve,
+                refund_amount,
+                response_hash,
+                resolved_at: now,
+            },
+            refunded,
+        ))
+    }
+}
+
+/// Close a request nobody answered before `expires_at` so the payment can be
+/// finalized. Permissionless. The customer can still dispute the payment
+/// while the dispute window is open.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExpireRefundRequest<'info> {
+    #[account(
+        mut,
+        seeds = [Payment::SEED, payment_account.merchant.as_ref(), payment_account.address_seed().as_ref()],
+        bump = payment_account.bump,
+    )]
+    pub payment_account: Account<'info, Payment>,
+
+    #[account(
+        mut,
+        seeds = [RefundRequest::SEED, payment_account.key().as_ref()],
+        bump = refund_request.bump,
+        constraint = refund_request.status == RefundRequestStatus::Open @ PaymentError::RefundRequestNotOpen,
+    )]
+    pub refund_request: Account<'info, RefundRequest>,
+}
+
+impl<'info> ExpireRefundRequest<'info> {
+    pub fn expire_refund_request(&mut self) -> Result<RefundRequestExpired> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(self.refund_request.is_expired(now), PaymentError::RefundRequestNotExpired);
+
+        let request = &mut self.refund_request;
+        request.status = RefundRequestStatus::Expired;
+        request.resolved_at = Some(now);
+        self.payment_account.refund_request_open = false;
+
+        msg!("Refund request on payment {} expired unanswered", self.payment_account.payment_id);
+        Ok(RefundRequestExpired {
+            refund_request: request.key(),
+            payment: request.payment,
+            merchant: request.merchant,
+            expired_at: now,
+        })
+    }
+}
+
+pub fn open_refund_request(
+    ctx: Context<OpenRefundRequest>,
+    amount: u64,
+    reason: RefundReason,
+    evidence_hash: [u8; 32],
+) -> Result<()> {
+    let event = ctx.accounts.open_refund_request(amount, reason, evidence_hash, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn approve_refund_request(ctx: Context<ApproveRefundRequest>) -> Result<()> {
+    let (approved, refunded) = ctx.accounts.approve_refund_request()?;
+    emit_cpi!(refunded);
+    emit_cpi!(approved);
+    Ok(())
+}
+
+pub fn deny_refund_request(ctx: Context<DenyRefundRequest>, response_hash: [u8; 32]) -> Result<()> {
+    let event = ctx.accounts.deny_refund_request(response_hash)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn resolve_refund_request(ctx: Context<ResolveRefundRequest>, approve: bool, response_hash: [u8; 32]) -> Result<()> {
+    let (resolved, refunded) = ctx.accounts.resolve_refund_request(approve, response_hash)?;
+    if let Some(refunded) = refunded {
+        emit_cpi!(refunded);
+    }
+    emit_cpi!(resolved);
+    Ok(())
+}
+
+pub fn expire_refund_request(ctx: Context<ExpireRefundRequest>) -> Result<()> {
+    let event = ctx.accounts.expire_refund_request()?;
+    emit_cpi!(event);
+    Ok(())
+}
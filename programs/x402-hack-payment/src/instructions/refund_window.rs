@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Merchant, Platform};
+use crate::events::MerchantRefundWindowUpdated;
+use crate::errors::PaymentError;
+
+/// Set or clear the merchant's own refund window. Only affects payments made
+/// afterwards — each payment snapshots its deadline when it is processed.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetMerchantRefundWindow<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+}
+
+impl<'info> SetMerchantRefundWindow<'info> {
+    pub fn set_merchant_refund_window(&mut self, refund_window: Option<i64>) -> Result<MerchantRefundWindowUpdated> {
+        if let Some(refund_window) = refund_window {
+            require!(
+                (0..=Platform::MAX_REFUND_WINDOW).contains(&refund_window),
+                PaymentError::InvalidRefundWindow
+            );
+        }
+
+        let old_refund_window = self.merchant_account.refund_window;
+        self.merchant_account.refund_window = refund_window;
+
+        msg!(
+            "Merchant {} refund window changed from {:?}s to {:?}s",
+            self.merchant_account.merchant_id,
+            old_refund_window,
+            refund_window
+        );
+        Ok(MerchantRefundWindowUpdated {
+            merchant: self.merchant_account.key(),
+            old_refund_window,
+            new_refund_window: refund_window,
+            updated_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<SetMerchantRefundWindow>, refund_window: Option<i64>) -> Result<()> {
+    let event = ctx.accounts.set_merchant_refund_window(refund_window)?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Transfer, transfer, Mint, TokenAccount, Token};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Merchant, Platform};
+use crate::events::{MerchantReserveFunded, MerchantReserveWithdrawn};
+use crate::errors::PaymentError;
+
+/// Top up the merchant's reserve. The reserve is a USDC account owned by the
+/// platform PDA so dispute rulings can be paid from it without the merchant.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FundMerchantReserve<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Account<'info, Platform>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = usdc_mint,
+        token::authority = authority,
+    )]
+    pub authority_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [Merchant::RESERVE_SEED, merchant_account.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub merchant_reserve: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> FundMerchantReserve<'info> {
+    pub fn fund_merchant_reserve(&mut self, amount: u64) -> Result<MerchantReserveFunded> {
+        require!(amount > 0, PaymentError::InvalidAmount);
+
+        let cpi_accounts = Transfer {
+            from: self.authority_usdc.to_account_info(),
+            to: self.merchant_reserve.to_account_info(),
+            authority: self.authority.to_account_info(),
+        };
+        transfer(CpiContext::new(self.token_program.to_account_info(), cpi_accounts), amount)?;
+        self.merchant_reserve.reload()?;
+
+        msg!(
+            "Merchant {} reserve funded with {} USDC (balance {})",
+            self.merchant_account.merchant_id,
+            amount,
+            self.merchant_reserve.amount
+        );
+        Ok(MerchantReserveFunded {
+            merchant: self.merchant_account.key(),
+            reserve: self.merchant_reserve.key(),
+            amount,
+            balance: self.merchant_reserve.amount,
+            funded_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+/// Withdraw from the reserve to the merchant's settlement wallet. Whatever open
+/// disputes have locked stays behind.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawMerchantReserve<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Account<'info, Platform>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::RESERVE_SEED, merchant_account.key().as_ref()],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub merchant_reserve: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = merchant_account.settlement_wallet,
+    )]
+    pub merchant_usdc: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> WithdrawMerchantReserve<'info> {
+    pub fn withdraw_merchant_reserve(&mut self, amount: u64) -> Result<MerchantReserveWithdrawn> {
+        require!(amount > 0, PaymentError::InvalidAmount);
+        require!(self.merchant_reserve.amount >= amount, PaymentError::InsufficientReserve);
+        require!(
+            self.merchant_reserve.amount - amount >= self.merchant_account.reserve_locked,
+            PaymentError::ReserveLocked
+        );
+
+        let seeds = &[Platform::SEED, &[self.platform_config.bump]];
+        let signer = &[&seeds[..]];
+        let cpi_accounts = Transfer {
+            from: self.merchant_reserve.to_account_info(),
+            to: self.merchant_usdc.to_account_info(),
+            authority: self.platform_config.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer),
+            amount,
+        )?;
+        self.merchant_reserve.reload()?;
+
+        msg!(
+            "Merchant {} withdrew {} USDC from reserve (balance {})",
+            self.merchant_account.merchant_id,
+            amount,
+            self.merchant_reserve.amount
+        );
+        Ok(MerchantReserveWithdrawn {
+            merchant: self.merchant_account.key(),
+            destination: self.merchant_usdc.key(),
+            amount,
+            balance: self.merchant_reserve.amount,
+            withdrawn_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn fund_merchant_reserve(ctx: Context<FundMerchantReserve>, amount: u64) -> Result<()> {
+    let event = ctx.accounts.fund_merchant_reserve(amount)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn withdraw_merchant_reserve(ctx: Context<WithdrawMerchantReserve>, amount: u64) -> Result<()> {
+    let event = ctx.accounts.withdraw_merchant_reserve(amount)?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -0,0 +1,300 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::{self as instructions_sysvar, get_instruction_relative};
+use anchor_spl::token::{Mint, TokenAccount, Token, Transfer, transfer};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Customer, FeeMode, Merchant, Payment, PaymentStatus, Platform};
+use crate::events::{PaymentIntentExecuted, PaymentProcessed};
+use crate::errors::PaymentError;
+use crate::instructions::payment::payment_terms;
+
+/// Payment the customer signs off-chain. The signed message is
+/// `PaymentIntent::DOMAIN` followed by the Borsh encoding of this struct.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PaymentIntent {
+    /// Merchant PDA being paid
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub payment_id: String,
+    /// Unix time after which the intent can no longer be submitted
+    pub expiry: i64,
+    /// Must equal the customer's `next_intent_nonce`
+    pub nonce: u64,
+}
+
+impl PaymentIntent {
+    /// Prefix on every signed intent so the signature can't be replayed as another message.
+    pub const DOMAIN: &'static [u8] = b"settlr:payment-intent:v1";
+
+    pub fn message(&self) -> Result<Vec<u8>> {
+        let mut message = Self::DOMAIN.to_vec();
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+}
+
+/// Check that the instruction right before this one is an Ed25519 program
+/// verification of `message` by `signer`. The runtime has already rejected
+/// the transaction if the signature itself was bad.
+fn verify_intent_signature(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let ix = get_instruction_relative(-1, instructions)
+        .map_err(|_| error!(PaymentError::MissingIntentSignature))?;
+    require_keys_eq!(ix.program_id, ed25519_program::ID, PaymentError::MissingIntentSignature);
+
+    // Header: signature count, padding, then seven u16 offsets for the single signature
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, PaymentError::InvalidIntentSignature);
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let (signature_ix, public_key_offset, public_key_ix) = (read_u16(4), read_u16(6) as usize, read_u16(8));
+    let (message_offset, message_size, message_ix) = (read_u16(10) as usize, read_u16(12) as usize, read_u16(14));
+
+    // Everything must live inside the Ed25519 instruction itself
+    require!(
+        signature_ix == u16::MAX && public_key_ix == u16::MAX && message_ix == u16::MAX,
+        PaymentError::InvalidIntentSignature
+    );
+    let public_key = data.get(public_key_offset..public_key_offset + 32);
+    let signed_message = data.get(message_offset..message_offset + message_size);
+    require!(
+        public_key == Some(signer.as_ref()) && signed_message == Some(message),
+        PaymentError::InvalidIntentSignature
+    );
+    Ok(())
+}
+
+/// Any relayer submits a payment the customer signed off-chain. The customer
+/// approves the `Platform::DELEGATE_SEED` PDA through `approve_delegate`
+/// beforehand; the delegate then pulls the payment with the regular fee split.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(intent: PaymentIntent)]
+pub struct ProcessSignedPayment<'info> {
+    /// Relayer submitting the intent; pays rent and the transaction fee
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Authenticated by the Ed25519 signature over the intent
+    pub customer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        constraint = merchant_account.key() == intent.merchant @ PaymentError::IntentMerchantMismatch,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [Payment::SEED, merchant_account.key().as_ref(), Payment::id_seed(&intent.payment_id).as_ref()],
+        bump,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Customer::INIT_SPACE,
+        seeds = [b"customer", customer.key().as_ref()],
+        bump,
+    )]
+    pub customer_account: Box<Account<'info, Customer>>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = merchant_account.settlement_wallet,
+    )]
+    pub merchant_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [Platform::TREASURY_SEED],
+        bump = platform_config.treasury_bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub platform_treasury_usdc: Account<'info, TokenAccount>,
+
+    /// CHECK: Delegate PDA the customer approved; signs the transfers
+    #[account(
+        seeds = [Platform::DELEGATE_SEED],
+        bump,
+    )]
+    pub delegate: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar, read for the preceding Ed25519 instruction
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ProcessSignedPayment<'info> {
+    pub fn process_signed_payment(
+        &mut self,
+        intent: PaymentIntent,
+        bumps: &ProcessSignedPaymentBumps,
+    ) -> Result<(PaymentIntentExecuted, PaymentProcessed)> {
+        let now = Clock::get()?.unix_timestamp;
+        verify_intent_signature(&self.instructions, &self.customer.key(), &intent.message()?)?;
+        require!(now <= intent.expiry, PaymentError::IntentExpired);
+
+        let PaymentIntent { amount, payment_id, nonce, .. } = intent;
+        let (fee_bps, fee_source, fee, merchant_amount) =
+            payment_terms(&self.platform_config, &self.merchant_account, &payment_id, amount, FeeMode::Inclusive)?;
+
+        self.customer_account.init_if_new(self.customer.key(), now, bumps.customer_account);
+
+        // Consume the nonce so the same intent can't be submitted twice
+        require!(nonce == self.customer_account.next_intent_nonce, PaymentError::InvalidIntentNonce);
+        self.customer_account.next_intent_nonce = nonce
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+
+        let seeds = &[Platform::DELEGATE_SEED, &[bumps.delegate]];
+        let signer = &[&seeds[..]];
+
+        let transfer_to_merchant_accounts = Transfer {
+            from: self.customer_usdc.to_account_info(),
+            to: self.merchant_usdc.to_account_info(),
+            authority: self.delegate.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_to_merchant_accounts, signer),
+            merchant_amount,
+        )?;
+
+        let transfer_fee_accounts = Transfer {
+            from: self.customer_usdc.to_account_info(),
+            to: self.platform_treasury_usdc.to_account_info(),
+            authority: self.delegate.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_fee_accounts, signer),
+            fee,
+        )?;
+
+        self.platform_config.pending_fees = self.platform_config
+            .pending_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.customer_account.transaction_count = self.customer_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.customer_account.total_spent = self.customer_account
+            .total_spent
+            .checked_add(amount)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.merchant_account.transaction_count = self.merchant_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.volume = self.merchant_account
+            .volume
+            .checked_add(merchant_amount)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.total_fees = self.merchant_account
+            .total_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        let payment = &mut self.payment_account;
+        payment.payment_id = payment_id.clone();
+        payment.customer = self.customer.key();
+        payment.merchant = self.merchant_account.key();
+        payment.amount = amount;
+        payment.fee_amount = fee;
+        payment.fee_bps = fee_bps;
+        payment.fee_source = fee_source;
+        payment.fee_mode = FeeMode::Inclusive;
+        payment.merchant_amount = merchant_amount;
+        payment.refunded_amount = 0;
+        payment.status = PaymentStatus::Completed;
+        payment.created_at = now;
+        payment.refund_deadline = now
+            .checked_add(self.merchant_account.refund_window(self.platform_config.refund_window))
+            .ok_or(PaymentError::CalculationError)?;
+        payment.refunded_at = None;
+        payment.refund_request_open = false;
+        payment.dispute_open = false;
+        payment.escrow_release_at = None;
+        payment.requires_attestation = false;
+        payment.invoice_breakdown = None;
+        payment.sponsor_fee = 0;
+        payment.sponsor = None;
+        payment.parent = None;
+        payment.parent_index = 0;
+        payment.dispute_shortfall = 0;
+        payment.bump = bumps.payment_account;
+
+        msg!(
+            "Signed payment {} (nonce {}) processed: {} USDC to merchant {}, {} USDC fee to platform",
+            payment_id,
+            nonce,
+            merchant_amount,
+            self.merchant_account.merchant_id,
+            fee
+        );
+        Ok((
+            PaymentIntentExecuted {
+                payment: payment.key(),
+                customer: self.customer.key(),
+                submitted_by: self.payer.key(),
+                nonce,
+                expiry: intent.expiry,
+                fee_mode: FeeMode::Inclusive,
+            },
+            PaymentProcessed {
+                payment: payment.key(),
+                payment_id,
+                merchant: self.merchant_account.key(),
+                customer: self.customer.key(),
+                amount,
+                fee_amount: fee,
+                fee_bps,
+                fee_source,
+                fee_mode: FeeMode::Inclusive,
+                merchant_amount,
+                sponsor_fee: 0,
+                sponsor: None,
+                created_at: now,
+                refund_deadline: payment.refund_deadline,
+            },
+        ))
+    }
+}
+
+pub fn process_signed_payment(ctx: Context<ProcessSignedPayment>, intent: PaymentIntent) -> Result<()> {
+    let (executed, processed) = ctx.accounts.process_signed_payment(intent, &ctx.bumps)?;
+    emit_cpi!(processed);
+    emit_cpi!(executed);
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Platform;
+use crate::events::{SponsorAdded, SponsorRemoved};
+use crate::errors::PaymentError;
+
+/// Register or remove a relayer that sponsors transactions and may be
+/// reimbursed through `process_payment`'s sponsor fee.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ManageSponsor<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub platform_config: Account<'info, Platform>,
+}
+
+impl<'info> ManageSponsor<'info> {
+    pub fn add_sponsor(&mut self, sponsor: Pubkey) -> Result<SponsorAdded> {
+        let config = &mut self.platform_config;
+        require!(!config.is_sponsor(&sponsor), PaymentError::SponsorAlreadyRegistered);
+        require!(config.sponsors.len() < Platform::MAX_SPONSORS, PaymentError::SponsorRegistryFull);
+
+        config.sponsors.push(sponsor);
+
+        msg!("Sponsor {} added", sponsor);
+        Ok(SponsorAdded {
+            sponsor,
+            added_at: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    pub fn remove_sponsor(&mut self, sponsor: Pubkey) -> Result<SponsorRemoved> {
+        let config = &mut self.platform_config;
+        let index = config
+            .sponsors
+            .iter()
+            .position(|key| *key == sponsor)
+            .ok_or(PaymentError::SponsorNotFound)?;
+
+        config.sponsors.swap_remove(index);
+
+        msg!("Sponsor {} removed", sponsor);
+        Ok(SponsorRemoved {
+            sponsor,
+            removed_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn add_sponsor(ctx: Context<ManageSponsor>, sponsor: Pubkey) -> Result<()> {
+    let event = ctx.accounts.add_sponsor(sponsor)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn remove_sponsor(ctx: Context<ManageSponsor>, sponsor: Pubkey) -> Result<()> {
+    let event = ctx.accounts.remove_sponsor(sponsor)?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -0,0 +1,451 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Customer, FeeMode, Merchant, Payment, PaymentStatus, Platform, Subscription, SubscriptionStatus};
+use crate::events::{PaymentProcessed, SubscriptionCancelled, SubscriptionCharged, SubscriptionCreated};
+use crate::errors::PaymentError;
+use crate::instructions::delegate::approve_additional;
+use crate::instructions::payment::payment_terms;
+
+/// Customer signs up for a recurring charge and approves the program delegate
+/// PDA for `amount * max_cycles` on top of any allowance it already holds for
+/// their other subscriptions and signed intents.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(subscription_id: String)]
+pub struct CreateSubscription<'info> {
+    /// Pays rent for the new accounts. May be a relayer so the customer
+    /// doesn't need SOL; can be the same key as `customer`.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Owner of `customer_usdc`; signs the delegate approval
+    pub customer: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Subscription::INIT_SPACE,
+        seeds = [Subscription::SEED, merchant_account.key().as_ref(), Subscription::id_seed(&subscription_id).as_ref()],
+        bump,
+    )]
+    pub subscription: Box<Account<'info, Subscription>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + Customer::INIT_SPACE,
+        seeds = [b"customer", customer.key().as_ref()],
+        bump,
+    )]
+    pub customer_account: Box<Account<'info, Customer>>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA approved as delegate on `customer_usdc`; holds no data
+    #[account(
+        seeds = [Platform::DELEGATE_SEED],
+        bump,
+    )]
+    pub delegate: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateSubscription<'info> {
+    pub fn create_subscription(
+        &mut self,
+        subscription_id: String,
+        amount: u64,
+        interval: i64,
+        max_cycles: u32,
+        bumps: &CreateSubscriptionBumps,
+    ) -> Result<SubscriptionCreated> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            !subscription_id.is_empty() && subscription_id.len() <= Subscription::MAX_ID_LEN,
+            PaymentError::InvalidPaymentId
+        );
+        require!(interval > 0, PaymentError::InvalidInterval);
+        require!(max_cycles > 0, PaymentError::InvalidMaxCycles);
+        // Same checks a one-off payment of this amount would face
+        payment_terms(&self.platform_config, &self.merchant_account, &subscription_id, amount, FeeMode::Inclusive)?;
+
+        self.customer_account.init_if_new(self.customer.key(), now, bumps.customer_account);
+
+        let cap = amount
+            .checked_mul(max_cycles as u64)
+            .ok_or(PaymentError::CalculationError)?;
+        let delegated_amount = approve_additional(
+            &self.token_program,
+            &self.customer_usdc,
+            &self.delegate.to_account_info(),
+            &self.customer,
+            cap,
+        )?;
+
+        self.subscription.set_inner(Subscription {
+            subscription_id: subscription_id.clone(),
+            customer: self.customer.key(),
+            merchant: self.merchant_account.key(),
+            amount,
+            interval,
+            next_due: now,
+            max_cycles,
+            cycles_charged: 0,
+            status: SubscriptionStatus::Active,
+            created_at: now,
+            cancelled_at: None,
+            bump: bumps.subscription,
+        });
+
+        msg!(
+            "Subscription {} created: {} USDC every {}s, up to {} cycles",
+            subscription_id,
+            amount,
+            interval,
+            max_cycles
+        );
+        Ok(SubscriptionCreated {
+            subscription: self.subscription.key(),
+            subscription_id,
+            customer: self.customer.key(),
+            merchant: self.merchant_account.key(),
+            amount,
+            interval,
+            max_cycles,
+            first_due: now,
+            delegated_amount,
+            created_at: now,
+        })
+    }
+}
+
+/// Permissionless crank: pull one due cycle from the customer through the
+/// delegate PDA, with the same fee split as `process_payment`.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ChargeSubscription<'info> {
+    /// Pays rent for the cycle's payment record
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Box<Account<'info, Platform>>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Box<Account<'info, Merchant>>,
+
+    #[account(
+        mut,
+        seeds = [Subscription::SEED, merchant_account.key().as_ref(), Subscription::id_seed(&subscription.subscription_id).as_ref()],
+        bump = subscription.bump,
+        constraint = subscription.status == SubscriptionStatus::Active @ PaymentError::SubscriptionNotActive,
+    )]
+    pub subscription: Box<Account<'info, Subscription>>,
+
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + Payment::INIT_SPACE,
+        seeds = [
+            Payment::SEED,
+            merchant_account.key().as_ref(),
+            Payment::derived_seed(&subscription.key(), subscription.cycles_charged as u64).as_ref(),
+        ],
+        bump,
+    )]
+    pub payment_account: Box<Account<'info, Payment>>,
+
+    #[account(
+        mut,
+        seeds = [b"customer", subscription.customer.as_ref()],
+        bump = customer_account.bump,
+    )]
+    pub customer_account: Box<Account<'info, Customer>>,
+
+    #[account(
+        constraint = usdc_mint.key() == platform_config.usdc_mint @ PaymentError::InvalidTokenMint
+    )]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = subscription.customer,
+    )]
+    pub customer_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = merchant_account.settlement_wallet,
+    )]
+    pub merchant_usdc: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [Platform::TREASURY_SEED],
+        bump = platform_config.treasury_bump,
+        token::mint = usdc_mint,
+        token::authority = platform_config,
+    )]
+    pub platform_treasury_usdc: Account<'info, TokenAccount>,
+
+    /// CHECK: Delegate PDA the customer approved at signup; signs the transfers
+    #[account(
+        seeds = [Platform::DELEGATE_SEED],
+        bump,
+    )]
+    pub delegate: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ChargeSubscription<'info> {
+    pub fn charge_subscription(
+        &mut self,
+        bumps: &ChargeSubscriptionBumps,
+    ) -> Result<(SubscriptionCharged, PaymentProcessed)> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= self.subscription.next_due, PaymentError::SubscriptionNotDue);
+
+        let cycle = self.subscription.cycles_charged;
+        let payment_id = self.subscription.cycle_payment_id(cycle);
+        let amount = self.subscription.amount;
+        let (fee_bps, fee_source, fee, merchant_amount) =
+            payment_terms(&self.platform_config, &self.merchant_account, &payment_id, amount, FeeMode::Inclusive)?;
+
+        let seeds = &[Platform::DELEGATE_SEED, &[bumps.delegate]];
+        let signer = &[&seeds[..]];
+
+        let transfer_to_merchant_accounts = Transfer {
+            from: self.customer_usdc.to_account_info(),
+            to: self.merchant_usdc.to_account_info(),
+            authority: self.delegate.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_to_merchant_accounts, signer),
+            merchant_amount,
+        )?;
+
+        let transfer_fee_accounts = Transfer {
+            from: self.customer_usdc.to_account_info(),
+            to: self.platform_treasury_usdc.to_account_info(),
+            authority: self.delegate.to_account_info(),
+        };
+        transfer(
+            CpiContext::new_with_signer(self.token_program.to_account_info(), transfer_fee_accounts, signer),
+            fee,
+        )?;
+
+        self.platform_config.pending_fees = self.platform_config
+            .pending_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.customer_account.transaction_count = self.customer_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.customer_account.total_spent = self.customer_account
+            .total_spent
+            .checked_add(amount)
+            .ok_or(PaymentError::CalculationError)?;
+
+        self.merchant_account.transaction_count = self.merchant_account
+            .transaction_count
+            .checked_add(1)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.volume = self.merchant_account
+            .volume
+            .checked_add(merchant_amount)
+            .ok_or(PaymentError::CalculationError)?;
+        self.merchant_account.total_fees = self.merchant_account
+            .total_fees
+            .checked_add(fee)
+            .ok_or(PaymentError::CalculationError)?;
+
+        // Advance the schedule. Cycles missed while nobody cranked are
+        // charged one per call rather than skipped.
+        let subscription = &mut self.subscription;
+        subscription.cycles_charged = cycle + 1;
+        subscription.next_due = subscription.next_due
+            .checked_add(subscription.interval)
+            .ok_or(PaymentError::CalculationError)?;
+        let completed = subscription.cycles_charged >= subscription.max_cycles;
+        if completed {
+            subscription.status = SubscriptionStatus::Completed;
+        }
+
+        // Record the cycle as a regular payment, addressed from the subscription and cycle
+        let payment = &mut self.payment_account;
+        payment.payment_id = payment_id.clone();
+        payment.customer = subscription.customer;
+        payment.merchant = self.merchant_account.key();
+        payment.amount = amount;
+        payment.fee_amount = fee;
+        payment.fee_bps = fee_bps;
+        payment.fee_source = fee_source;
+        payment.fee_mode = FeeMode::Inclusive;
+        payment.merchant_amount = merchant_amount;
+        payment.refunded_amount = 0;
+        payment.status = PaymentStatus::Completed;
+        payment.created_at = now;
+        payment.refund_deadline = now
+            .checked_add(self.merchant_account.refund_window(self.platform_config.refund_window))
+            .ok_or(PaymentError::CalculationError)?;
+        payment.refunded_at = None;
+        payment.refund_request_open = false;
+        payment.dispute_open = false;
+        payment.escrow_release_at = None;
+        payment.requires_attestation = false;
+        payment.invoice_breakdown = None;
+        payment.sponsor_fee = 0;
+        payment.sponsor = None;
+        payment.parent = Some(subscription.key());
+        payment.parent_index = cycle as u64;
+        payment.dispute_shortfall = 0;
+        payment.bump = bumps.payment_account;
+
+        msg!(
+            "Subscription {} cycle {} charged: {} USDC to merchant {}, {} USDC fee to platform",
+            subscription.subscription_id,
+            cycle,
+            merchant_amount,
+            self.merchant_account.merchant_id,
+            fee
+        );
+        Ok((
+            SubscriptionCharged {
+                subscription: subscription.key(),
+                payment: payment.key(),
+                cycle,
+                amount,
+                fee_mode: FeeMode::Inclusive,
+                next_due: subscription.next_due,
+                completed,
+                charged_at: now,
+            },
+            PaymentProcessed {
+                payment: payment.key(),
+                payment_id,
+                merchant: self.merchant_account.key(),
+                customer: subscription.customer,
+                amount,
+                fee_amount: fee,
+                fee_bps,
+                fee_source,
+                fee_mode: FeeMode::Inclusive,
+                merchant_amount,
+                sponsor_fee: 0,
+                sponsor: None,
+                created_at: now,
+                refund_deadline: payment.refund_deadline,
+            },
+        ))
+    }
+}
+
+/// Customer or merchant stops future charges. The customer can also revoke
+/// the token delegate directly; remaining allowance is simply never used.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelSubscription<'info> {
+    /// Customer, or the merchant authority
+    #[account(
+        constraint = signer.key() == subscription.customer
+            || signer.key() == merchant_account.authority @ PaymentError::Unauthorized,
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+
+    #[account(
+        mut,
+        seeds = [Subscription::SEED, merchant_account.key().as_ref(), Subscription::id_seed(&subscription.subscription_id).as_ref()],
+        bump = subscription.bump,
+        constraint = subscription.status == SubscriptionStatus::Active @ PaymentError::SubscriptionNotActive,
+    )]
+    pub subscription: Account<'info, Subscription>,
+}
+
+impl<'info> CancelSubscription<'info> {
+    pub fn cancel_subscription(&mut self) -> Result<SubscriptionCancelled> {
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &mut self.subscription;
+        subscription.status = SubscriptionStatus::Cancelled;
+        subscription.cancelled_at = Some(now);
+
+        msg!("Subscription {} cancelled by {}", subscription.subscription_id, self.signer.key());
+        Ok(SubscriptionCancelled {
+            subscription: subscription.key(),
+            cancelled_by: self.signer.key(),
+            cycles_charged: subscription.cycles_charged,
+            cancelled_at: now,
+        })
+    }
+}
+
+pub fn create_subscription(
+    ctx: Context<CreateSubscription>,
+    subscription_id: String,
+    amount: u64,
+    interval: i64,
+    max_cycles: u32,
+) -> Result<()> {
+    let event = ctx.accounts.create_subscription(subscription_id, amount, interval, max_cycles, &ctx.bumps)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn charge_subscription(ctx: Context<ChargeSubscription>) -> Result<()> {
+    let (charged, processed) = ctx.accounts.charge_subscription(&ctx.bumps)?;
+    emit_cpi!(processed);
+    emit_cpi!(charged);
+    Ok(())
+}
+
+pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
+    let event = ctx.accounts.cancel_subscription()?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Merchant, Platform, SuspensionReason};
+use crate::events::{MerchantReinstated, MerchantSuspended};
+use crate::errors::PaymentError;
+
+/// Compliance hold on a merchant. While suspended, `process_payment` rejects
+/// with `MerchantSuspended`; refunds against existing payments stay allowed.
+/// Suspending an already suspended merchant updates the reason code.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SuspendMerchant<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub platform_config: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+}
+
+impl<'info> SuspendMerchant<'info> {
+    pub fn suspend_merchant(&mut self, reason: SuspensionReason) -> Result<MerchantSuspended> {
+        let now = Clock::get()?.unix_timestamp;
+        let merchant = &mut self.merchant_account;
+
+        merchant.suspension_reason = Some(reason);
+        merchant.suspended_at = Some(now);
+
+        msg!("Merchant {} suspended: {:?}", merchant.merchant_id, reason);
+        Ok(MerchantSuspended {
+            merchant: merchant.key(),
+            reason,
+            suspended_at: now,
+        })
+    }
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ReinstateMerchant<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub platform_config: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+}
+
+impl<'info> ReinstateMerchant<'info> {
+    pub fn reinstate_merchant(&mut self) -> Result<MerchantReinstated> {
+        let now = Clock::get()?.unix_timestamp;
+        let merchant = &mut self.merchant_account;
+
+        let previous_reason = merchant
+            .suspension_reason
+            .take()
+            .ok_or(PaymentError::MerchantNotSuspended)?;
+        merchant.suspended_at = None;
+
+        msg!("Merchant {} reinstated", merchant.merchant_id);
+        Ok(MerchantReinstated {
+            merchant: merchant.key(),
+            previous_reason,
+            reinstated_at: now,
+        })
+    }
+}
+
+pub fn suspend_merchant(ctx: Context<SuspendMerchant>, reason: SuspensionReason) -> Result<()> {
+    let event = ctx.accounts.suspend_merchant(reason)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn reinstate_merchant(ctx: Context<ReinstateMerchant>) -> Result<()> {
+    let event = ctx.accounts.reinstate_merchant()?;
+    emit_cpi!(event);
+    Ok(())
+}
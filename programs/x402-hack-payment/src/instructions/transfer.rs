@@ -1,16 +1,19 @@
 use anchor_lang::prelude::*;
 
 use crate::state::platform::Platform;
+use crate::events::{AuthorityTransferCancelled, AuthorityTransferProposed, AuthorityTransferred};
 use crate::errors::PaymentError;
 
+/// Step 1 — the current authority nominates a successor.
+/// Nothing changes until the nominee signs `accept_authority`.
+#[event_cpi]
 #[derive(Accounts)]
-pub struct TransferAuthority<'info> {
-    #[account(mut)]
+pub struct ProposeAuthority<'info> {
     pub authority: Signer<'info>,
-    
-    /// CHECK: This is the new authority (can be a wallet or Squads vault)
+
+    /// CHECK: This is the proposed authority (can be a wallet or Squads vault)
     pub new_authority: UncheckedAccount<'info>,
-    
+
     #[account(
         mut,
         seeds = [b"platform_config"],
@@ -20,20 +23,111 @@ pub struct TransferAuthority<'info> {
     pub platform_config: Account<'info, Platform>,
 }
 
-impl<'info> TransferAuthority<'info> {
-    pub fn transfer_authority(&mut self) -> Result<()> {
+impl<'info> ProposeAuthority<'info> {
+    pub fn propose_authority(&mut self) -> Result<AuthorityTransferProposed> {
+        require_keys_neq!(
+            self.new_authority.key(),
+            self.platform_config.authority,
+            PaymentError::AuthorityUnchanged
+        );
+
+        self.platform_config.pending_authority = Some(self.new_authority.key());
+
+        msg!(
+            "Authority transfer proposed from {} to {}",
+            self.platform_config.authority,
+            self.new_authority.key()
+        );
+        Ok(AuthorityTransferProposed {
+            authority: self.platform_config.authority,
+            proposed_authority: self.new_authority.key(),
+            proposed_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+/// Step 2 — the nominee proves it controls the key by signing.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub new_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        constraint = platform_config.pending_authority.is_some() @ PaymentError::NoPendingAuthority,
+        constraint = platform_config.pending_authority == Some(new_authority.key()) @ PaymentError::Unauthorized,
+    )]
+    pub platform_config: Account<'info, Platform>,
+}
+
+impl<'info> AcceptAuthority<'info> {
+    pub fn accept_authority(&mut self) -> Result<AuthorityTransferred> {
         let old_authority = self.platform_config.authority;
         self.platform_config.authority = self.new_authority.key();
-        
+        self.platform_config.pending_authority = None;
+
         msg!(
             "Authority transferred from {} to {}",
             old_authority,
             self.new_authority.key()
         );
-        Ok(())
+        Ok(AuthorityTransferred {
+            old_authority,
+            new_authority: self.new_authority.key(),
+            transferred_at: Clock::get()?.unix_timestamp,
+        })
     }
 }
 
-pub fn handler(ctx: Context<TransferAuthority>) -> Result<()> {
-    ctx.accounts.transfer_authority()
+/// Withdraw a pending proposal (e.g. the wrong vault address was nominated).
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelAuthorityTransfer<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+        constraint = platform_config.pending_authority.is_some() @ PaymentError::NoPendingAuthority,
+    )]
+    pub platform_config: Account<'info, Platform>,
+}
+
+impl<'info> CancelAuthorityTransfer<'info> {
+    pub fn cancel_authority_transfer(&mut self) -> Result<AuthorityTransferCancelled> {
+        let cancelled = self
+            .platform_config
+            .pending_authority
+            .take()
+            .ok_or(PaymentError::NoPendingAuthority)?;
+
+        msg!("Authority transfer to {} cancelled", cancelled);
+        Ok(AuthorityTransferCancelled {
+            authority: self.platform_config.authority,
+            cancelled_authority: cancelled,
+            cancelled_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn propose_authority(ctx: Context<ProposeAuthority>) -> Result<()> {
+    let event = ctx.accounts.propose_authority()?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+    let event = ctx.accounts.accept_authority()?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn cancel_authority_transfer(ctx: Context<CancelAuthorityTransfer>) -> Result<()> {
+    let event = ctx.accounts.cancel_authority_transfer()?;
+    emit_cpi!(event);
+    Ok(())
 }
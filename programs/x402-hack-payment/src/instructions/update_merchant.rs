@@ -0,0 +1,328 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{FeeMode, Merchant, Platform};
+use crate::events::{
+    MerchantAuthorityTransferCancelled, MerchantAuthorityTransferred, MerchantUpdated,
+    SettlementWalletChangeCancelled, SettlementWalletChanged,
+};
+use crate::errors::PaymentError;
+
+/// Merchant-controlled fields. Any field left as `None` keeps its current value.
+/// A new `settlement_wallet` or `authority` is staged and only takes effect
+/// after `Platform.settlement_wallet_delay` has elapsed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct UpdateMerchantArgs {
+    pub settlement_wallet: Option<Pubkey>,
+    pub authority: Option<Pubkey>,
+    pub is_active: Option<bool>,
+    /// Whether the platform fee is taken out of the price or charged on top
+    pub fee_mode: Option<FeeMode>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateMerchant<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+}
+
+impl<'info> UpdateMerchant<'info> {
+    pub fn update_merchant(&mut self, args: UpdateMerchantArgs) -> Result<MerchantUpdated> {
+        let now = Clock::get()?.unix_timestamp;
+        let merchant = &mut self.merchant_account;
+
+        if let Some(settlement_wallet) = args.settlement_wallet {
+            let effective_at = now
+                .checked_add(self.platform_config.settlement_wallet_delay)
+                .ok_or(PaymentError::CalculationError)?;
+
+            // Staging again replaces any earlier pending wallet and restarts the timelock
+            merchant.pending_settlement_wallet = Some(settlement_wallet);
+            merchant.settlement_wallet_effective_at = effective_at;
+
+            msg!(
+                "Merchant {} settlement wallet change to {} staged, effective at {}",
+                merchant.merchant_id,
+                settlement_wallet,
+                effective_at
+            );
+        }
+
+        if let Some(authority) = args.authority {
+            require_keys_neq!(authority, merchant.authority, PaymentError::AuthorityUnchanged);
+            let effective_at = now
+                .checked_add(self.platform_config.settlement_wallet_delay)
+                .ok_or(PaymentError::CalculationError)?;
+
+            // Same timelock as the settlement wallet, so the current authority can
+            // cancel a rotation it didn't make before it lands
+            merchant.pending_authority = Some(authority);
+            merchant.authority_effective_at = effective_at;
+
+            msg!(
+                "Merchant {} authority change to {} staged, effective at {}",
+                merchant.merchant_id,
+                authority,
+                effective_at
+            );
+        }
+
+        if let Some(is_active) = args.is_active {
+            msg!(
+                "Merchant {} active flag changed from {} to {}",
+                merchant.merchant_id,
+                merchant.is_active,
+                is_active
+            );
+            merchant.is_active = is_active;
+        }
+
+        if let Some(fee_mode) = args.fee_mode {
+            msg!(
+                "Merchant {} fee mode changed from {:?} to {:?}",
+                merchant.merchant_id,
+                merchant.fee_mode,
+                fee_mode
+            );
+            merchant.fee_mode = fee_mode;
+        }
+
+        Ok(MerchantUpdated {
+            merchant: merchant.key(),
+            authority: merchant.authority,
+            is_active: merchant.is_active,
+            fee_mode: merchant.fee_mode,
+            pending_settlement_wallet: merchant.pending_settlement_wallet,
+            settlement_wallet_effective_at: merchant.settlement_wallet_effective_at,
+            pending_authority: merchant.pending_authority,
+            authority_effective_at: merchant.authority_effective_at,
+            updated_at: now,
+        })
+    }
+}
+
+/// Apply a staged settlement wallet once its timelock has elapsed.
+/// Permissionless — the change was already authorized when it was staged.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ApplySettlementWallet<'info> {
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        constraint = merchant_account.pending_settlement_wallet.is_some() @ PaymentError::NoPendingSettlementWallet,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+}
+
+impl<'info> ApplySettlementWallet<'info> {
+    pub fn apply_settlement_wallet(&mut self) -> Result<SettlementWalletChanged> {
+        let now = Clock::get()?.unix_timestamp;
+        let merchant = &mut self.merchant_account;
+        require!(
+            now >= merchant.settlement_wallet_effective_at,
+            PaymentError::SettlementWalletTimelocked
+        );
+
+        let old_wallet = merchant.settlement_wallet;
+        merchant.settlement_wallet = merchant
+            .pending_settlement_wallet
+            .take()
+            .ok_or(PaymentError::NoPendingSettlementWallet)?;
+        merchant.settlement_wallet_effective_at = 0;
+
+        msg!(
+            "Merchant {} settlement wallet changed from {} to {}",
+            merchant.merchant_id,
+            old_wallet,
+            merchant.settlement_wallet
+        );
+        Ok(SettlementWalletChanged {
+            merchant: merchant.key(),
+            old_wallet,
+            new_wallet: merchant.settlement_wallet,
+            changed_at: now,
+        })
+    }
+}
+
+/// Cancel a staged settlement wallet change during its timelock. The
+/// authority replaced by the latest handover may also cancel for one
+/// timelock period after it.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelSettlementWalletChange<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+    )]
+    pub platform_config: Account<'info, Platform>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        constraint = merchant_account.pending_settlement_wallet.is_some() @ PaymentError::NoPendingSettlementWallet,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+}
+
+impl<'info> CancelSettlementWalletChange<'info> {
+    pub fn cancel_settlement_wallet_change(&mut self) -> Result<SettlementWalletChangeCancelled> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            self.merchant_account.can_cancel_wallet_change(
+                &self.authority.key(),
+                now,
+                self.platform_config.settlement_wallet_delay,
+            ),
+            PaymentError::Unauthorized
+        );
+
+        let merchant = &mut self.merchant_account;
+        let cancelled = merchant
+            .pending_settlement_wallet
+            .take()
+            .ok_or(PaymentError::NoPendingSettlementWallet)?;
+        merchant.settlement_wallet_effective_at = 0;
+
+        msg!(
+            "Merchant {} settlement wallet change to {} cancelled",
+            merchant.merchant_id,
+            cancelled
+        );
+        Ok(SettlementWalletChangeCancelled {
+            merchant: merchant.key(),
+            cancelled_wallet: cancelled,
+            cancelled_at: now,
+        })
+    }
+}
+
+/// Complete a staged authority change. The nominee signs once the timelock
+/// has elapsed, proving it controls the new key.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptMerchantAuthority<'info> {
+    pub new_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        constraint = merchant_account.pending_authority.is_some() @ PaymentError::NoPendingAuthority,
+        constraint = merchant_account.pending_authority == Some(new_authority.key()) @ PaymentError::Unauthorized,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+}
+
+impl<'info> AcceptMerchantAuthority<'info> {
+    pub fn accept_merchant_authority(&mut self) -> Result<MerchantAuthorityTransferred> {
+        let now = Clock::get()?.unix_timestamp;
+        let merchant = &mut self.merchant_account;
+        require!(now >= merchant.authority_effective_at, PaymentError::MerchantAuthorityTimelocked);
+
+        let old_authority = merchant.authority;
+        merchant.authority = self.new_authority.key();
+        merchant.pending_authority = None;
+        merchant.authority_effective_at = 0;
+        merchant.previous_authority = Some(old_authority);
+        merchant.authority_changed_at = now;
+
+        msg!(
+            "Merchant {} authority changed from {} to {}",
+            merchant.merchant_id,
+            old_authority,
+            merchant.authority
+        );
+        Ok(MerchantAuthorityTransferred {
+            merchant: merchant.key(),
+            old_authority,
+            new_authority: merchant.authority,
+            transferred_at: now,
+        })
+    }
+}
+
+/// Cancel a staged authority change during its timelock.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelMerchantAuthorityTransfer<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Merchant::SEED, Merchant::id_seed(&merchant_account.merchant_id).as_ref()],
+        bump = merchant_account.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+        constraint = merchant_account.pending_authority.is_some() @ PaymentError::NoPendingAuthority,
+    )]
+    pub merchant_account: Account<'info, Merchant>,
+}
+
+impl<'info> CancelMerchantAuthorityTransfer<'info> {
+    pub fn cancel_merchant_authority_transfer(&mut self) -> Result<MerchantAuthorityTransferCancelled> {
+        let merchant = &mut self.merchant_account;
+        let cancelled = merchant
+            .pending_authority
+            .take()
+            .ok_or(PaymentError::NoPendingAuthority)?;
+        merchant.authority_effective_at = 0;
+
+        msg!(
+            "Merchant {} authority change to {} cancelled",
+            merchant.merchant_id,
+            cancelled
+        );
+        Ok(MerchantAuthorityTransferCancelled {
+            merchant: merchant.key(),
+            cancelled_authority: cancelled,
+            cancelled_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn update_merchant(ctx: Context<UpdateMerchant>, args: UpdateMerchantArgs) -> Result<()> {
+    let event = ctx.accounts.update_merchant(args)?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn apply_settlement_wallet(ctx: Context<ApplySettlementWallet>) -> Result<()> {
+    let event = ctx.accounts.apply_settlement_wallet()?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn cancel_settlement_wallet_change(ctx: Context<CancelSettlementWalletChange>) -> Result<()> {
+    let event = ctx.accounts.cancel_settlement_wallet_change()?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn accept_merchant_authority(ctx: Context<AcceptMerchantAuthority>) -> Result<()> {
+    let event = ctx.accounts.accept_merchant_authority()?;
+    emit_cpi!(event);
+    Ok(())
+}
+
+pub fn cancel_merchant_authority_transfer(ctx: Context<CancelMerchantAuthorityTransfer>) -> Result<()> {
+    let event = ctx.accounts.cancel_merchant_authority_transfer()?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use anchor_lang::prelude::*;
+
+use crate::state::platform::Platform;
+use crate::events::PlatformConfigUpdated;
+use crate::errors::PaymentError;
+
+/// Fields of the platform config that can be changed after launch.
+/// Any field left as `None` keeps its current value.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct UpdatePlatformConfigArgs {
+    pub fee_bps: Option<u64>,
+    pub min_payment_amount: Option<u64>,
+    pub is_active: Option<bool>,
+    pub settlement_wallet_delay: Option<i64>,
+    pub refund_window: Option<i64>,
+    pub arbiter: Option<Pubkey>,
+    pub escrow_timeout: Option<i64>,
+    pub authorization_ttl: Option<i64>,
+    pub max_sponsor_fee: Option<u64>,
+    pub dispute_window: Option<i64>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdatePlatformConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Platform::SEED],
+        bump = platform_config.bump,
+        has_one = authority @ PaymentError::Unauthorized,
+    )]
+    pub platform_config: Account<'info, Platform>,
+}
+
+impl<'info> UpdatePlatformConfig<'info> {
+    pub fn update_platform_config(&mut self, args: UpdatePlatformConfigArgs) -> Result<PlatformConfigUpdated> {
+        let config = &mut self.platform_config;
+
+        if let Some(fee_bps) = args.fee_bps {
+            require!(fee_bps <= Platform::MAX_FEE_BPS, PaymentError::InvalidFeeBps);
+            msg!("Fee updated from {} to {} bps", config.fee_bps, fee_bps);
+            config.fee_bps = fee_bps;
+        }
+
+        if let Some(min_payment_amount) = args.min_payment_amount {
+            require!(min_payment_amount > 0, PaymentError::InvalidMinPaymentAmount);
+            msg!(
+                "Minimum payment amount updated from {} to {}",
+                config.min_payment_amount,
+                min_payment_amount
+            );
+            config.min_payment_amount = min_payment_amount;
+        }
+
+        if let Some(is_active) = args.is_active {
+            msg!("Platform active flag updated from {} to {}", config.is_active, is_active);
+            config.is_active = is_active;
+        }
+
+        if let Some(delay) = args.settlement_wallet_delay {
+            require!(
+                (0..=Platform::MAX_SETTLEMENT_WALLET_DELAY).contains(&delay),
+                PaymentError::InvalidDelay
+            );
+            msg!(
+                "Settlement wallet delay updated from {}s to {}s",
+                config.settlement_wallet_delay,
+                delay
+            );
+            config.settlement_wallet_delay = delay;
+        }
+
+        if let Some(refund_window) = args.refund_window {
+            require!(
+                (0..=Platform::MAX_REFUND_WINDOW).contains(&refund_window),
+                PaymentError::InvalidRefundWindow
+            );
+            msg!(
+                "Refund window updated from {}s to {}s",
+                config.refund_window,
+                refund_window
+            );
+            config.refund_window = refund_window;
+        }
+
+        if let Some(arbiter) = args.arbiter {
+            msg!("Arbiter updated from {} to {}", config.arbiter, arbiter);
+            config.arbiter = arbiter;
+        }
+
+        if let Some(escrow_timeout) = args.escrow_timeout {
+            require!(
+                (0..=Platform::MAX_ESCROW_TIMEOUT).contains(&escrow_timeout),
+                PaymentError::InvalidEscrowTimeout
+            );
+            msg!(
+                "Escrow timeout updated from {}s to {}s",
+                config.escrow_timeout,
+                escrow_timeout
+            );
+            config.escrow_timeout = escrow_timeout;
+        }
+
+        if let Some(authorization_ttl) = args.authorization_ttl {
+            require!(
+                (1..=Platform::MAX_AUTHORIZATION_TTL).contains(&authorization_ttl),
+                PaymentError::InvalidAuthorizationTtl
+            );
+            msg!(
+                "Authorization TTL updated from {}s to {}s",
+                config.authorization_ttl,
+                authorization_ttl
+            );
+            config.authorization_ttl = authorization_ttl;
+        }
+
+        if let Some(max_sponsor_fee) = args.max_sponsor_fee {
+            msg!(
+                "Max sponsor fee updated from {} to {}",
+                config.max_sponsor_fee,
+                max_sponsor_fee
+            );
+            config.max_sponsor_fee = max_sponsor_fee;
+        }
+
+        if let Some(dispute_window) = args.dispute_window {
+            require!(
+                (0..=Platform::MAX_DISPUTE_WINDOW).contains(&dispute_window),
+                PaymentError::InvalidDisputeWindow
+            );
+            msg!(
+                "Dispute window updated from {}s to {}s",
+                config.dispute_window,
+                dispute_window
+            );
+            config.dispute_window = dispute_window;
+        }
+
+        Ok(PlatformConfigUpdated {
+            platform_config: config.key(),
+            authority: config.authority,
+            fee_bps: config.fee_bps,
+            min_payment_amount: config.min_payment_amount,
+            is_active: config.is_active,
+            settlement_wallet_delay: config.settlement_wallet_delay,
+            refund_window: config.refund_window,
+            arbiter: config.arbiter,
+            escrow_timeout: config.escrow_timeout,
+            authorization_ttl: config.authorization_ttl,
+            max_sponsor_fee: config.max_sponsor_fee,
+            dispute_window: config.dispute_window,
+            updated_at: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<UpdatePlatformConfig>, args: UpdatePlatformConfigArgs) -> Result<()> {
+    let event = ctx.accounts.update_platform_config(args)?;
+    emit_cpi!(event);
+    Ok(())
+}
@@ -3,9 +3,11 @@ use ephemeral_rollups_sdk::anchor::ephemeral;
 
 pub mod instructions;
 pub mod errors;
+pub mod events;
 pub mod state;
 
 use instructions::*;
+use state::{DisputeRuling, RefundReason, SuspensionReason};
 
 
 declare_id!("339A4zncMj8fbM2zvEopYXu6TZqRieJKebDiXCKwquA5");
@@ -19,24 +21,347 @@ pub mod x402_hack_payment {
         instructions::platform::handler(ctx, fee_bps, min_payment_amount)
     }
 
+    /// Update fee, minimum payment or active flag on the live platform config.
+    pub fn update_platform_config(ctx: Context<UpdatePlatformConfig>, args: UpdatePlatformConfigArgs) -> Result<()> {
+        instructions::update_platform::handler(ctx, args)
+    }
+
+    /// Register a merchant. `fee_bps` is deprecated and ignored — fee overrides are
+    /// set by the platform with `set_merchant_fee`. It will be removed in the next release.
     pub fn initialize_merchant(ctx: Context<InitializeMerchant>, merchant_id: String, fee_bps: u16) -> Result<()> {
         instructions::initialize::handler(ctx, merchant_id, fee_bps)
     }
 
-    pub fn process_payment(ctx: Context<ProcessPayment>, payment_id: String, amount: u64) -> Result<()> {
-        instructions::payment::handler(ctx, payment_id, amount)
+    /// Update merchant settings. Settlement wallet and authority changes are staged behind a timelock.
+    pub fn update_merchant(ctx: Context<UpdateMerchant>, args: UpdateMerchantArgs) -> Result<()> {
+        instructions::update_merchant::update_merchant(ctx, args)
+    }
+
+    /// Apply a staged settlement wallet once its timelock has elapsed.
+    pub fn apply_settlement_wallet(ctx: Context<ApplySettlementWallet>) -> Result<()> {
+        instructions::update_merchant::apply_settlement_wallet(ctx)
+    }
+
+    /// Cancel a staged settlement wallet change.
+    pub fn cancel_settlement_wallet_change(ctx: Context<CancelSettlementWalletChange>) -> Result<()> {
+        instructions::update_merchant::cancel_settlement_wallet_change(ctx)
+    }
+
+    /// Complete a staged merchant authority change once its timelock has elapsed.
+    pub fn accept_merchant_authority(ctx: Context<AcceptMerchantAuthority>) -> Result<()> {
+        instructions::update_merchant::accept_merchant_authority(ctx)
+    }
+
+    /// Cancel a staged merchant authority change.
+    pub fn cancel_merchant_authority_transfer(ctx: Context<CancelMerchantAuthorityTransfer>) -> Result<()> {
+        instructions::update_merchant::cancel_merchant_authority_transfer(ctx)
+    }
+
+    /// Suspend a merchant for a compliance reason. Blocks new payments; refunds stay allowed.
+    pub fn suspend_merchant(ctx: Context<SuspendMerchant>, reason: SuspensionReason) -> Result<()> {
+        instructions::suspend::suspend_merchant(ctx, reason)
+    }
+
+    /// Lift a merchant suspension.
+    pub fn reinstate_merchant(ctx: Context<ReinstateMerchant>) -> Result<()> {
+        instructions::suspend::reinstate_merchant(ctx)
+    }
+
+    /// Set (or clear with `None`) a merchant's negotiated fee override.
+    pub fn set_merchant_fee(ctx: Context<SetMerchantFee>, fee_bps: Option<u16>) -> Result<()> {
+        instructions::merchant_fee::handler(ctx, fee_bps)
+    }
+
+    /// Set (or clear with `None`) the merchant's own refund window, in seconds.
+    pub fn set_merchant_refund_window(ctx: Context<SetMerchantRefundWindow>, refund_window: Option<i64>) -> Result<()> {
+        instructions::refund_window::handler(ctx, refund_window)
+    }
+
+    /// `sponsor_fee` reimburses the relayer that sponsored the transaction. It is
+    /// taken out of `amount` and paid to `sponsor_usdc` before the platform fee.
+    pub fn process_payment(
+        ctx: Context<ProcessPayment>,
+        payment_id: String,
+        amount: u64,
+        sponsor_fee: Option<u64>,
+    ) -> Result<()> {
+        instructions::payment::handler(ctx, payment_id, amount, sponsor_fee)
+    }
+
+    /// Submit a payment intent the customer signed off-chain. Must follow an Ed25519
+    /// verification instruction; funds are pulled through the program delegate.
+    pub fn process_signed_payment(ctx: Context<ProcessSignedPayment>, intent: PaymentIntent) -> Result<()> {
+        instructions::signed_payment::process_signed_payment(ctx, intent)
+    }
+
+    /// Raise the program delegate's allowance on the customer's USDC account.
+    pub fn approve_delegate(ctx: Context<ApproveDelegate>, amount: u64) -> Result<()> {
+        instructions::delegate::approve_delegate(ctx, amount)
+    }
+
+    /// Register a relayer that may receive sponsor fees.
+    pub fn add_sponsor(ctx: Context<ManageSponsor>, sponsor: Pubkey) -> Result<()> {
+        instructions::sponsor::add_sponsor(ctx, sponsor)
+    }
+
+    /// Remove a relayer from the sponsor registry.
+    pub fn remove_sponsor(ctx: Context<ManageSponsor>, sponsor: Pubkey) -> Result<()> {
+        instructions::sponsor::remove_sponsor(ctx, sponsor)
+    }
+
+    /// Pay into escrow. Released on delivery confirmation or after the platform escrow timeout.
+    pub fn process_escrow_payment(ctx: Context<ProcessEscrowPayment>, payment_id: String, amount: u64) -> Result<()> {
+        instructions::escrow::process_escrow_payment(ctx, payment_id, amount)
+    }
+
+    /// Customer confirms delivery and releases the escrow to the merchant.
+    pub fn confirm_delivery(ctx: Context<ReleaseEscrow>) -> Result<()> {
+        instructions::escrow::confirm_delivery(ctx)
+    }
+
+    /// Release an unconfirmed escrow once its timeout has passed. Permissionless.
+    pub fn release_escrow(ctx: Context<ReleaseEscrow>) -> Result<()> {
+        instructions::escrow::release_escrow(ctx)
+    }
+
+    /// Merchant cancels an escrowed payment and the customer gets everything back.
+    pub fn cancel_escrow(ctx: Context<CancelEscrow>) -> Result<()> {
+        instructions::escrow::cancel_escrow(ctx)
+    }
+
+    /// Pay into an escrow that only releases on a passing attestation from an approved attester.
+    /// Without one by the escrow timeout, anyone can refund the customer.
+    pub fn process_attested_payment(ctx: Context<ProcessEscrowPayment>, payment_id: String, amount: u64) -> Result<()> {
+        instructions::escrow::process_attested_payment(ctx, payment_id, amount)
+    }
+
+    /// Approve an attester (testing lab, inspector) for attestation-gated escrow.
+    pub fn add_attester(ctx: Context<ManageAttester>, attester: Pubkey) -> Result<()> {
+        instructions::attester::add_attester(ctx, attester)
+    }
+
+    /// Remove an attester from the approved registry.
+    pub fn remove_attester(ctx: Context<ManageAttester>, attester: Pubkey) -> Result<()> {
+        instructions::attester::remove_attester(ctx, attester)
+    }
+
+    /// Attester posts a verdict with a document hash. A pass releases the escrow to the merchant.
+    pub fn submit_attestation(ctx: Context<SubmitAttestation>, document_hash: [u8; 32], passed: bool) -> Result<()> {
+        instructions::attestation::submit_attestation(ctx, document_hash, passed)
+    }
+
+    /// Return an attestation-gated escrow to the customer after a failed attestation
+    /// or once its deadline passes without one. Permissionless.
+    pub fn refund_failed_attestation(ctx: Context<RefundFailedAttestation>) -> Result<()> {
+        instructions::attestation::refund_failed_attestation(ctx)
+    }
+
+    /// Merchant issues an invoice with an amount, optional designated payer, due date, memo
+    /// and optional early-payment discount / late fee terms.
+    pub fn create_invoice(ctx: Context<CreateInvoice>, invoice_id: String, args: InvoiceArgs) -> Result<()> {
+        instructions::invoice::create_invoice(ctx, invoice_id, args)
+    }
+
+    /// Pay an open invoice; the amount owed reflects any discount or late fee at payment time.
+    pub fn pay_invoice(ctx: Context<PayInvoice>, invoice_id: String) -> Result<()> {
+        instructions::invoice::pay_invoice(ctx, invoice_id)
+    }
+
+    /// Merchant voids an unpaid invoice.
+    pub fn void_invoice(ctx: Context<VoidInvoice>) -> Result<()> {
+        instructions::invoice::void_invoice(ctx)
+    }
+
+    /// Void an unpaid invoice and issue a replacement with new terms.
+    pub fn reissue_invoice(ctx: Context<ReissueInvoice>, new_invoice_id: String, args: InvoiceArgs) -> Result<()> {
+        instructions::invoice::reissue_invoice(ctx, new_invoice_id, args)
+    }
+
+    /// Create a payment link with a fixed amount, expiry and use limit. Merchant or admin.
+    pub fn create_checkout_session(
+        ctx: Context<CreateCheckoutSession>,
+        session_id: String,
+        amount: u64,
+        expires_at: i64,
+        max_uses: u16,
+    ) -> Result<()> {
+        instructions::checkout_session::create_checkout_session(ctx, session_id, amount, expires_at, max_uses)
+    }
+
+    /// Pay through an unexpired checkout session that still has uses left.
+    pub fn pay_checkout_session(ctx: Context<PayCheckoutSession>, payment_id: String) -> Result<()> {
+        instructions::checkout_session::pay_checkout_session(ctx, payment_id)
+    }
+
+    /// Close an expired or used-up checkout session and return its rent. Permissionless.
+    pub fn close_checkout_session(ctx: Context<CloseCheckoutSession>) -> Result<()> {
+        instructions::checkout_session::close_checkout_session(ctx)
+    }
+
+    /// Customer subscribes and approves the program delegate for `amount * max_cycles`.
+    pub fn create_subscription(
+        ctx: Context<CreateSubscription>,
+        subscription_id: String,
+        amount: u64,
+        interval: i64,
+        max_cycles: u32,
+    ) -> Result<()> {
+        instructions::subscription::create_subscription(ctx, subscription_id, amount, interval, max_cycles)
+    }
+
+    /// Charge the next due subscription cycle through the delegate. Permissionless crank.
+    pub fn charge_subscription(ctx: Context<ChargeSubscription>) -> Result<()> {
+        instructions::subscription::charge_subscription(ctx)
+    }
+
+    /// Stop future charges. Customer or merchant authority.
+    pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
+        instructions::subscription::cancel_subscription(ctx)
+    }
+
+    /// Buyer funds a milestone-based purchase order up front.
+    pub fn create_purchase_order(
+        ctx: Context<CreatePurchaseOrder>,
+        order_id: String,
+        milestones: Vec<MilestoneArgs>,
+        deliver_by: i64,
+    ) -> Result<()> {
+        instructions::purchase_order::create_purchase_order(ctx, order_id, milestones, deliver_by)
+    }
+
+    /// Release one milestone to the merchant as a regular payment. Buyer, or anyone after its release time.
+    pub fn release_milestone(ctx: Context<ReleaseMilestone>, milestone_index: u8) -> Result<()> {
+        instructions::purchase_order::release_milestone(ctx, milestone_index)
+    }
+
+    /// Buyer takes back outstanding milestones once the order's delivery deadline has passed.
+    pub fn cancel_purchase_order(ctx: Context<CancelPurchaseOrder>) -> Result<()> {
+        instructions::purchase_order::cancel_purchase_order(ctx)
+    }
+
+    /// Place a hold: the customer's USDC moves into a hold account until capture, void or expiry.
+    pub fn authorize_payment(ctx: Context<AuthorizePayment>, authorization_id: String, amount: u64) -> Result<()> {
+        instructions::authorization::authorize_payment(ctx, authorization_id, amount)
+    }
+
+    /// Merchant captures up to the authorized amount; the remainder returns to the customer.
+    pub fn capture_payment(ctx: Context<CapturePayment>, amount: u64) -> Result<()> {
+        instructions::authorization::capture_payment(ctx, amount)
+    }
+
+    /// Merchant releases the full hold back to the customer.
+    pub fn void_authorization(ctx: Context<ReleaseAuthorization>) -> Result<()> {
+        instructions::authorization::void_authorization(ctx)
+    }
+
+    /// Release an expired hold back to the customer. Permissionless.
+    pub fn release_expired_authorization(ctx: Context<ReleaseAuthorization>) -> Result<()> {
+        instructions::authorization::release_expired_authorization(ctx)
+    }
+
+    /// Finalize a payment whose refund window has closed. Permissionless.
+    pub fn finalize_payment(ctx: Context<FinalizePayment>) -> Result<()> {
+        instructions::finalize::handler(ctx)
+    }
+
+    /// Move a merchant registered before merchant IDs were hashed to its new address.
+    pub fn migrate_merchant(ctx: Context<MigrateMerchant>, merchant_id: String) -> Result<()> {
+        instructions::migrate_merchant::handler(ctx, merchant_id)
+    }
+
+    /// Move a payment created before payment IDs were merchant-scoped to its new address.
+    pub fn migrate_payment(ctx: Context<MigratePayment>, payment_id: String) -> Result<()> {
+        instructions::migrate_payment::handler(ctx, payment_id)
+    }
+
+    /// Grow the deployed platform config to the current layout, defaulting the appended fields.
+    /// `legacy_fees` is the audited fee revenue in the treasury; the rest stays payout escrow.
+    pub fn migrate_platform_config(ctx: Context<MigratePlatformConfig>, legacy_fees: u64) -> Result<()> {
+        instructions::migrate_layout::migrate_platform_config(ctx, legacy_fees)
+    }
+
+    /// Grow a pre-upgrade customer account to the current layout. Permissionless.
+    pub fn migrate_customer(ctx: Context<MigrateCustomer>) -> Result<()> {
+        instructions::migrate_layout::migrate_customer(ctx)
     }
 
     pub fn claim_platform_fees(ctx: Context<ClaimPlatformFees>) -> Result<()> {
         instructions::claim::handler(ctx)
     }
 
-    pub fn refund_payment(ctx: Context<RefundPayment>) -> Result<()> {
-        instructions::refund::handler(ctx)
+    /// Refund `refund_amount` of a payment. Repeatable until the full amount is returned.
+    pub fn refund_payment(ctx: Context<RefundPayment>, refund_amount: u64) -> Result<()> {
+        instructions::refund::handler(ctx, refund_amount)
+    }
+
+    /// Customer opens a refund request with a reason code and a hash of their evidence.
+    pub fn open_refund_request(
+        ctx: Context<OpenRefundRequest>,
+        amount: u64,
+        reason: RefundReason,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        instructions::refund_request::open_refund_request(ctx, amount, reason, evidence_hash)
+    }
+
+    /// Merchant approves and pays out a refund request.
+    pub fn approve_refund_request(ctx: Context<ApproveRefundRequest>) -> Result<()> {
+        instructions::refund_request::approve_refund_request(ctx)
+    }
+
+    /// Merchant denies a refund request before its response deadline.
+    pub fn deny_refund_request(ctx: Context<DenyRefundRequest>, response_hash: [u8; 32]) -> Result<()> {
+        instructions::refund_request::deny_refund_request(ctx, response_hash)
+    }
+
+    /// Platform rules on a refund request the merchant did not answer in time; approvals pay out immediately.
+    pub fn resolve_refund_request(ctx: Context<ResolveRefundRequest>, approve: bool, response_hash: [u8; 32]) -> Result<()> {
+        instructions::refund_request::resolve_refund_request(ctx, approve, response_hash)
+    }
+
+    /// Close a refund request nobody answered before it expired. Permissionless.
+    pub fn expire_refund_request(ctx: Context<ExpireRefundRequest>) -> Result<()> {
+        instructions::refund_request::expire_refund_request(ctx)
+    }
+
+    /// Merchant tops up the reserve that backs dispute rulings.
+    pub fn fund_merchant_reserve(ctx: Context<FundMerchantReserve>, amount: u64) -> Result<()> {
+        instructions::reserve::fund_merchant_reserve(ctx, amount)
+    }
+
+    /// Merchant withdraws from the reserve. Locked while any dispute is open.
+    pub fn withdraw_merchant_reserve(ctx: Context<WithdrawMerchantReserve>, amount: u64) -> Result<()> {
+        instructions::reserve::withdraw_merchant_reserve(ctx, amount)
+    }
+
+    /// Customer disputes a completed payment with a hash of their evidence.
+    pub fn open_dispute(ctx: Context<OpenDispute>, evidence_hash: [u8; 32]) -> Result<()> {
+        instructions::dispute::open_dispute(ctx, evidence_hash)
+    }
+
+    /// Merchant submits its evidence on an open dispute.
+    pub fn respond_to_dispute(ctx: Context<RespondToDispute>, evidence_hash: [u8; 32]) -> Result<()> {
+        instructions::dispute::respond_to_dispute(ctx, evidence_hash)
+    }
+
+    /// Arbiter rules on a dispute; refunds are paid from the merchant reserve.
+    pub fn resolve_dispute(ctx: Context<ResolveDispute>, ruling: DisputeRuling, refund_amount: u64) -> Result<()> {
+        instructions::dispute::resolve_dispute(ctx, ruling, refund_amount)
+    }
+
+    /// Nominate a new platform authority. Takes effect once the nominee accepts.
+    pub fn propose_authority(ctx: Context<ProposeAuthority>) -> Result<()> {
+        instructions::transfer::propose_authority(ctx)
+    }
+
+    /// Accept a pending authority nomination. Must be signed by the nominee.
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        instructions::transfer::accept_authority(ctx)
     }
 
-    pub fn transfer_authority(ctx: Context<TransferAuthority>) -> Result<()> {
-        instructions::transfer::handler(ctx)
+    /// Withdraw a pending authority nomination.
+    pub fn cancel_authority_transfer(ctx: Context<CancelAuthorityTransfer>) -> Result<()> {
+        instructions::transfer::cancel_authority_transfer(ctx)
     }
 
     /// Process a payout from the platform treasury to a recipient wallet.
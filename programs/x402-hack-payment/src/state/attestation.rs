@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+/// An approved attester's verdict on an attestation-gated escrowed payment,
+/// e.g. a lab's Certificate of Analysis. One per payment.
+#[account]
+#[derive(InitSpace)]
+pub struct Attestation {
+    pub payment: Pubkey,
+    pub attester: Pubkey,
+    /// Hash of the attested document (COA, inspection report)
+    pub document_hash: [u8; 32],
+    pub passed: bool,
+    pub attested_at: i64,
+    pub bump: u8,
+}
+
+impl Attestation {
+    pub const SEED: &'static [u8] = b"attestation";
+}
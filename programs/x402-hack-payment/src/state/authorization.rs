@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+use crate::state::FeeSource;
+
+/// A hold placed on a customer's funds. The authorized amount sits in a
+/// platform-owned hold account until the merchant captures part or all of
+/// it, voids it, or it expires. Capturing creates a regular `Payment` whose
+/// `payment_id` is the authorization ID.
+#[account]
+#[derive(InitSpace)]
+pub struct Authorization {
+    #[max_len(64)]
+    pub authorization_id: String,
+    pub customer: Pubkey,
+    pub merchant: Pubkey,
+    /// Maximum that can be captured
+    pub amount: u64,
+    /// Set on capture; the rest went back to the customer
+    pub captured_amount: u64,
+    /// Fee terms snapshotted at authorization so the customer's quote can't change
+    pub fee_bps: u64,
+    pub fee_source: FeeSource,
+    pub status: AuthorizationStatus,
+    pub created_at: i64,
+    /// After this the hold can no longer be captured and anyone can release it
+    pub expires_at: i64,
+    pub resolved_at: Option<i64>,
+    pub bump: u8,
+}
+
+impl Authorization {
+    pub const SEED: &'static [u8] = b"authorization";
+    /// Seed of the USDC account holding the authorized funds.
+    pub const HOLD_SEED: &'static [u8] = b"authorization_hold";
+    pub const MAX_ID_LEN: usize = 64;
+
+    pub fn id_seed(authorization_id: &str) -> [u8; 32] {
+        hash(authorization_id.as_bytes()).to_bytes()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum AuthorizationStatus {
+    /// Funds are on hold
+    Authorized,
+    Captured,
+    /// Released in full by the merchant
+    Voided,
+    /// Released in full after the TTL passed
+    Expired,
+}
@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+/// A payment link. Fixes what gets paid and to whom; each use creates a
+/// regular `Payment` until the session expires or runs out of uses.
+#[account]
+#[derive(InitSpace)]
+pub struct CheckoutSession {
+    #[max_len(64)]
+    pub session_id: String,
+    pub merchant: Pubkey,
+    /// Merchant or platform authority that created it; gets the rent back on close
+    pub creator: Pubkey,
+    pub amount: u64,
+    pub mint: Pubkey,
+    pub expires_at: i64,
+    pub max_uses: u16,
+    pub use_count: u16,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl CheckoutSession {
+    pub const SEED: &'static [u8] = b"checkout_session";
+    pub const MAX_ID_LEN: usize = 64;
+
+    pub fn id_seed(session_id: &str) -> [u8; 32] {
+        hash(session_id.as_bytes()).to_bytes()
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at
+    }
+
+    pub fn is_used_up(&self) -> bool {
+        self.use_count >= self.max_uses
+    }
+}
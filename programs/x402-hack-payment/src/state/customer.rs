@@ -8,5 +8,27 @@ pub struct Customer {
     pub transaction_count: u64,
     pub created_at: i64,
     pub bump: u8,
+    /// Nonce the customer's next signed payment intent must carry.
+    /// Appended after launch; older accounts gain it via `migrate_customer`
+    pub next_intent_nonce: u64,
 }
 
+impl Customer {
+    /// Account size before `next_intent_nonce` was appended, discriminator included.
+    pub const LEGACY_LEN: usize = 8 + 32 + 8 + 8 + 8 + 1;
+
+    /// Set up the account on the customer's first payment. Every path that
+    /// creates a `Customer` with `init_if_needed` goes through here.
+    pub fn init_if_new(&mut self, customer: Pubkey, now: i64, bump: u8) {
+        if self.transaction_count == 0 {
+            *self = Customer {
+                customer,
+                total_spent: 0,
+                transaction_count: 0,
+                created_at: now,
+                bump,
+                next_intent_nonce: 0,
+            };
+        }
+    }
+}
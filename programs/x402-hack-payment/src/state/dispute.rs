@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+/// Chargeback-style dispute on a `Payment`, ruled on by the platform arbiter.
+///
+/// Both sides' evidence lives off-chain; only its hash is recorded here. Opening
+/// a dispute locks the merchant's share of the refundable amount in the
+/// merchant reserve, and a ruling in the customer's favour is paid from it, so
+/// it doesn't depend on the merchant cooperating.
+#[account]
+#[derive(InitSpace)]
+pub struct Dispute {
+    pub payment: Pubkey,
+    pub customer: Pubkey,
+    pub merchant: Pubkey,
+    /// Payment amount at the time the dispute was opened
+    pub amount: u64,
+    /// Reserve held back for this dispute when it was opened
+    pub locked_amount: u64,
+    pub customer_evidence_hash: [u8; 32],
+    pub merchant_evidence_hash: Option<[u8; 32]>,
+    pub status: DisputeStatus,
+    pub ruling: Option<DisputeRuling>,
+    /// Amount the ruling awarded the customer, including any `unpaid_amount`
+    pub refund_amount: u64,
+    /// Merchant share of the ruling the reserve couldn't cover
+    pub unpaid_amount: u64,
+    pub opened_at: i64,
+    pub merchant_responded_at: Option<i64>,
+    pub resolved_at: Option<i64>,
+    pub bump: u8,
+}
+
+impl Dispute {
+    pub const SEED: &'static [u8] = b"dispute";
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum DisputeStatus {
+    Open,
+    Resolved,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum DisputeRuling {
+    /// Everything not yet refunded goes back to the customer
+    FullRefund,
+    /// The arbiter picks the amount
+    PartialRefund,
+    /// Merchant keeps the funds
+    Rejected,
+}
@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+/// A bill issued by a merchant. Paying it creates a `Payment` whose
+/// `payment_id` is the invoice ID, for the invoiced amount adjusted by any
+/// discount or late fee in `terms`.
+#[account]
+#[derive(InitSpace)]
+pub struct Invoice {
+    #[max_len(64)]
+    pub invoice_id: String,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    /// Only this wallet may pay; `None` lets anyone pay
+    pub payer: Option<Pubkey>,
+    pub due_date: i64,
+    /// Free-form reference (PO number, order ref) for reconciliation
+    #[max_len(128)]
+    pub memo: String,
+    /// Early-payment discount and late fee; `None` bills the flat amount
+    pub terms: Option<InvoiceTerms>,
+    pub status: InvoiceStatus,
+    pub payment: Option<Pubkey>,
+    /// Invoice this one replaced, if it was reissued
+    pub reissued_from: Option<Pubkey>,
+    /// Invoice that replaced this one
+    pub reissued_as: Option<Pubkey>,
+    pub created_at: i64,
+    pub paid_at: Option<i64>,
+    pub bump: u8,
+}
+
+impl Invoice {
+    pub const SEED: &'static [u8] = b"invoice";
+    pub const MAX_ID_LEN: usize = 64;
+    pub const MAX_MEMO_LEN: usize = 128;
+
+    pub fn id_seed(invoice_id: &str) -> [u8; 32] {
+        hash(invoice_id.as_bytes()).to_bytes()
+    }
+
+    /// What is owed if paid at `now`. The discount applies up to and including
+    /// its deadline; the late fee applies once `due_date` has passed. Returns
+    /// `None` on overflow.
+    pub fn amount_due(&self, now: i64) -> Option<InvoiceBreakdown> {
+        let mut breakdown = InvoiceBreakdown {
+            base_amount: self.amount,
+            discount_amount: 0,
+            late_fee_amount: 0,
+        };
+        if let Some(terms) = &self.terms {
+            if terms.discount_bps > 0 && now <= terms.discount_deadline {
+                breakdown.discount_amount = terms.discount_on(self.amount)?;
+            }
+            if now > self.due_date {
+                breakdown.late_fee_amount = match terms.late_fee {
+                    Some(LateFee::Bps(bps)) => self.amount.checked_mul(bps as u64)? / 10_000,
+                    Some(LateFee::Flat(fee)) => fee,
+                    None => 0,
+                };
+            }
+        }
+        Some(breakdown)
+    }
+}
+
+/// Net terms such as "2/10 net 30": 2% off if paid within 10 days,
+/// otherwise the full amount is due by `due_date`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct InvoiceTerms {
+    /// Early-payment discount off the base amount
+    pub discount_bps: u16,
+    /// Last moment the discount applies; must not be after `due_date`
+    pub discount_deadline: i64,
+    /// Charged once on payments after `due_date`. With no late fee the
+    /// invoice can't be paid once overdue.
+    pub late_fee: Option<LateFee>,
+}
+
+impl InvoiceTerms {
+    /// Early-payment discount off `amount`; `None` on overflow.
+    pub fn discount_on(&self, amount: u64) -> Option<u64> {
+        Some(amount.checked_mul(self.discount_bps as u64)? / 10_000)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum LateFee {
+    /// Percentage of the base amount
+    Bps(u16),
+    /// Fixed USDC amount
+    Flat(u64),
+}
+
+/// How an invoice payment's amount was arrived at. Recorded on the `Payment`:
+/// `amount = base_amount - discount_amount + late_fee_amount`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct InvoiceBreakdown {
+    pub base_amount: u64,
+    pub discount_amount: u64,
+    pub late_fee_amount: u64,
+}
+
+impl InvoiceBreakdown {
+    pub fn total(&self) -> Option<u64> {
+        self.base_amount
+            .checked_sub(self.discount_amount)?
+            .checked_add(self.late_fee_amount)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum InvoiceStatus {
+    Open,
+    Paid,
+    /// Voided directly or replaced by `reissue_invoice`
+    Voided,
+}
@@ -1,9 +1,88 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+use crate::state::{FeeMode, FeeSource};
 
 #[account]
 #[derive(InitSpace)]
 pub struct Merchant {
-    #[max_len(200)]
+    #[max_len(64)]
+    pub merchant_id: String,
+    pub authority: Pubkey,
+    pub settlement_wallet: Pubkey,
+    pub fee: Option<u16>,               // Negotiated fee override in bps; None = platform default
+    pub volume: u64,
+    pub total_fees: u64,
+    pub transaction_count: u64,
+    pub created_at: i64,
+    pub is_active: bool,
+    pub bump: u8,
+    // Fields below were added after launch
+    pub pending_settlement_wallet: Option<Pubkey>, // Staged wallet, applied after the platform timelock
+    pub settlement_wallet_effective_at: i64,       // When the staged wallet may be applied
+    pub refund_window: Option<i64>,     // Merchant's refund window in seconds; None = platform default
+    pub suspension_reason: Option<SuspensionReason>, // Set by the platform authority; blocks new payments
+    pub suspended_at: Option<i64>,
+    pub open_disputes: u16,             // Disputes awaiting a ruling
+    pub fee_mode: FeeMode,              // Inclusive (fee out of the price) or Additive (fee on top)
+    pub pending_authority: Option<Pubkey>, // Staged authority; the nominee accepts after the timelock
+    pub authority_effective_at: i64,       // When the staged authority may accept
+    pub previous_authority: Option<Pubkey>, // Authority replaced by the last handover
+    pub authority_changed_at: i64,          // When that handover happened
+    pub reserve_locked: u64,            // Reserve held back for open disputes; can't be withdrawn
+}
+
+impl Merchant {
+    pub const SEED: &'static [u8] = b"merchant";
+    /// Seed of the merchant's USDC reserve, which funds dispute rulings.
+    pub const RESERVE_SEED: &'static [u8] = b"merchant_reserve";
+    /// Longest accepted `merchant_id` (matches `#[max_len]` above).
+    pub const MAX_ID_LEN: usize = 64;
+
+    /// PDA seed for a merchant ID. IDs are hashed so any length up to
+    /// `MAX_ID_LEN` fits Solana's 32-byte per-seed limit.
+    pub fn id_seed(merchant_id: &str) -> [u8; 32] {
+        hash(merchant_id.as_bytes()).to_bytes()
+    }
+
+    /// Address the merchant had before IDs were hashed (`[b"merchant", merchant_id]`).
+    /// `None` for IDs over 32 bytes, which could never have been raw seeds.
+    pub fn legacy_address(merchant_id: &str) -> Option<Pubkey> {
+        Pubkey::try_find_program_address(&[Self::SEED, merchant_id.as_bytes()], &crate::ID)
+            .map(|(address, _)| address)
+    }
+
+    /// Fee rate applied to this merchant's payments: the negotiated override
+    /// if one is set, otherwise the platform default.
+    pub fn fee_terms(&self, platform_fee_bps: u64) -> (u64, FeeSource) {
+        match self.fee {
+            Some(fee_bps) => (fee_bps as u64, FeeSource::MerchantOverride),
+            None => (platform_fee_bps, FeeSource::Platform),
+        }
+    }
+
+    /// Whether `key` may cancel a staged settlement wallet change: the current
+    /// authority, or the one it replaced for one timelock period after the
+    /// handover, so a stolen key can't rotate both and lock the owner out.
+    pub fn can_cancel_wallet_change(&self, key: &Pubkey, now: i64, delay: i64) -> bool {
+        *key == self.authority
+            || (self.previous_authority == Some(*key)
+                && now < self.authority_changed_at.saturating_add(delay))
+    }
+
+    /// Refund window applied to this merchant's payments: the merchant's own
+    /// setting if there is one, otherwise the platform default.
+    pub fn refund_window(&self, platform_refund_window: i64) -> i64 {
+        self.refund_window.unwrap_or(platform_refund_window)
+    }
+}
+
+/// Merchant layout written before merchant IDs were hashed into the PDA seed.
+/// These accounts live at `[b"merchant", merchant_id.as_bytes()]` and are only
+/// read by `migrate_merchant`. `fee` was recorded at registration but never
+/// charged, so it is dropped rather than carried over as an override.
+#[derive(AnchorDeserialize)]
+pub struct LegacyMerchant {
     pub merchant_id: String,
     pub authority: Pubkey,
     pub settlement_wallet: Pubkey,
@@ -14,4 +93,12 @@ pub struct Merchant {
     pub created_at: i64,
     pub is_active: bool,
     pub bump: u8,
-}
\ No newline at end of file
+}
+
+/// Compliance reason recorded when the platform suspends a merchant.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum SuspensionReason {
+    LicenseLapse,
+    FraudReview,
+    ChargebackRatio,
+}
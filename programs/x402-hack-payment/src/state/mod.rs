@@ -1,11 +1,27 @@
 pub mod merchant;
 pub mod platform;
+pub mod attestation;
+pub mod authorization;
+pub mod checkout_session;
 pub mod customer;
+pub mod dispute;
+pub mod invoice;
 pub mod payment;
 pub mod private_receipt;
+pub mod purchase_order;
+pub mod refund_request;
+pub mod subscription;
 
 pub use merchant::*;
 pub use platform::*;
+pub use attestation::*;
+pub use authorization::*;
+pub use checkout_session::*;
 pub use customer::*;
+pub use dispute::*;
+pub use invoice::*;
 pub use payment::*;
-pub use private_receipt::*;
\ No newline at end of file
+pub use private_receipt::*;
+pub use purchase_order::*;
+pub use refund_request::*;
+pub use subscription::*;
\ No newline at end of file
@@ -1,4 +1,7 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::{hash, hashv};
+
+use crate::state::InvoiceBreakdown;
 
 #[account]
 #[derive(InitSpace)]
@@ -7,21 +10,169 @@ pub struct Payment {
     pub payment_id: String,
     pub customer: Pubkey,
     pub merchant: Pubkey,
+    /// Total charged to the customer, excluding any sponsor fee: `merchant_amount + fee_amount`
     pub amount: u64,
     pub fee_amount: u64,
     pub merchant_amount: u64,
     pub status: PaymentStatus,
     pub created_at: i64,
+    /// Time of the most recent refund
     pub refunded_at: Option<i64>,
     pub bump: u8,
+    // Fields below were added after launch
+    pub fee_bps: u64,
+    pub fee_source: FeeSource,
+    /// Whether the fee came out of the merchant's price or was charged on top
+    pub fee_mode: FeeMode,
+    /// Total returned to the customer so far, across all refunds
+    pub refunded_amount: u64,
+    /// Refunds are rejected after this time; the payment can then be finalized
+    pub refund_deadline: i64,
+    /// A customer refund request is awaiting a decision; blocks finalization
+    pub refund_request_open: bool,
+    /// A dispute is awaiting the arbiter's ruling; blocks finalization
+    pub dispute_open: bool,
+    /// Escrowed payments only: when the escrow auto-releases to the merchant,
+    /// or for attestation-gated escrow, when it can be refunded to the customer
+    pub escrow_release_at: Option<i64>,
+    /// Escrow only releases on a passing attestation from an approved attester
+    pub requires_attestation: bool,
+    /// Invoice payments only: base amount, discount and late fee behind `amount`
+    pub invoice_breakdown: Option<InvoiceBreakdown>,
+    /// Gas reimbursement paid out of the customer's transfer. Not part of
+    /// `amount`, so it carries no platform fee and is never refunded
+    pub sponsor_fee: u64,
+    /// Registered sponsor that received `sponsor_fee`
+    pub sponsor: Option<Pubkey>,
+    /// Purchase order, invoice or subscription this payment was recorded for.
+    /// Such payments are addressed by `derived_seed(parent, parent_index)`
+    /// instead of their ID, so the ID can't be taken ahead of time
+    pub parent: Option<Pubkey>,
+    /// Milestone or billing cycle within `parent`; 0 for invoices
+    pub parent_index: u64,
+    /// Merchant share of a dispute ruling the reserve couldn't cover. Still
+    /// owed to the customer and settled first by the next refund; its fee leg
+    /// was already returned. Blocks finalization while non-zero
+    pub dispute_shortfall: u64,
 }
 
 impl Payment {
     pub const SEED: &'static [u8] = b"payment";
+    /// Seed of the USDC account holding an escrowed payment until release.
+    pub const ESCROW_SEED: &'static [u8] = b"escrow";
+    /// Longest accepted `payment_id` (matches `#[max_len]` above).
+    pub const MAX_ID_LEN: usize = 64;
+
+    /// PDA seed for a payment ID. IDs are hashed so any length up to
+    /// `MAX_ID_LEN` fits Solana's 32-byte per-seed limit.
+    pub fn id_seed(payment_id: &str) -> [u8; 32] {
+        hash(payment_id.as_bytes()).to_bytes()
+    }
+
+    /// PDA seed for a payment derived from a parent account. The leading
+    /// 0xff byte never occurs in UTF-8, so this can't equal `id_seed` of any ID.
+    pub fn derived_seed(parent: &Pubkey, index: u64) -> [u8; 32] {
+        hashv(&[&[0xff], parent.as_ref(), &index.to_le_bytes()]).to_bytes()
+    }
+
+    /// Last PDA seed of this payment, whichever way it was addressed.
+    pub fn address_seed(&self) -> [u8; 32] {
+        match self.parent {
+            Some(parent) => Self::derived_seed(&parent, self.parent_index),
+            None => Self::id_seed(&self.payment_id),
+        }
+    }
+
+    /// Amount that can still be refunded.
+    pub fn refundable_amount(&self) -> u64 {
+        self.amount.saturating_sub(self.refunded_amount)
+    }
+
+    /// Part of `fee_amount` not yet returned by refunds.
+    pub fn retained_fee(&self) -> Option<u64> {
+        self.fee_amount.checked_sub(self.fee_share(self.fee_refunded_basis()?)?)
+    }
+
+    /// Split a refund into its (merchant, fee) legs. The fee leg is the change in
+    /// the pro-rata fee share of the cumulative refunded amount, so rounding never
+    /// drifts and a payment refunded in full returns exactly `fee_amount`. Since
+    /// `amount` is the total charged in either fee mode, a full refund of an
+    /// additive payment returns the merchant's whole price plus the surcharge.
+    /// Any `dispute_shortfall` is settled first, entirely from the merchant.
+    pub fn refund_split(&self, refund_amount: u64) -> Option<(u64, u64)> {
+        let basis = self.fee_refunded_basis()?;
+        let beyond_shortfall = refund_amount.saturating_sub(self.dispute_shortfall);
+        let fee_leg = self.fee_share(basis.checked_add(beyond_shortfall)?)?.checked_sub(self.fee_share(basis)?)?;
+        let merchant_leg = refund_amount.checked_sub(fee_leg)?;
+        Some((merchant_leg, fee_leg))
+    }
+
+    /// Amount whose fee leg has already gone back to the customer: everything
+    /// refunded plus the unpaid part of a dispute ruling.
+    fn fee_refunded_basis(&self) -> Option<u64> {
+        self.refunded_amount.checked_add(self.dispute_shortfall)
+    }
+
+    /// Pro-rata share of `fee_amount` in `refunded` of `amount`.
+    fn fee_share(&self, refunded: u64) -> Option<u64> {
+        let share = (self.fee_amount as u128)
+            .checked_mul(refunded as u128)?
+            .checked_div(self.amount as u128)?;
+        u64::try_from(share).ok()
+    }
+}
+
+/// Payment layout written before payment IDs were scoped per merchant.
+/// These accounts live at `[b"payment", payment_id.as_bytes()]` and are only
+/// read by `migrate_payment`.
+#[derive(AnchorDeserialize)]
+pub struct LegacyPayment {
+    pub payment_id: String,
+    pub customer: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub fee_amount: u64,
+    pub merchant_amount: u64,
+    pub status: PaymentStatus,
+    pub created_at: i64,
+    pub refunded_at: Option<i64>,
+    pub bump: u8,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
 pub enum PaymentStatus {
     Completed,
     Refunded,
+    /// Some, but not all, of `amount` has been refunded.
+    /// Declared after the original variants so existing accounts keep their Borsh discriminants.
+    PartiallyRefunded,
+    /// Refund window has closed; revenue is final
+    Finalized,
+    /// Funds are held in escrow until delivery is confirmed or the timeout passes
+    Escrowed,
+    /// Escrow was cancelled by the merchant and returned to the customer
+    Cancelled,
+}
+
+/// Which rate `Payment.fee_amount` was computed from.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum FeeSource {
+    /// `Platform.fee_bps`
+    Platform,
+    /// Per-merchant override in `Merchant.fee`
+    MerchantOverride,
+}
+
+/// How the platform fee is applied to a merchant's price. The merchant's mode
+/// only applies where the caller names the price at payment time
+/// (`process_payment`, escrowed payments). Amounts fixed on-chain beforehand —
+/// invoices, checkout sessions, subscriptions, authorizations, purchase orders
+/// and signed intents — are charged exactly, so those always use `Inclusive`
+/// and report it in their own event's `fee_mode`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum FeeMode {
+    /// Fee is deducted from the price; the merchant receives `amount - fee`
+    Inclusive,
+    /// Fee is a surcharge; the merchant receives `amount`, the customer pays `amount + fee`
+    Additive,
 }
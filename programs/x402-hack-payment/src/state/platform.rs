@@ -11,9 +11,95 @@ pub struct Platform {
     pub is_active: bool,
     pub bump: u8,
     pub treasury_bump: u8,
+    // Fields below were added after launch; deployed configs gain them via `migrate_platform_config`
+    pub pending_authority: Option<Pubkey>, // Proposed authority awaiting accept_authority
+    pub accrued_fees: u64,              // Unclaimed fee revenue held in the treasury
+    pub settlement_wallet_delay: i64,   // Seconds before a staged merchant settlement wallet or authority takes effect
+    pub refund_window: i64,             // Default seconds after payment during which refunds are allowed
+    pub arbiter: Pubkey,                // Rules on payment disputes
+    pub escrow_timeout: i64,            // Seconds before an unconfirmed escrow auto-releases to the merchant
+    #[max_len(16)]
+    pub attesters: Vec<Pubkey>,         // Approved labs/inspectors for attestation-gated escrow
+    pub authorization_ttl: i64,         // Seconds an uncaptured authorization holds funds before anyone can release it
+    pub max_sponsor_fee: u64,           // Cap on the USDC gas reimbursement a payment can carry; 0 disables sponsor fees
+    #[max_len(8)]
+    pub sponsors: Vec<Pubkey>,          // Registered relayers that may receive sponsor fees
+    pub pending_fees: u64,              // Fees on unfinalized payments; refundable, moved to accrued_fees on finalize
+    pub dispute_window: i64,            // Seconds after payment during which the customer can open a dispute
+    pub legacy_fees: u64,               // Audited pre-upgrade fees not yet released by `migrate_payment`
 }
 
 impl Platform {
     pub const SEED: &'static [u8] = b"platform_config";
     pub const TREASURY_SEED: &'static [u8] = b"platform_treasury";
+    /// Seed of the PDA customers approve as SPL token delegate. Subscriptions
+    /// and signed payment intents share it, since a token account holds a
+    /// single delegate at a time.
+    pub const DELEGATE_SEED: &'static [u8] = b"payment_delegate";
+    /// Upper bound on the platform fee (10%).
+    pub const MAX_FEE_BPS: u64 = 1000;
+    /// Default timelock on merchant settlement wallet changes (24h).
+    pub const DEFAULT_SETTLEMENT_WALLET_DELAY: i64 = 24 * 60 * 60;
+    /// Upper bound on the settlement wallet timelock (30 days).
+    pub const MAX_SETTLEMENT_WALLET_DELAY: i64 = 30 * 24 * 60 * 60;
+    /// Default refund window (30 days).
+    pub const DEFAULT_REFUND_WINDOW: i64 = 30 * 24 * 60 * 60;
+    /// Upper bound on any refund window, platform or merchant (180 days).
+    pub const MAX_REFUND_WINDOW: i64 = 180 * 24 * 60 * 60;
+    /// Default escrow auto-release timeout (14 days).
+    pub const DEFAULT_ESCROW_TIMEOUT: i64 = 14 * 24 * 60 * 60;
+    /// Upper bound on the escrow timeout (90 days).
+    pub const MAX_ESCROW_TIMEOUT: i64 = 90 * 24 * 60 * 60;
+    /// Default authorization hold lifetime (7 days).
+    pub const DEFAULT_AUTHORIZATION_TTL: i64 = 7 * 24 * 60 * 60;
+    /// Upper bound on the authorization hold lifetime (30 days).
+    pub const MAX_AUTHORIZATION_TTL: i64 = 30 * 24 * 60 * 60;
+    /// Default dispute window (60 days).
+    pub const DEFAULT_DISPUTE_WINDOW: i64 = 60 * 24 * 60 * 60;
+    /// Upper bound on the dispute window (180 days).
+    pub const MAX_DISPUTE_WINDOW: i64 = 180 * 24 * 60 * 60;
+    /// Size of the approved attester registry.
+    pub const MAX_ATTESTERS: usize = 16;
+    /// Size of the registered sponsor list.
+    pub const MAX_SPONSORS: usize = 8;
+
+    pub fn is_attester(&self, key: &Pubkey) -> bool {
+        self.attesters.contains(key)
+    }
+
+    pub fn is_sponsor(&self, key: &Pubkey) -> bool {
+        self.sponsors.contains(key)
+    }
+
+    /// Last moment a payment created at `created_at` can be disputed.
+    pub fn dispute_deadline(&self, created_at: i64) -> Option<i64> {
+        created_at.checked_add(self.dispute_window)
+    }
+
+    /// Portion of the treasury balance that is payout escrow rather than fee revenue.
+    pub fn escrow_balance(&self, treasury_balance: u64) -> u64 {
+        treasury_balance
+            .saturating_sub(self.accrued_fees)
+            .saturating_sub(self.pending_fees)
+            .saturating_sub(self.legacy_fees)
+    }
+}
+
+/// Platform config layout written before any of the appended fields existed.
+/// Only read by `migrate_platform_config`.
+#[derive(AnchorDeserialize)]
+pub struct LegacyPlatform {
+    pub authority: Pubkey,
+    pub treasury: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub min_payment_amount: u64,
+    pub fee_bps: u64,
+    pub is_active: bool,
+    pub bump: u8,
+    pub treasury_bump: u8,
+}
+
+impl LegacyPlatform {
+    /// Serialized size, discriminator included.
+    pub const LEN: usize = 8 + 32 * 3 + 8 + 8 + 1 + 1 + 1;
 }
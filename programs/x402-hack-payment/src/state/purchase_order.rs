@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+use crate::state::FeeSource;
+
+/// A purchase order paid in stages. The buyer funds the whole order up front
+/// into a platform-owned escrow; each milestone is then released on its own
+/// and becomes a regular `Payment`. Past `deliver_by` the buyer can cancel
+/// whatever hasn't been released or fallen due and take it back.
+#[account]
+#[derive(InitSpace)]
+pub struct PurchaseOrder {
+    #[max_len(60)]
+    pub order_id: String,
+    pub buyer: Pubkey,
+    pub merchant: Pubkey,
+    pub total_amount: u64,
+    /// Sum of milestone amounts released so far
+    pub released_amount: u64,
+    pub released_count: u8,
+    /// Sum of milestone amounts returned to the buyer by `cancel_purchase_order`
+    pub cancelled_amount: u64,
+    /// Buyer may cancel outstanding milestones after this time
+    pub deliver_by: i64,
+    /// Fee terms snapshotted at creation so the buyer's quote can't change
+    pub fee_bps: u64,
+    pub fee_source: FeeSource,
+    #[max_len(10)]
+    pub milestones: Vec<Milestone>,
+    pub status: PurchaseOrderStatus,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl PurchaseOrder {
+    pub const SEED: &'static [u8] = b"purchase_order";
+    /// Seed of the USDC account holding the unreleased balance.
+    pub const ESCROW_SEED: &'static [u8] = b"purchase_order_escrow";
+    /// Longest accepted `order_id`. Leaves room for the milestone suffix in
+    /// the payment IDs derived from it.
+    pub const MAX_ID_LEN: usize = 60;
+    pub const MAX_MILESTONES: usize = 10;
+
+    pub fn id_seed(order_id: &str) -> [u8; 32] {
+        hash(order_id.as_bytes()).to_bytes()
+    }
+
+    /// Payment ID recorded for a released milestone, e.g. `po-123-m0`. Only a
+    /// label: the payment is addressed by `Payment::derived_seed(order, index)`.
+    pub fn milestone_payment_id(&self, index: u8) -> String {
+        format!("{}-m{}", self.order_id, index)
+    }
+
+    /// No milestone is left to release or cancel.
+    pub fn is_settled(&self) -> bool {
+        self.milestones.iter().all(|m| m.status != MilestoneStatus::Pending)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, InitSpace)]
+pub struct Milestone {
+    pub amount: u64,
+    /// Anyone may release after this time; `None` means buyer approval only
+    pub release_after: Option<i64>,
+    pub status: MilestoneStatus,
+    pub released_at: Option<i64>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum MilestoneStatus {
+    Pending,
+    Released,
+    /// Returned to the buyer by `cancel_purchase_order`
+    Cancelled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum PurchaseOrderStatus {
+    /// At least one milestone is still pending
+    Open,
+    /// Every milestone has been released
+    Completed,
+    /// The buyer cancelled the milestones outstanding after `deliver_by`;
+    /// any that had already fallen due can still be released
+    Cancelled,
+}
@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+
+/// A customer's on-chain request to get money back on a `Payment`.
+///
+/// The merchant approves (which runs the refund) or denies it before
+/// `respond_by`. Once that deadline passes without an answer the request is
+/// escalated and the platform authority rules on it, paying an approval from
+/// the merchant reserve. A request nobody answers by `expires_at` can be
+/// closed by anyone so it stops blocking finalization.
+#[account]
+#[derive(InitSpace)]
+pub struct RefundRequest {
+    pub payment: Pubkey,
+    pub customer: Pubkey,
+    pub merchant: Pubkey,
+    /// Amount the customer is asking for
+    pub amount: u64,
+    pub reason: RefundReason,
+    /// Hash of off-chain evidence (photos, tracking, messages)
+    pub evidence_hash: [u8; 32],
+    /// Hash of the merchant's or platform's written response
+    pub response_hash: Option<[u8; 32]>,
+    pub status: RefundRequestStatus,
+    pub opened_at: i64,
+    /// Merchant must answer by this time or the request escalates
+    pub respond_by: i64,
+    /// After this an unanswered request can be expired by anyone
+    pub expires_at: i64,
+    pub resolved_at: Option<i64>,
+    pub bump: u8,
+}
+
+impl RefundRequest {
+    pub const SEED: &'static [u8] = b"refund_request";
+    /// How long the merchant has to answer a request (3 days).
+    pub const RESPONSE_WINDOW: i64 = 3 * 24 * 60 * 60;
+    /// How long the platform has to rule once a request escalates (7 days).
+    pub const REVIEW_WINDOW: i64 = 7 * 24 * 60 * 60;
+
+    /// An open request the merchant failed to answer in time.
+    pub fn is_escalated(&self, now: i64) -> bool {
+        self.status == RefundRequestStatus::Open && now > self.respond_by
+    }
+
+    /// An open request nobody answered before `expires_at`.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.status == RefundRequestStatus::Open && now > self.expires_at
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum RefundReason {
+    NotReceived,
+    NotAsDescribed,
+    Damaged,
+    Duplicate,
+    Other,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum RefundRequestStatus {
+    /// Waiting on the merchant (or, past `respond_by`, on the platform)
+    Open,
+    /// Refund paid out
+    Approved,
+    Denied,
+    /// Nobody answered before `expires_at`
+    Expired,
+}
@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+
+/// A recurring charge. The customer approves the subscription delegate PDA on
+/// their USDC account at signup; `charge_subscription` then pulls one cycle at
+/// a time and records each as a regular `Payment`.
+#[account]
+#[derive(InitSpace)]
+pub struct Subscription {
+    #[max_len(56)]
+    pub subscription_id: String,
+    pub customer: Pubkey,
+    pub merchant: Pubkey,
+    /// Charged every cycle
+    pub amount: u64,
+    /// Seconds between charges
+    pub interval: i64,
+    /// The next cycle can be charged from this time
+    pub next_due: i64,
+    pub max_cycles: u32,
+    pub cycles_charged: u32,
+    pub status: SubscriptionStatus,
+    pub created_at: i64,
+    pub cancelled_at: Option<i64>,
+    pub bump: u8,
+}
+
+impl Subscription {
+    pub const SEED: &'static [u8] = b"subscription";
+    /// Longest accepted `subscription_id`. Leaves room for the cycle suffix in
+    /// the payment IDs derived from it.
+    pub const MAX_ID_LEN: usize = 56;
+
+    pub fn id_seed(subscription_id: &str) -> [u8; 32] {
+        hash(subscription_id.as_bytes()).to_bytes()
+    }
+
+    /// Payment ID recorded for a charged cycle, e.g. `sub-42-c0`. It's a label
+    /// only: the payment itself is addressed from the subscription and cycle.
+    pub fn cycle_payment_id(&self, cycle: u32) -> String {
+        format!("{}-c{}", self.subscription_id, cycle)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum SubscriptionStatus {
+    Active,
+    /// Cancelled by the customer or the merchant
+    Cancelled,
+    /// Every cycle up to `max_cycles` has been charged
+    Completed,
+}
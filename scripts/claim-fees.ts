@@ -61,14 +61,7 @@ async function main() {
     // Get treasury USDC balance - the treasury PDA IS the token account
     try {
         const treasuryBalance = await connection.getTokenAccountBalance(platformTreasuryPDA);
-        const balanceUsdc = parseFloat(treasuryBalance.value.uiAmountString || "0");
-
-        console.log(`\n💵 Treasury Balance: $${balanceUsdc.toFixed(2)} USDC`);
-
-        if (balanceUsdc === 0) {
-            console.log("\n✅ No fees to claim. Treasury is empty.");
-            return;
-        }
+        console.log(`\n💵 Treasury Balance: $${parseFloat(treasuryBalance.value.uiAmountString || "0").toFixed(2)} USDC`);
 
         // Authority's USDC ATA
         const authorityAta = await getAssociatedTokenAddress(USDC_MINT, authority.publicKey);
@@ -110,6 +103,17 @@ async function main() {
         const idl = JSON.parse(fs.readFileSync(idlPath, "utf-8"));
         const program = new Program(idl, provider);
 
+        // Only fees from finalized payments are claimable; pending fees stay refundable
+        // and the rest of the treasury is payout escrow
+        const platform: any = await (program.account as any).platform.fetch(platformConfigPDA);
+        const balanceUsdc = platform.accruedFees.toNumber() / 1_000_000;
+        console.log(`🧾 Accrued Fees: $${balanceUsdc.toFixed(2)} USDC`);
+
+        if (balanceUsdc === 0) {
+            console.log("\n✅ No fees to claim.");
+            return;
+        }
+
         console.log("\n🚀 Claiming fees...");
 
         // Call claim_platform_fees
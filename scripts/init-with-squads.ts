@@ -103,12 +103,12 @@ async function main() {
     if (existingAccount) {
         console.log("\n⚠️  Platform already initialized. Checking authority...");
 
-        // Try to transfer authority to Squads if we're still the authority
+        // Try to propose Squads as authority if we're still the authority
         try {
-            console.log("\n🔄 Transferring authority to Squads...");
+            console.log("\n🔄 Proposing Squads vault as authority...");
 
             const tx = await program.methods
-                .transferAuthority()
+                .proposeAuthority()
                 .accounts({
                     authority: payer.publicKey,
                     newAuthority: SQUADS_VAULT,
@@ -117,8 +117,9 @@ async function main() {
                 .signers([payer])
                 .rpc();
 
-            console.log(`\n✅ Authority transferred to Squads!`);
+            console.log(`\n✅ Squads vault proposed as authority!`);
             console.log(`   Transaction: https://explorer.solana.com/tx/${tx}?cluster=devnet`);
+            console.log(`   Execute 'accept_authority' from the vault to complete the handover.`);
             console.log(`\n🛡️  Squads Vault: ${SQUADS_VAULT.toBase58()}`);
             console.log(`   Dashboard: https://devnet.squads.so/squads/${SQUADS_VAULT.toBase58()}/home`);
 
@@ -126,7 +127,7 @@ async function main() {
             if (error.message?.includes("Unauthorized")) {
                 console.log("\n✅ Authority already transferred (not owned by this wallet)");
             } else {
-                console.error("\n❌ Transfer failed:", error.message);
+                console.error("\n❌ Proposal failed:", error.message);
             }
         }
         return;
@@ -153,10 +154,10 @@ async function main() {
         console.log(`   Transaction: https://explorer.solana.com/tx/${initTx}?cluster=devnet`);
 
         // Step 2: Transfer authority to Squads
-        console.log("\n🔄 Transferring authority to Squads...");
+        console.log("\n🔄 Proposing Squads vault as authority...");
 
         const transferTx = await program.methods
-            .transferAuthority()
+            .proposeAuthority()
             .accounts({
                 authority: payer.publicKey,
                 newAuthority: SQUADS_VAULT,
@@ -165,8 +166,9 @@ async function main() {
             .signers([payer])
             .rpc();
 
-        console.log(`\n✅ Authority transferred to Squads!`);
+        console.log(`\n✅ Squads vault proposed as authority!`);
         console.log(`   Transaction: https://explorer.solana.com/tx/${transferTx}?cluster=devnet`);
+        console.log(`   Execute 'accept_authority' from the vault to complete the handover.`);
 
         console.log("\n" + "=".repeat(50));
         console.log("🎉 SETUP COMPLETE!");
@@ -3,12 +3,16 @@
  *
  * One-shot setup that:
  *   1. Creates a new Squads v4 multisig with the configured members + threshold
- *   2. Calls `transfer_authority` on the platform program to point
- *      Platform.authority at the new vault PDA
+ *   2. Calls `propose_authority` on the platform program to nominate
+ *      the new vault PDA as Platform.authority
  *   3. Prints the multisig PDA + vault PDA so you can set
  *      `PLATFORM_MULTISIG_PDA` in your frontend env
  *
- * After running this, the on-chain `claim_platform_fees` instruction can
+ * The handover completes once the vault executes `accept_authority`
+ * through a Squads vault transaction. Until then the current authority
+ * can withdraw the nomination with `cancel_authority_transfer`.
+ *
+ * After accepting, the on-chain `claim_platform_fees` instruction can
  * only be invoked by the multisig vault (via Squads `vaultTransactionExecute`
  * CPI). The admin UI auto-detects the multisig and switches to a
  * propose / approve / execute flow.
@@ -205,8 +209,8 @@ async function main() {
     );
     console.log(`✅ Multisig created: ${createSig}\n`);
 
-    // ── 2. Transfer Platform.authority → vault PDA ──────────────
-    console.log("⏳ Transferring platform authority to vault PDA...");
+    // ── 2. Propose vault PDA as Platform.authority ──────────────
+    console.log("⏳ Proposing vault PDA as platform authority...");
 
     const idlPath = path.join(
         __dirname,
@@ -226,26 +230,27 @@ async function main() {
     );
     const program = new anchor.Program(idl as any, provider);
 
-    const transferSig = await program.methods
-        .transferAuthority()
+    const proposeSig = await program.methods
+        .proposeAuthority()
         .accounts({
             authority: payer.publicKey,
             newAuthority: vaultPda,
             platformConfig: platformConfigPDA,
         } as any)
         .rpc();
-    console.log(`✅ Authority transferred: ${transferSig}\n`);
+    console.log(`✅ Authority proposed: ${proposeSig}\n`);
 
     // ── 3. Verify ───────────────────────────────────────────────
-    const updated = await connection.getAccountInfo(platformConfigPDA);
-    const newAuthority = new PublicKey(updated!.data.slice(8, 8 + 32));
-    if (!newAuthority.equals(vaultPda)) {
+    const updated: any = await (program.account as any).platform.fetch(platformConfigPDA);
+    if (!updated.pendingAuthority || !updated.pendingAuthority.equals(vaultPda)) {
         console.error(
-            `❌ Verification failed. On-chain authority is now ${newAuthority.toBase58()}, expected ${vaultPda.toBase58()}.`,
+            `❌ Verification failed. On-chain pending authority is ${updated.pendingAuthority?.toBase58() ?? "none"}, expected ${vaultPda.toBase58()}.`,
         );
         process.exit(1);
     }
-    console.log(`✅ Verified: Platform.authority == ${vaultPda.toBase58()}\n`);
+    console.log(`✅ Verified: Platform.pending_authority == ${vaultPda.toBase58()}`);
+    console.log("   Next: create a Squads vault transaction calling `accept_authority`");
+    console.log("   with newAuthority = vault PDA, then approve + execute it.\n");
 
     // ── 4. Print env config ─────────────────────────────────────
     console.log("=".repeat(60));
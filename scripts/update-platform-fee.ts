@@ -6,8 +6,7 @@
 
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
-import { PublicKey, SystemProgram } from "@solana/web3.js";
-import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { PublicKey } from "@solana/web3.js";
 import { X402HackPayment } from "../target/types/x402_hack_payment";
 
 async function main() {
@@ -23,9 +22,6 @@ async function main() {
     // Minimum payment: 10000 = 0.01 USDC (6 decimals)
     const MIN_PAYMENT_AMOUNT = 10000;
 
-    // USDC Devnet mint
-    const USDC_MINT = new PublicKey("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU");
-
     // Derive PDAs
     const [platformConfigPDA] = PublicKey.findProgramAddressSync(
         [Buffer.from("platform_config")],
@@ -47,17 +43,22 @@ async function main() {
 
     try {
         const tx = await program.methods
-            .setPlatformConfig(
-                new anchor.BN(NEW_FEE_BPS),
-                new anchor.BN(MIN_PAYMENT_AMOUNT)
-            )
-            .accountsStrict({
+            .updatePlatformConfig({
+                feeBps: new anchor.BN(NEW_FEE_BPS),
+                minPaymentAmount: new anchor.BN(MIN_PAYMENT_AMOUNT),
+                isActive: null,
+                settlementWalletDelay: null,
+                refundWindow: null,
+                arbiter: null,
+                escrowTimeout: null,
+                authorizationTtl: null,
+                maxSponsorFee: null,
+                disputeWindow: null,
+            })
+            // Event authority and program accounts are resolved from the IDL
+            .accountsPartial({
                 authority: provider.wallet.publicKey,
                 platformConfig: platformConfigPDA,
-                platformTreasury: platformTreasuryPDA,
-                usdcMint: USDC_MINT,
-                systemProgram: SystemProgram.programId,
-                tokenProgram: TOKEN_PROGRAM_ID,
             })
             .rpc();
 
@@ -8,7 +8,7 @@
 
 import * as anchor from "@coral-xyz/anchor";
 import { BN, Program } from "@coral-xyz/anchor";
-import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { Ed25519Program, Keypair, PublicKey, SystemProgram, SYSVAR_INSTRUCTIONS_PUBKEY } from "@solana/web3.js";
 import {
     createMint,
     getOrCreateAssociatedTokenAccount,
@@ -16,15 +16,31 @@ import {
     TOKEN_PROGRAM_ID,
     ASSOCIATED_TOKEN_PROGRAM_ID,
     getAssociatedTokenAddress,
+    approve,
+    getAccount,
 } from "@solana/spl-token";
 import { expect } from "chai";
+import { createHash } from "crypto";
 import { X402HackPayment } from "../target/types/x402_hack_payment";
 
+// PDA seeds hash IDs so they fit Solana's 32-byte seed limit (mirrors `Merchant::id_seed` / `Payment::id_seed`)
+const idSeed = (id: string): Buffer => createHash("sha256").update(id).digest();
+// Seed of a payment derived from a parent account (mirrors `Payment::derived_seed`)
+const derivedSeed = (parent: PublicKey, index: number): Buffer =>
+    createHash("sha256")
+        .update(Buffer.concat([Buffer.from([0xff]), parent.toBuffer(), new BN(index).toArrayLike(Buffer, "le", 8)]))
+        .digest();
+
 describe("x402-hack-payment edge cases", () => {
     const provider = anchor.AnchorProvider.env();
     anchor.setProvider(provider);
 
     const program = anchor.workspace.X402HackPayment as Program<X402HackPayment>;
+    // Signer for self-CPI event emission (`emit_cpi!`)
+    const [eventAuthorityPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("__event_authority")],
+        program.programId,
+    );
     const connection = provider.connection;
     const authority = provider.wallet;
 
@@ -47,7 +63,7 @@ describe("x402-hack-payment edge cases", () => {
         );
 
         [merchantAccountPDA] = PublicKey.findProgramAddressSync(
-            [Buffer.from("merchant"), Buffer.from(MERCHANT_ID)],
+            [Buffer.from("merchant"), idSeed(MERCHANT_ID)],
             program.programId,
         );
 
@@ -69,6 +85,8 @@ describe("x402-hack-payment edge cases", () => {
                 usdcMint: usdcMint,
                 systemProgram: SystemProgram.programId,
                 tokenProgram: TOKEN_PROGRAM_ID,
+                eventAuthority: eventAuthorityPDA,
+                program: program.programId,
             })
             .rpc();
 
@@ -81,6 +99,8 @@ describe("x402-hack-payment edge cases", () => {
                 platformConfig: platformConfigPDA,
                 settlementWallet: settlementWallet.publicKey,
                 systemProgram: SystemProgram.programId,
+                eventAuthority: eventAuthorityPDA,
+                program: program.programId,
             })
             .rpc();
     });
@@ -92,7 +112,7 @@ describe("x402-hack-payment edge cases", () => {
 
             const paymentId = `pay_min_${Date.now()}`;
             const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
-                [Buffer.from("payment"), Buffer.from(paymentId)],
+                [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
                 program.programId,
             );
             const [customerAccountPDA] = PublicKey.findProgramAddressSync(
@@ -124,9 +144,10 @@ describe("x402-hack-payment edge cases", () => {
 
             try {
                 await program.methods
-                    .processPayment(paymentId, new BN(100)) // Below 10000 minimum
+                    .processPayment(paymentId, new BN(100), null) // Below 10000 minimum
                     .accountsStrict({
                         payer: user.publicKey,
+                        customer: user.publicKey,
                         platformConfig: platformConfigPDA,
                         paymentAccount: paymentAccountPDA,
                         customerAccount: customerAccountPDA,
@@ -135,9 +156,12 @@ describe("x402-hack-payment edge cases", () => {
                         customerUsdc: customerUsdcAccount.address,
                         merchantUsdc: merchantUsdcAccount.address,
                         platformTreasuryUsdc: platformTreasuryPDA,
+                        sponsorUsdc: null,
                         tokenProgram: TOKEN_PROGRAM_ID,
                         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
                         systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
                     })
                     .signers([user])
                     .rpc();
@@ -155,7 +179,7 @@ describe("x402-hack-payment edge cases", () => {
 
             const paymentId = `pay_insuf_${Date.now()}`;
             const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
-                [Buffer.from("payment"), Buffer.from(paymentId)],
+                [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
                 program.programId,
             );
             const [customerAccountPDA] = PublicKey.findProgramAddressSync(
@@ -180,9 +204,10 @@ describe("x402-hack-payment edge cases", () => {
 
             try {
                 await program.methods
-                    .processPayment(paymentId, new BN(50000))
+                    .processPayment(paymentId, new BN(50000), null)
                     .accountsStrict({
                         payer: user.publicKey,
+                        customer: user.publicKey,
                         platformConfig: platformConfigPDA,
                         paymentAccount: paymentAccountPDA,
                         customerAccount: customerAccountPDA,
@@ -191,9 +216,12 @@ describe("x402-hack-payment edge cases", () => {
                         customerUsdc: customerUsdcAccount.address,
                         merchantUsdc: merchantUsdcAccount.address,
                         platformTreasuryUsdc: platformTreasuryPDA,
+                        sponsorUsdc: null,
                         tokenProgram: TOKEN_PROGRAM_ID,
                         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
                         systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
                     })
                     .signers([user])
                     .rpc();
@@ -222,6 +250,8 @@ describe("x402-hack-payment edge cases", () => {
                         usdcMint: usdcMint,
                         systemProgram: SystemProgram.programId,
                         tokenProgram: TOKEN_PROGRAM_ID,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
                     })
                     .signers([fakeAuthority])
                     .rpc();
@@ -255,6 +285,8 @@ describe("x402-hack-payment edge cases", () => {
                         usdcMint: usdcMint,
                         tokenProgram: TOKEN_PROGRAM_ID,
                         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
                     })
                     .signers([fakeAuthority])
                     .rpc();
@@ -278,6 +310,8 @@ describe("x402-hack-payment edge cases", () => {
                         platformConfig: platformConfigPDA,
                         settlementWallet: settlementWallet.publicKey,
                         systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
                     })
                     .rpc();
                 expect.fail("Should have thrown - account already exists");
@@ -288,10 +322,11 @@ describe("x402-hack-payment edge cases", () => {
         });
     });
 
-    describe("Sequential Payments", () => {
-        it("should handle multiple payments in sequence", async () => {
+    describe("Merchant Suspension", () => {
+        it("should reject payments while suspended and accept them after reinstatement", async () => {
             const user = Keypair.generate();
-            await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
 
             const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
                 connection,
@@ -305,31 +340,22 @@ describe("x402-hack-payment edge cases", () => {
                 usdcMint,
                 settlementWallet.publicKey,
             );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
 
-            await mintTo(
-                connection,
-                authority.payer,
-                usdcMint,
-                customerUsdcAccount.address,
-                authority.publicKey,
-                1000000, // 1 USDC
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
             );
-
-            for (let i = 0; i < 3; i++) {
-                const paymentId = `pay_seq_${Date.now()}_${i}`;
+            const pay = (paymentId: string) => {
                 const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
-                    [Buffer.from("payment"), Buffer.from(paymentId)],
-                    program.programId,
-                );
-                const [customerAccountPDA] = PublicKey.findProgramAddressSync(
-                    [Buffer.from("customer"), user.publicKey.toBuffer()],
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
                     program.programId,
                 );
-
-                await program.methods
-                    .processPayment(paymentId, new BN(20000))
+                return program.methods
+                    .processPayment(paymentId, new BN(20000), null)
                     .accountsStrict({
                         payer: user.publicKey,
+                        customer: user.publicKey,
                         platformConfig: platformConfigPDA,
                         paymentAccount: paymentAccountPDA,
                         customerAccount: customerAccountPDA,
@@ -338,77 +364,2928 @@ describe("x402-hack-payment edge cases", () => {
                         customerUsdc: customerUsdcAccount.address,
                         merchantUsdc: merchantUsdcAccount.address,
                         platformTreasuryUsdc: platformTreasuryPDA,
+                        sponsorUsdc: null,
                         tokenProgram: TOKEN_PROGRAM_ID,
                         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
                         systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
                     })
                     .signers([user])
                     .rpc();
+            };
+
+            await program.methods
+                .suspendMerchant({ licenseLapse: {} })
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            const suspended = await program.account.merchant.fetch(merchantAccountPDA);
+            expect(suspended.suspensionReason).to.deep.equal({ licenseLapse: {} });
+            expect(suspended.suspendedAt).to.not.equal(null);
+
+            try {
+                await pay(`pay_susp_${Date.now()}`);
+                expect.fail("Should have thrown MerchantSuspended");
+            } catch (err: any) {
+                expect(err.toString()).to.include("MerchantSuspended");
             }
 
-            // If we get here, all 3 payments succeeded
-            expect(true).to.be.true;
+            await program.methods
+                .reinstateMerchant()
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            await pay(`pay_reinst_${Date.now()}`);
+            const reinstated = await program.account.merchant.fetch(merchantAccountPDA);
+            expect(reinstated.suspensionReason).to.equal(null);
+        });
+
+        it("should fail when non-authority suspends a merchant", async () => {
+            const fakeAuthority = Keypair.generate();
+            try {
+                await program.methods
+                    .suspendMerchant({ fraudReview: {} })
+                    .accountsStrict({
+                        authority: fakeAuthority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([fakeAuthority])
+                    .rpc();
+                expect.fail("Should have thrown Unauthorized");
+            } catch (err: any) {
+                expect(err.toString()).to.satisfy((msg: string) =>
+                    msg.includes("Unauthorized") || msg.includes("Constraint")
+                );
+            }
         });
     });
 
-    describe("Privacy - Private Receipt Edge Cases (MagicBlock PER)", () => {
-        const DELEGATION_PROGRAM_ID = new PublicKey('DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh');
+    describe("Partial Refunds", () => {
+        it("should refund pro-rata in steps until the full amount is returned", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
 
-        // Helper to derive private receipt PDA
-        function findPrivateReceiptPda(paymentId: string): [PublicKey, number] {
-            return PublicKey.findProgramAddressSync(
-                [Buffer.from('private_receipt'), Buffer.from(paymentId)],
-                program.programId
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+            // The merchant authority pulls the merchant leg from the settlement wallet's ATA
+            await approve(
+                connection,
+                authority.payer,
+                merchantUsdcAccount.address,
+                authority.publicKey,
+                settlementWallet,
+                1_000_000,
             );
-        }
 
-        it("should fail to issue private receipt with empty payment ID", async () => {
-            const user = Keypair.generate();
-            await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            const paymentId = `pay_partial_${Date.now()}`;
+            const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                program.programId,
+            );
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
 
-            const emptyPaymentId = "";
-            const amount = new anchor.BN(100000);
-            const feeAmount = new anchor.BN(1000);
+            await program.methods
+                .processPayment(paymentId, new BN(100000), null)
+                .accountsStrict({
+                    payer: user.publicKey,
+                    customer: user.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    paymentAccount: paymentAccountPDA,
+                    customerAccount: customerAccountPDA,
+                    usdcMint: usdcMint,
+                    customerUsdc: customerUsdcAccount.address,
+                    merchantUsdc: merchantUsdcAccount.address,
+                    platformTreasuryUsdc: platformTreasuryPDA,
+                    sponsorUsdc: null,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .signers([user])
+                .rpc();
+
+            const refund = (amount: number) =>
+                program.methods
+                    .refundPayment(new BN(amount))
+                    .accountsStrict({
+                        merchantAuthority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        paymentAccount: paymentAccountPDA,
+                        merchantAccount: merchantAccountPDA,
+                        customer: user.publicKey,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+
+            await refund(30000);
+            let payment = await program.account.payment.fetch(paymentAccountPDA);
+            expect(payment.status).to.deep.equal({ partiallyRefunded: {} });
+            expect(payment.refundedAmount.toNumber()).to.equal(30000);
 
             try {
-                const [privateReceiptPDA] = findPrivateReceiptPda(emptyPaymentId);
+                await refund(70001);
+                expect.fail("Should have thrown InvalidRefundAmount");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvalidRefundAmount");
+            }
+
+            await refund(70000);
+            payment = await program.account.payment.fetch(paymentAccountPDA);
+            expect(payment.status).to.deep.equal({ refunded: {} });
+            expect(payment.refundedAmount.toNumber()).to.equal(100000);
+
+            // Both legs, fee included, add back up to the original payment
+            const customerBalance = await connection.getTokenAccountBalance(customerUsdcAccount.address);
+            expect(customerBalance.value.amount).to.equal("100000");
+
+            try {
+                await refund(1);
+                expect.fail("Should have thrown PaymentAlreadyRefunded");
+            } catch (err: any) {
+                expect(err.toString()).to.include("PaymentAlreadyRefunded");
+            }
+        });
+    });
+
+    describe("Refund Window", () => {
+        it("should reject refunds after the window and let anyone finalize", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+            await approve(
+                connection,
+                authority.payer,
+                merchantUsdcAccount.address,
+                authority.publicKey,
+                settlementWallet,
+                1_000_000,
+            );
+
+            const setRefundWindow = (refundWindow: BN | null) =>
+                program.methods
+                    .setMerchantRefundWindow(refundWindow)
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        merchantAccount: merchantAccountPDA,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            const setDisputeWindow = (disputeWindow: number) =>
+                program.methods
+                    .updatePlatformConfig({
+                        feeBps: null,
+                        minPaymentAmount: null,
+                        isActive: null,
+                        settlementWalletDelay: null,
+                        refundWindow: null,
+                        arbiter: null,
+                        escrowTimeout: null,
+                        authorizationTtl: null,
+                        maxSponsorFee: null,
+                        disputeWindow: new BN(disputeWindow),
+                    })
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
 
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+            const pay = async (paymentId: string) => {
+                const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                    program.programId,
+                );
                 await program.methods
-                    .issuePrivateReceipt(emptyPaymentId, amount, feeAmount, "test")
+                    .processPayment(paymentId, new BN(20000), null)
                     .accountsStrict({
+                        payer: user.publicKey,
                         customer: user.publicKey,
-                        merchant: settlementWallet.publicKey,
-                        privateReceipt: privateReceiptPDA,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        paymentAccount: paymentAccountPDA,
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        sponsorUsdc: null,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
                         systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
                     })
                     .signers([user])
                     .rpc();
-                expect.fail("Should have thrown InvalidPaymentId");
+                return paymentAccountPDA;
+            };
+            const refund = (paymentAccountPDA: PublicKey) =>
+                program.methods
+                    .refundPayment(new BN(20000))
+                    .accountsStrict({
+                        merchantAuthority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        paymentAccount: paymentAccountPDA,
+                        merchantAccount: merchantAccountPDA,
+                        customer: user.publicKey,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            const finalize = (paymentAccountPDA: PublicKey) =>
+                program.methods
+                    .finalizePayment()
+                    .accountsStrict({
+                        paymentAccount: paymentAccountPDA,
+                        merchantAccount: merchantAccountPDA,
+                        platformConfig: platformConfigPDA,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+
+            // Under the platform default window the payment can't be finalized yet
+            const openPayment = await pay(`pay_window_open_${Date.now()}`);
+            try {
+                await finalize(openPayment);
+                expect.fail("Should have thrown RefundWindowOpen");
             } catch (err: any) {
-                // Should fail with InvalidPaymentId or validation error
-                expect(err.toString()).to.satisfy((msg: string) =>
-                    msg.includes("InvalidPaymentId") || msg.includes("Error")
-                );
+                expect(err.toString()).to.include("RefundWindowOpen");
+            }
+
+            // Claiming only sweeps finalized fees, so the open payment stays fully refundable
+            const authorityUsdc = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                authority.publicKey,
+            );
+            const { accruedFees } = await program.account.platform.fetch(platformConfigPDA);
+            if (accruedFees.toNumber() > 0) {
+                await program.methods
+                    .claimPlatformFees()
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        authorityUsdc: authorityUsdc.address,
+                        usdcMint: usdcMint,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            }
+            await refund(openPayment);
+            expect((await program.account.payment.fetch(openPayment)).status).to.deep.equal({ refunded: {} });
+
+            // A zero-second merchant window closes as soon as the clock moves on,
+            // but the payment can't be finalized while it can still be disputed
+            await setRefundWindow(new BN(0));
+            const closedPayment = await pay(`pay_window_closed_${Date.now()}`);
+            await setRefundWindow(null);
+            await new Promise((resolve) => setTimeout(resolve, 2000));
+            try {
+                await finalize(closedPayment);
+                expect.fail("Should have thrown DisputeWindowOpen");
+            } catch (err: any) {
+                expect(err.toString()).to.include("DisputeWindowOpen");
+            }
+            await setDisputeWindow(0);
+
+            try {
+                await refund(closedPayment);
+                expect.fail("Should have thrown RefundWindowClosed");
+            } catch (err: any) {
+                expect(err.toString()).to.include("RefundWindowClosed");
+            }
+
+            const before = await program.account.platform.fetch(platformConfigPDA);
+            await finalize(closedPayment);
+            await setDisputeWindow(60 * 24 * 60 * 60);
+            const finalized = await program.account.payment.fetch(closedPayment);
+            expect(finalized.status).to.deep.equal({ finalized: {} });
+
+            // Finalizing moves the fee from the refundable bucket to claimable revenue
+            const after = await program.account.platform.fetch(platformConfigPDA);
+            expect(after.pendingFees.toNumber()).to.equal(before.pendingFees.toNumber() - finalized.feeAmount.toNumber());
+            expect(after.accruedFees.toNumber()).to.equal(before.accruedFees.toNumber() + finalized.feeAmount.toNumber());
+
+            try {
+                await refund(closedPayment);
+                expect.fail("Should have thrown PaymentAlreadyFinalized");
+            } catch (err: any) {
+                expect(err.toString()).to.include("PaymentAlreadyFinalized");
             }
         });
+    });
 
-        it("should create private receipt and verify session status", async () => {
+    describe("Refund Requests", () => {
+        it("should let the customer request a refund and the merchant approve or deny it", async () => {
             const user = Keypair.generate();
-            await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
 
-            const paymentId = `pay_per_${Date.now()}`;
-            const amount = new anchor.BN(5_000_000); // 5 USDC
-            const feeAmount = new anchor.BN(50_000);  // 0.05 USDC
-            const [privateReceiptPDA] = findPrivateReceiptPda(paymentId);
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+            await approve(
+                connection,
+                authority.payer,
+                merchantUsdcAccount.address,
+                authority.publicKey,
+                settlementWallet,
+                1_000_000,
+            );
 
-            try {
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+            const pay = async (paymentId: string) => {
+                const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                    program.programId,
+                );
                 await program.methods
-                    .issuePrivateReceipt(paymentId, amount, feeAmount, "PER edge case test")
+                    .processPayment(paymentId, new BN(20000), null)
                     .accountsStrict({
+                        payer: user.publicKey,
+                        customer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        paymentAccount: paymentAccountPDA,
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        sponsorUsdc: null,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+                return paymentAccountPDA;
+            };
+            const openRequest = async (paymentAccountPDA: PublicKey, amount: number) => {
+                const [refundRequestPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("refund_request"), paymentAccountPDA.toBuffer()],
+                    program.programId,
+                );
+                await program.methods
+                    .openRefundRequest(new BN(amount), { notAsDescribed: {} }, Array(32).fill(7))
+                    .accountsStrict({
+                        customer: user.publicKey,
+                        paymentAccount: paymentAccountPDA,
+                        refundRequest: refundRequestPDA,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+                return refundRequestPDA;
+            };
+
+            // Approve: the requested amount goes back and the request closes out
+            const approvedPayment = await pay(`pay_request_approve_${Date.now()}`);
+            const approvedRequest = await openRequest(approvedPayment, 5000);
+
+            let payment = await program.account.payment.fetch(approvedPayment);
+            expect(payment.refundRequestOpen).to.be.true;
+
+            const balanceBefore = (await getAccount(connection, customerUsdcAccount.address)).amount;
+            await program.methods
+                .approveRefundRequest()
+                .accountsStrict({
+                    refund: {
+                        merchantAuthority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        paymentAccount: approvedPayment,
+                        merchantAccount: merchantAccountPDA,
+                        customer: user.publicKey,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    },
+                    refundRequest: approvedRequest,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            const balanceAfter = (await getAccount(connection, customerUsdcAccount.address)).amount;
+            expect(Number(balanceAfter - balanceBefore)).to.equal(5000);
+
+            const request = await program.account.refundRequest.fetch(approvedRequest);
+            expect(request.status).to.deep.equal({ approved: {} });
+            payment = await program.account.payment.fetch(approvedPayment);
+            expect(payment.refundedAmount.toNumber()).to.equal(5000);
+            expect(payment.refundRequestOpen).to.be.false;
+
+            // Deny: nothing moves, and the merchant can't deny twice
+            const deniedPayment = await pay(`pay_request_deny_${Date.now()}`);
+            const deniedRequest = await openRequest(deniedPayment, 20000);
+            const deny = () =>
+                program.methods
+                    .denyRefundRequest(Array(32).fill(9))
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        paymentAccount: deniedPayment,
+                        merchantAccount: merchantAccountPDA,
+                        refundRequest: deniedRequest,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            await deny();
+
+            const denied = await program.account.refundRequest.fetch(deniedRequest);
+            expect(denied.status).to.deep.equal({ denied: {} });
+            expect(denied.responseHash).to.deep.equal(Array(32).fill(9));
+
+            try {
+                await deny();
+                expect.fail("Should have thrown RefundRequestNotOpen");
+            } catch (err: any) {
+                expect(err.toString()).to.include("RefundRequestNotOpen");
+            }
+
+            // The platform can only step in once the merchant's deadline has passed
+            const escalatedPayment = await pay(`pay_request_escalate_${Date.now()}`);
+            const escalatedRequest = await openRequest(escalatedPayment, 1000);
+            try {
+                await program.methods
+                    .resolveRefundRequest(true, Array(32).fill(1))
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        paymentAccount: escalatedPayment,
+                        refundRequest: escalatedRequest,
+                        customer: user.publicKey,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantReserve: null,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+                expect.fail("Should have thrown RefundRequestNotEscalated");
+            } catch (err: any) {
+                expect(err.toString()).to.include("RefundRequestNotEscalated");
+            }
+
+            // Nor can anyone expire it before the platform's review window has passed
+            const escalated = await program.account.refundRequest.fetch(escalatedRequest);
+            expect(escalated.expiresAt.toNumber()).to.be.greaterThan(escalated.respondBy.toNumber());
+            try {
+                await program.methods
+                    .expireRefundRequest()
+                    .accountsStrict({
+                        paymentAccount: escalatedPayment,
+                        refundRequest: escalatedRequest,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+                expect.fail("Should have thrown RefundRequestNotExpired");
+            } catch (err: any) {
+                expect(err.toString()).to.include("RefundRequestNotExpired");
+            }
+        });
+    });
+
+    describe("Disputes", () => {
+        it("should hold the merchant reserve while disputed and pay the ruling from it", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            const authorityUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                authority.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+            await mintTo(connection, authority.payer, usdcMint, authorityUsdcAccount.address, authority.publicKey, 50000);
+
+            const [merchantReservePDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("merchant_reserve"), merchantAccountPDA.toBuffer()],
+                program.programId,
+            );
+            await program.methods
+                .fundMerchantReserve(new BN(50000))
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    usdcMint: usdcMint,
+                    authorityUsdc: authorityUsdcAccount.address,
+                    merchantReserve: merchantReservePDA,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            const paymentId = `pay_dispute_${Date.now()}`;
+            const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                program.programId,
+            );
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+            await program.methods
+                .processPayment(paymentId, new BN(20000), null)
+                .accountsStrict({
+                    payer: user.publicKey,
+                    customer: user.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    paymentAccount: paymentAccountPDA,
+                    customerAccount: customerAccountPDA,
+                    usdcMint: usdcMint,
+                    customerUsdc: customerUsdcAccount.address,
+                    merchantUsdc: merchantUsdcAccount.address,
+                    platformTreasuryUsdc: platformTreasuryPDA,
+                    sponsorUsdc: null,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .signers([user])
+                .rpc();
+
+            const [disputePDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("dispute"), paymentAccountPDA.toBuffer()],
+                program.programId,
+            );
+            await program.methods
+                .openDispute(Array(32).fill(3))
+                .accountsStrict({
+                    customer: user.publicKey,
+                    platformConfig: platformConfigPDA,
+                    paymentAccount: paymentAccountPDA,
+                    merchantAccount: merchantAccountPDA,
+                    merchantReserve: merchantReservePDA,
+                    dispute: disputePDA,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .signers([user])
+                .rpc();
+
+            await program.methods
+                .respondToDispute(Array(32).fill(4))
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    merchantAccount: merchantAccountPDA,
+                    dispute: disputePDA,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            // Opening the dispute locked the merchant's share of a full refund
+            const opened = await program.account.dispute.fetch(disputePDA);
+            const { feeAmount } = await program.account.payment.fetch(paymentAccountPDA);
+            expect(opened.lockedAmount.toNumber()).to.equal(20000 - feeAmount.toNumber());
+            const merchant = await program.account.merchant.fetch(merchantAccountPDA);
+            expect(merchant.reserveLocked.toNumber()).to.equal(opened.lockedAmount.toNumber());
+
+            const withdraw = (amount: number) =>
+                program.methods
+                    .withdrawMerchantReserve(new BN(amount))
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        usdcMint: usdcMint,
+                        merchantReserve: merchantReservePDA,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            // Only the unlocked part of the reserve can be withdrawn meanwhile
+            const reserveBalance = Number((await getAccount(connection, merchantReservePDA)).amount);
+            const unlocked = reserveBalance - merchant.reserveLocked.toNumber();
+            try {
+                await withdraw(unlocked + 1);
+                expect.fail("Should have thrown ReserveLocked");
+            } catch (err: any) {
+                expect(err.toString()).to.include("ReserveLocked");
+            }
+            await withdraw(1000);
+
+            // The arbiter (the platform authority by default) rules a partial refund
+            const reserveBefore = (await getAccount(connection, merchantReservePDA)).amount;
+            const customerBefore = (await getAccount(connection, customerUsdcAccount.address)).amount;
+            await program.methods
+                .resolveDispute({ partialRefund: {} }, new BN(8000))
+                .accountsStrict({
+                    arbiter: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    paymentAccount: paymentAccountPDA,
+                    dispute: disputePDA,
+                    customer: user.publicKey,
+                    usdcMint: usdcMint,
+                    customerUsdc: customerUsdcAccount.address,
+                    merchantReserve: merchantReservePDA,
+                    platformTreasuryUsdc: platformTreasuryPDA,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            const customerAfter = (await getAccount(connection, customerUsdcAccount.address)).amount;
+            expect(Number(customerAfter - customerBefore)).to.equal(8000);
+
+            const dispute = await program.account.dispute.fetch(disputePDA);
+            expect(dispute.status).to.deep.equal({ resolved: {} });
+            expect(dispute.ruling).to.deep.equal({ partialRefund: {} });
+            expect(dispute.merchantEvidenceHash).to.deep.equal(Array(32).fill(4));
+            expect(dispute.merchantRespondedAt).to.not.be.null;
+
+            const payment = await program.account.payment.fetch(paymentAccountPDA);
+            expect(payment.refundedAmount.toNumber()).to.equal(8000);
+            expect(payment.disputeOpen).to.be.false;
+
+            // Merchant leg came out of the reserve; fee leg from the treasury
+            const reserveAfter = (await getAccount(connection, merchantReservePDA)).amount;
+            expect(Number(reserveBefore - reserveAfter)).to.equal(8000 - Math.floor((payment.feeAmount.toNumber() * 8000) / 20000));
+
+            // With the dispute closed the reserve unlocks
+            expect(dispute.unpaidAmount.toNumber()).to.equal(0);
+            expect((await program.account.merchant.fetch(merchantAccountPDA)).reserveLocked.toNumber()).to.equal(0);
+            await withdraw(1000);
+        });
+
+        it("should leave a reserve shortfall refundable until the merchant pays it", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 20000);
+            // The merchant authority pays the shortfall from the settlement wallet's ATA
+            await approve(
+                connection,
+                authority.payer,
+                merchantUsdcAccount.address,
+                authority.publicKey,
+                settlementWallet,
+                1_000_000,
+            );
+            const [merchantReservePDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("merchant_reserve"), merchantAccountPDA.toBuffer()],
+                program.programId,
+            );
+
+            const paymentId = `pay_shortfall_${Date.now()}`;
+            const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                program.programId,
+            );
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+            const [disputePDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("dispute"), paymentAccountPDA.toBuffer()],
+                program.programId,
+            );
+            await program.methods
+                .processPayment(paymentId, new BN(20000), null)
+                .accountsStrict({
+                    payer: user.publicKey,
+                    customer: user.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    paymentAccount: paymentAccountPDA,
+                    customerAccount: customerAccountPDA,
+                    usdcMint: usdcMint,
+                    customerUsdc: customerUsdcAccount.address,
+                    merchantUsdc: merchantUsdcAccount.address,
+                    platformTreasuryUsdc: platformTreasuryPDA,
+                    sponsorUsdc: null,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .signers([user])
+                .rpc();
+
+            // Empty the reserve so the ruling can't be covered
+            const merchant = await program.account.merchant.fetch(merchantAccountPDA);
+            const unlocked = Number((await getAccount(connection, merchantReservePDA)).amount) - merchant.reserveLocked.toNumber();
+            if (unlocked > 0) {
+                await program.methods
+                    .withdrawMerchantReserve(new BN(unlocked))
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        usdcMint: usdcMint,
+                        merchantReserve: merchantReservePDA,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            }
+
+            await program.methods
+                .openDispute(Array(32).fill(5))
+                .accountsStrict({
+                    customer: user.publicKey,
+                    platformConfig: platformConfigPDA,
+                    paymentAccount: paymentAccountPDA,
+                    merchantAccount: merchantAccountPDA,
+                    merchantReserve: merchantReservePDA,
+                    dispute: disputePDA,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .signers([user])
+                .rpc();
+            await program.methods
+                .resolveDispute({ fullRefund: {} }, new BN(0))
+                .accountsStrict({
+                    arbiter: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    paymentAccount: paymentAccountPDA,
+                    dispute: disputePDA,
+                    customer: user.publicKey,
+                    usdcMint: usdcMint,
+                    customerUsdc: customerUsdcAccount.address,
+                    merchantReserve: merchantReservePDA,
+                    platformTreasuryUsdc: platformTreasuryPDA,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            // Only the fee leg reached the customer; the merchant share stays owed and refundable
+            let payment = await program.account.payment.fetch(paymentAccountPDA);
+            const fee = payment.feeAmount.toNumber();
+            const dispute = await program.account.dispute.fetch(disputePDA);
+            expect(dispute.refundAmount.toNumber()).to.equal(20000);
+            expect(dispute.unpaidAmount.toNumber()).to.equal(20000 - fee);
+            expect(payment.refundedAmount.toNumber()).to.equal(fee);
+            expect(payment.disputeShortfall.toNumber()).to.equal(20000 - fee);
+            expect(payment.status).to.deep.equal({ partiallyRefunded: {} });
+            expect(Number((await getAccount(connection, customerUsdcAccount.address)).amount)).to.equal(fee);
+
+            // The merchant settles it through a regular refund, entirely from its own funds
+            await program.methods
+                .refundPayment(new BN(20000 - fee))
+                .accountsStrict({
+                    merchantAuthority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    paymentAccount: paymentAccountPDA,
+                    merchantAccount: merchantAccountPDA,
+                    customer: user.publicKey,
+                    usdcMint: usdcMint,
+                    customerUsdc: customerUsdcAccount.address,
+                    merchantUsdc: merchantUsdcAccount.address,
+                    platformTreasuryUsdc: platformTreasuryPDA,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            payment = await program.account.payment.fetch(paymentAccountPDA);
+            expect(payment.disputeShortfall.toNumber()).to.equal(0);
+            expect(payment.status).to.deep.equal({ refunded: {} });
+            expect(Number((await getAccount(connection, customerUsdcAccount.address)).amount)).to.equal(20000);
+        });
+    });
+
+    describe("Escrowed Payments", () => {
+        it("should hold funds until delivery is confirmed and return them on cancel", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+            const payIntoEscrow = async (paymentId: string) => {
+                const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                    program.programId,
+                );
+                const [escrowPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("escrow"), paymentAccountPDA.toBuffer()],
+                    program.programId,
+                );
+                await program.methods
+                    .processEscrowPayment(paymentId, new BN(20000))
+                    .accountsStrict({
+                        payer: user.publicKey,
+                        customer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        paymentAccount: paymentAccountPDA,
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        escrow: escrowPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+                return { paymentAccountPDA, escrowPDA };
+            };
+            const releaseAccounts = (signer: PublicKey, paymentAccountPDA: PublicKey, escrowPDA: PublicKey) => ({
+                signer,
+                platformConfig: platformConfigPDA,
+                merchantAccount: merchantAccountPDA,
+                paymentAccount: paymentAccountPDA,
+                customer: user.publicKey,
+                usdcMint: usdcMint,
+                escrow: escrowPDA,
+                merchantUsdc: merchantUsdcAccount.address,
+                platformTreasuryUsdc: platformTreasuryPDA,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                eventAuthority: eventAuthorityPDA,
+                program: program.programId,
+            });
+
+            // Funds sit in escrow; the merchant sees nothing yet
+            const merchantBefore = (await getAccount(connection, merchantUsdcAccount.address)).amount;
+            const delivered = await payIntoEscrow(`pay_escrow_confirm_${Date.now()}`);
+            expect(Number((await getAccount(connection, delivered.escrowPDA)).amount)).to.equal(20000);
+
+            let payment = await program.account.payment.fetch(delivered.paymentAccountPDA);
+            expect(payment.status).to.deep.equal({ escrowed: {} });
+
+            // Nobody but the customer can release before the timeout
+            try {
+                await program.methods
+                    .releaseEscrow()
+                    .accountsStrict(releaseAccounts(authority.publicKey, delivered.paymentAccountPDA, delivered.escrowPDA))
+                    .rpc();
+                expect.fail("Should have thrown EscrowTimeoutNotReached");
+            } catch (err: any) {
+                expect(err.toString()).to.include("EscrowTimeoutNotReached");
+            }
+
+            await program.methods
+                .confirmDelivery()
+                .accountsStrict(releaseAccounts(user.publicKey, delivered.paymentAccountPDA, delivered.escrowPDA))
+                .signers([user])
+                .rpc();
+
+            payment = await program.account.payment.fetch(delivered.paymentAccountPDA);
+            expect(payment.status).to.deep.equal({ completed: {} });
+            const merchantAfter = (await getAccount(connection, merchantUsdcAccount.address)).amount;
+            expect(Number(merchantAfter - merchantBefore)).to.equal(payment.merchantAmount.toNumber());
+            expect(await connection.getAccountInfo(delivered.escrowPDA)).to.be.null;
+
+            // Cancelling returns the full amount, fee included
+            const customerBefore = (await getAccount(connection, customerUsdcAccount.address)).amount;
+            const cancelled = await payIntoEscrow(`pay_escrow_cancel_${Date.now()}`);
+            await program.methods
+                .cancelEscrow()
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    paymentAccount: cancelled.paymentAccountPDA,
+                    customer: user.publicKey,
+                    usdcMint: usdcMint,
+                    customerUsdc: customerUsdcAccount.address,
+                    escrow: cancelled.escrowPDA,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            const customerAfter = (await getAccount(connection, customerUsdcAccount.address)).amount;
+            expect(customerAfter).to.equal(customerBefore);
+            payment = await program.account.payment.fetch(cancelled.paymentAccountPDA);
+            expect(payment.status).to.deep.equal({ cancelled: {} });
+        });
+    });
+
+    describe("Authorize and Capture", () => {
+        it("should capture part of a hold, void holds, and release expired ones to the customer", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+            const balance = async () => Number((await getAccount(connection, customerUsdcAccount.address)).amount);
+
+            const authorize = async (authorizationId: string, amount: number) => {
+                const [authorizationPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("authorization"), merchantAccountPDA.toBuffer(), idSeed(authorizationId)],
+                    program.programId,
+                );
+                const [holdPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("authorization_hold"), authorizationPDA.toBuffer()],
+                    program.programId,
+                );
+                await program.methods
+                    .authorizePayment(authorizationId, new BN(amount))
+                    .accountsStrict({
+                        customer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        authorization: authorizationPDA,
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        hold: holdPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+                return { authorizationId, authorizationPDA, holdPDA };
+            };
+            const releaseAccounts = (signer: PublicKey, hold: Awaited<ReturnType<typeof authorize>>) => ({
+                signer,
+                platformConfig: platformConfigPDA,
+                merchantAccount: merchantAccountPDA,
+                authorization: hold.authorizationPDA,
+                customer: user.publicKey,
+                usdcMint: usdcMint,
+                customerUsdc: customerUsdcAccount.address,
+                hold: hold.holdPDA,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                eventAuthority: eventAuthorityPDA,
+                program: program.programId,
+            });
+
+            // Authorize 30000, then capture 22000 after weighing the order
+            const captured = await authorize(`auth_capture_${Date.now()}`, 30000);
+            expect(await balance()).to.equal(70000);
+            const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment"), merchantAccountPDA.toBuffer(), derivedSeed(captured.authorizationPDA, 0)],
+                program.programId,
+            );
+
+            // Someone else taking the authorization ID as a regular payment ID doesn't block the capture
+            const squatter = Keypair.generate();
+            const squatterUsdc = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                squatter.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, squatterUsdc.address, authority.publicKey, 10000);
+            await program.methods
+                .processPayment(captured.authorizationId, new BN(10000), null)
+                .accountsStrict({
+                    payer: authority.publicKey,
+                    customer: squatter.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    paymentAccount: PublicKey.findProgramAddressSync(
+                        [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(captured.authorizationId)],
+                        program.programId,
+                    )[0],
+                    customerAccount: PublicKey.findProgramAddressSync(
+                        [Buffer.from("customer"), squatter.publicKey.toBuffer()],
+                        program.programId,
+                    )[0],
+                    usdcMint: usdcMint,
+                    customerUsdc: squatterUsdc.address,
+                    merchantUsdc: merchantUsdcAccount.address,
+                    platformTreasuryUsdc: platformTreasuryPDA,
+                    sponsorUsdc: null,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .signers([squatter])
+                .rpc();
+
+            const capture = (amount: number) =>
+                program.methods
+                    .capturePayment(new BN(amount))
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        authorization: captured.authorizationPDA,
+                        paymentAccount: paymentAccountPDA,
+                        customerAccount: customerAccountPDA,
+                        customer: user.publicKey,
+                        usdcMint: usdcMint,
+                        hold: captured.holdPDA,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+
+            try {
+                await capture(30001);
+                expect.fail("Should have thrown InvalidCaptureAmount");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvalidCaptureAmount");
+            }
+            await capture(22000);
+            expect(await balance()).to.equal(78000);
+            const payment = await program.account.payment.fetch(paymentAccountPDA);
+            expect(payment.amount.toNumber()).to.equal(22000);
+            expect(payment.parent.toBase58()).to.equal(captured.authorizationPDA.toBase58());
+            const authorization = await program.account.authorization.fetch(captured.authorizationPDA);
+            expect(authorization.status).to.deep.equal({ captured: {} });
+            expect(await connection.getAccountInfo(captured.holdPDA)).to.be.null;
+
+            // Only the merchant can void, and the customer gets everything back
+            const voided = await authorize(`auth_void_${Date.now()}`, 10000);
+            try {
+                await program.methods
+                    .voidAuthorization()
+                    .accountsStrict(releaseAccounts(user.publicKey, voided))
+                    .signers([user])
+                    .rpc();
+                expect.fail("Should have thrown Unauthorized");
+            } catch (err: any) {
+                expect(err.toString()).to.include("Unauthorized");
+            }
+            await program.methods
+                .voidAuthorization()
+                .accountsStrict(releaseAccounts(authority.publicKey, voided))
+                .rpc();
+            expect(await balance()).to.equal(78000);
+
+            // With a short TTL, anyone can release the hold once it expires
+            const setTtl = (ttl: number) =>
+                program.methods
+                    .updatePlatformConfig({
+                        feeBps: null,
+                        minPaymentAmount: null,
+                        isActive: null,
+                        settlementWalletDelay: null,
+                        refundWindow: null,
+                        arbiter: null,
+                        escrowTimeout: null,
+                        authorizationTtl: new BN(ttl),
+                        maxSponsorFee: null,
+                        disputeWindow: null,
+                    })
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            await setTtl(2);
+            const expiring = await authorize(`auth_expire_${Date.now()}`, 10000);
+            await setTtl(7 * 24 * 60 * 60);
+            try {
+                await program.methods
+                    .releaseExpiredAuthorization()
+                    .accountsStrict(releaseAccounts(authority.publicKey, expiring))
+                    .rpc();
+                expect.fail("Should have thrown AuthorizationNotExpired");
+            } catch (err: any) {
+                expect(err.toString()).to.include("AuthorizationNotExpired");
+            }
+            await new Promise((resolve) => setTimeout(resolve, 4000));
+
+            const stranger = Keypair.generate();
+            await program.methods
+                .releaseExpiredAuthorization()
+                .accountsStrict(releaseAccounts(stranger.publicKey, expiring))
+                .signers([stranger])
+                .rpc();
+            expect(await balance()).to.equal(78000);
+        });
+    });
+
+    describe("Attestation-Gated Escrow", () => {
+        it("should release on a passing attestation and refund on a failing one", async () => {
+            const user = Keypair.generate();
+            const lab = Keypair.generate();
+            for (const kp of [user, lab]) {
+                const sig = await connection.requestAirdrop(kp.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+                await connection.confirmTransaction(sig);
+            }
+
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+
+            await program.methods
+                .addAttester(lab.publicKey)
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+            const payAttested = async (paymentId: string) => {
+                const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                    program.programId,
+                );
+                const [escrowPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("escrow"), paymentAccountPDA.toBuffer()],
+                    program.programId,
+                );
+                const [attestationPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("attestation"), paymentAccountPDA.toBuffer()],
+                    program.programId,
+                );
+                await program.methods
+                    .processAttestedPayment(paymentId, new BN(20000))
+                    .accountsStrict({
+                        payer: user.publicKey,
+                        customer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        paymentAccount: paymentAccountPDA,
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        escrow: escrowPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+                return { paymentAccountPDA, escrowPDA, attestationPDA };
+            };
+            const attest = (attester: Keypair, ids: Awaited<ReturnType<typeof payAttested>>, passed: boolean) =>
+                program.methods
+                    .submitAttestation(Array(32).fill(5), passed)
+                    .accountsStrict({
+                        attester: attester.publicKey,
+                        attestation: ids.attestationPDA,
+                        release: {
+                            signer: attester.publicKey,
+                            platformConfig: platformConfigPDA,
+                            merchantAccount: merchantAccountPDA,
+                            paymentAccount: ids.paymentAccountPDA,
+                            customer: user.publicKey,
+                            usdcMint: usdcMint,
+                            escrow: ids.escrowPDA,
+                            merchantUsdc: merchantUsdcAccount.address,
+                            platformTreasuryUsdc: platformTreasuryPDA,
+                            tokenProgram: TOKEN_PROGRAM_ID,
+                            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                            eventAuthority: eventAuthorityPDA,
+                            program: program.programId,
+                        },
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([attester])
+                    .rpc();
+
+            // Passing COA: released straight to the merchant
+            const passing = await payAttested(`pay_coa_pass_${Date.now()}`);
+
+            // The customer can't bypass the lab by confirming delivery
+            try {
+                await program.methods
+                    .confirmDelivery()
+                    .accountsStrict({
+                        signer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        paymentAccount: passing.paymentAccountPDA,
+                        customer: user.publicKey,
+                        usdcMint: usdcMint,
+                        escrow: passing.escrowPDA,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+                expect.fail("Should have thrown AttestationRequired");
+            } catch (err: any) {
+                expect(err.toString()).to.include("AttestationRequired");
+            }
+
+            // Unapproved keys can't attest
+            const impostor = Keypair.generate();
+            const sig = await connection.requestAirdrop(impostor.publicKey, anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+            try {
+                await attest(impostor, passing, true);
+                expect.fail("Should have thrown AttesterNotApproved");
+            } catch (err: any) {
+                expect(err.toString()).to.include("AttesterNotApproved");
+            }
+
+            await attest(lab, passing, true);
+            let payment = await program.account.payment.fetch(passing.paymentAccountPDA);
+            expect(payment.status).to.deep.equal({ completed: {} });
+            const attestation = await program.account.attestation.fetch(passing.attestationPDA);
+            expect(attestation.passed).to.be.true;
+            expect(attestation.attester.toBase58()).to.equal(lab.publicKey.toBase58());
+
+            // Failing COA: the customer gets everything back
+            const refund = (ids: Awaited<ReturnType<typeof payAttested>>, attestation: PublicKey | null) =>
+                program.methods
+                    .refundFailedAttestation()
+                    .accountsStrict({
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        paymentAccount: ids.paymentAccountPDA,
+                        attestation,
+                        customer: user.publicKey,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        escrow: ids.escrowPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            const failing = await payAttested(`pay_coa_fail_${Date.now()}`);
+            await attest(lab, failing, false);
+            const customerBefore = (await getAccount(connection, customerUsdcAccount.address)).amount;
+            await refund(failing, failing.attestationPDA);
+            const customerAfter = (await getAccount(connection, customerUsdcAccount.address)).amount;
+            expect(Number(customerAfter - customerBefore)).to.equal(20000);
+            payment = await program.account.payment.fetch(failing.paymentAccountPDA);
+            expect(payment.status).to.deep.equal({ refunded: {} });
+
+            // No attestation at all: refundable by anyone once the deadline passes
+            const setEscrowTimeout = (escrowTimeout: number) =>
+                program.methods
+                    .updatePlatformConfig({
+                        feeBps: null,
+                        minPaymentAmount: null,
+                        isActive: null,
+                        settlementWalletDelay: null,
+                        refundWindow: null,
+                        arbiter: null,
+                        escrowTimeout: new BN(escrowTimeout),
+                        authorizationTtl: null,
+                        maxSponsorFee: null,
+                        disputeWindow: null,
+                    })
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            await setEscrowTimeout(2);
+            const unattested = await payAttested(`pay_coa_none_${Date.now()}`);
+            await setEscrowTimeout(14 * 24 * 60 * 60);
+            payment = await program.account.payment.fetch(unattested.paymentAccountPDA);
+            expect(payment.escrowReleaseAt).to.not.be.null;
+            try {
+                await refund(unattested, null);
+                expect.fail("Should have thrown AttestationDeadlineNotReached");
+            } catch (err: any) {
+                expect(err.toString()).to.include("AttestationDeadlineNotReached");
+            }
+
+            await new Promise((resolve) => setTimeout(resolve, 3000));
+            try {
+                await attest(lab, unattested, true);
+                expect.fail("Should have thrown AttestationDeadlinePassed");
+            } catch (err: any) {
+                expect(err.toString()).to.include("AttestationDeadlinePassed");
+            }
+            await refund(unattested, null);
+            payment = await program.account.payment.fetch(unattested.paymentAccountPDA);
+            expect(payment.status).to.deep.equal({ refunded: {} });
+
+            await program.methods
+                .removeAttester(lab.publicKey)
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+        });
+    });
+
+    describe("Purchase Orders", () => {
+        it("should fund milestones up front and release each as a payment", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+
+            const orderId = `po_${Date.now()}`;
+            const [purchaseOrderPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("purchase_order"), merchantAccountPDA.toBuffer(), idSeed(orderId)],
+                program.programId,
+            );
+            const [escrowPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("purchase_order_escrow"), purchaseOrderPDA.toBuffer()],
+                program.programId,
+            );
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+
+            const createOrder = (order: PublicKey, orderEscrow: PublicKey, id: string, milestones: any[], deliverBy: number) =>
+                program.methods
+                    .createPurchaseOrder(id, milestones, new BN(deliverBy))
+                    .accountsStrict({
+                        payer: user.publicKey,
+                        buyer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        purchaseOrder: order,
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        escrow: orderEscrow,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+
+            // Second milestone can be released by anyone as soon as it is due
+            const now = Math.floor(Date.now() / 1000);
+            await createOrder(purchaseOrderPDA, escrowPDA, orderId, [
+                { amount: new BN(30000), releaseAfter: null },
+                { amount: new BN(10000), releaseAfter: new BN(now - 60) },
+            ], now + 30 * 86400);
+            expect(Number((await getAccount(connection, escrowPDA)).amount)).to.equal(40000);
+
+            const release = (index: number, signer: PublicKey, order = purchaseOrderPDA, orderEscrow = escrowPDA) => {
+                // Addressed from the order, so `<order_id>-m<i>` can't be taken through process_payment
+                const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), derivedSeed(order, index)],
+                    program.programId,
+                );
+                return {
+                    paymentAccountPDA,
+                    builder: program.methods.releaseMilestone(index).accountsStrict({
+                        signer,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        purchaseOrder: order,
+                        paymentAccount: paymentAccountPDA,
+                        buyer: user.publicKey,
+                        usdcMint: usdcMint,
+                        escrow: orderEscrow,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    }),
+                };
+            };
+
+            // Milestone 0 has no release time, so only the buyer can release it
+            try {
+                await release(0, authority.publicKey).builder.rpc();
+                expect.fail("Should have thrown MilestoneNotDue");
+            } catch (err: any) {
+                expect(err.toString()).to.include("MilestoneNotDue");
+            }
+
+            const first = release(0, user.publicKey);
+            await first.builder.signers([user]).rpc();
+            const payment = await program.account.payment.fetch(first.paymentAccountPDA);
+            expect(payment.paymentId).to.equal(`${orderId}-m0`);
+            expect(payment.parent.toBase58()).to.equal(purchaseOrderPDA.toBase58());
+            expect(payment.amount.toNumber()).to.equal(30000);
+            expect(payment.status).to.deep.equal({ completed: {} });
+
+            let order = await program.account.purchaseOrder.fetch(purchaseOrderPDA);
+            expect(order.releasedAmount.toNumber()).to.equal(30000);
+            expect(order.milestones[0].status).to.deep.equal({ released: {} });
+            expect(order.status).to.deep.equal({ open: {} });
+
+            try {
+                await release(0, user.publicKey).builder.signers([user]).rpc();
+                expect.fail("Should not release a milestone twice");
+            } catch (err: any) {
+                // The milestone's payment account already exists
+                expect(err.toString()).to.not.include("Should not release");
+            }
+
+            // Milestone 1 is past its release time, so the merchant can release it
+            await release(1, authority.publicKey).builder.rpc();
+            order = await program.account.purchaseOrder.fetch(purchaseOrderPDA);
+            expect(order.releasedAmount.toNumber()).to.equal(40000);
+            expect(order.status).to.deep.equal({ completed: {} });
+            expect(await connection.getAccountInfo(escrowPDA)).to.be.null;
+
+            // Past the delivery deadline the buyer takes back what hasn't fallen due
+            const lateOrderId = `po_late_${Date.now()}`;
+            const [lateOrderPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("purchase_order"), merchantAccountPDA.toBuffer(), idSeed(lateOrderId)],
+                program.programId,
+            );
+            const [lateEscrowPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("purchase_order_escrow"), lateOrderPDA.toBuffer()],
+                program.programId,
+            );
+            await createOrder(lateOrderPDA, lateEscrowPDA, lateOrderId, [
+                { amount: new BN(10000), releaseAfter: null },
+                { amount: new BN(5000), releaseAfter: new BN(now - 60) },
+            ], Math.floor(Date.now() / 1000) + 2);
+
+            const cancel = () =>
+                program.methods
+                    .cancelPurchaseOrder()
+                    .accountsStrict({
+                        buyer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        purchaseOrder: lateOrderPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        escrow: lateEscrowPDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+            try {
+                await cancel();
+                expect.fail("Should have thrown DeliveryDeadlineNotReached");
+            } catch (err: any) {
+                expect(err.toString()).to.include("DeliveryDeadlineNotReached");
+            }
+
+            await new Promise((resolve) => setTimeout(resolve, 3000));
+            const buyerBefore = (await getAccount(connection, customerUsdcAccount.address)).amount;
+            await cancel();
+            const buyerAfter = (await getAccount(connection, customerUsdcAccount.address)).amount;
+            expect(Number(buyerAfter - buyerBefore)).to.equal(10000);
+
+            order = await program.account.purchaseOrder.fetch(lateOrderPDA);
+            expect(order.status).to.deep.equal({ cancelled: {} });
+            expect(order.cancelledAmount.toNumber()).to.equal(10000);
+            expect(order.milestones[0].status).to.deep.equal({ cancelled: {} });
+
+            // The milestone that was already due is still the merchant's
+            expect(order.milestones[1].status).to.deep.equal({ pending: {} });
+            await release(1, authority.publicKey, lateOrderPDA, lateEscrowPDA).builder.rpc();
+            expect(await connection.getAccountInfo(lateEscrowPDA)).to.be.null;
+        });
+    });
+
+    describe("Checkout Sessions", () => {
+        it("should stop accepting payments once used up or expired, then close for rent", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+
+            const sessionPDA = (sessionId: string) =>
+                PublicKey.findProgramAddressSync(
+                    [Buffer.from("checkout_session"), merchantAccountPDA.toBuffer(), idSeed(sessionId)],
+                    program.programId,
+                )[0];
+            const createSession = (sessionId: string, expiresAt: number, maxUses: number) =>
+                program.methods
+                    .createCheckoutSession(sessionId, new BN(25000), new BN(expiresAt), maxUses)
+                    .accountsStrict({
+                        creator: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        checkoutSession: sessionPDA(sessionId),
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            const paySession = (sessionId: string, paymentId: string) => {
+                const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                    program.programId,
+                );
+                return program.methods
+                    .payCheckoutSession(paymentId)
+                    .accountsStrict({
+                        payment: {
+                            payer: user.publicKey,
+                            customer: user.publicKey,
+                            platformConfig: platformConfigPDA,
+                            merchantAccount: merchantAccountPDA,
+                            paymentAccount: paymentAccountPDA,
+                            customerAccount: customerAccountPDA,
+                            usdcMint: usdcMint,
+                            customerUsdc: customerUsdcAccount.address,
+                            merchantUsdc: merchantUsdcAccount.address,
+                            platformTreasuryUsdc: platformTreasuryPDA,
+                            sponsorUsdc: null,
+                            tokenProgram: TOKEN_PROGRAM_ID,
+                            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                            systemProgram: SystemProgram.programId,
+                            eventAuthority: eventAuthorityPDA,
+                            program: program.programId,
+                        },
+                        checkoutSession: sessionPDA(sessionId),
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+            };
+            const closeSession = (sessionId: string) =>
+                program.methods
+                    .closeCheckoutSession()
+                    .accountsStrict({
+                        checkoutSession: sessionPDA(sessionId),
+                        creator: authority.publicKey,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+
+            // Single-use link
+            const singleId = `cs_single_${Date.now()}`;
+            await createSession(singleId, Math.floor(Date.now() / 1000) + 3600, 1);
+
+            // Still usable, so it can't be closed yet
+            try {
+                await closeSession(singleId);
+                expect.fail("Should have thrown CheckoutSessionActive");
+            } catch (err: any) {
+                expect(err.toString()).to.include("CheckoutSessionActive");
+            }
+
+            await paySession(singleId, `cs_pay_1_${Date.now()}`);
+            const session = await program.account.checkoutSession.fetch(sessionPDA(singleId));
+            expect(session.useCount).to.equal(1);
+
+            try {
+                await paySession(singleId, `cs_pay_2_${Date.now()}`);
+                expect.fail("Should have thrown CheckoutSessionUsedUp");
+            } catch (err: any) {
+                expect(err.toString()).to.include("CheckoutSessionUsedUp");
+            }
+            await closeSession(singleId);
+            expect(await connection.getAccountInfo(sessionPDA(singleId))).to.be.null;
+
+            // Short-lived link
+            const expiringId = `cs_expiring_${Date.now()}`;
+            await createSession(expiringId, Math.floor(Date.now() / 1000) + 2, 5);
+            await new Promise((resolve) => setTimeout(resolve, 4000));
+            try {
+                await paySession(expiringId, `cs_pay_3_${Date.now()}`);
+                expect.fail("Should have thrown CheckoutSessionExpired");
+            } catch (err: any) {
+                expect(err.toString()).to.include("CheckoutSessionExpired");
+            }
+            await closeSession(expiringId);
+        });
+    });
+
+    describe("Subscriptions", () => {
+        it("should charge due cycles through the delegate and stop after cancel or max cycles", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+            const [delegatePDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment_delegate")],
+                program.programId,
+            );
+
+            const subscriptionPDA = (subscriptionId: string) =>
+                PublicKey.findProgramAddressSync(
+                    [Buffer.from("subscription"), merchantAccountPDA.toBuffer(), idSeed(subscriptionId)],
+                    program.programId,
+                )[0];
+            const subscribe = (subscriptionId: string, interval: number, maxCycles: number) =>
+                program.methods
+                    .createSubscription(subscriptionId, new BN(15000), new BN(interval), maxCycles)
+                    .accountsStrict({
+                        payer: user.publicKey,
+                        customer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        subscription: subscriptionPDA(subscriptionId),
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        delegate: delegatePDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+            // The crank is run by an unrelated wallet
+            const charge = (subscriptionId: string, cycle: number) => {
+                const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), derivedSeed(subscriptionPDA(subscriptionId), cycle)],
+                    program.programId,
+                );
+                return program.methods
+                    .chargeSubscription()
+                    .accountsStrict({
+                        cranker: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        subscription: subscriptionPDA(subscriptionId),
+                        paymentAccount: paymentAccountPDA,
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        delegate: delegatePDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+            };
+
+            const subscriptionId = `sub_${Date.now()}`;
+            await subscribe(subscriptionId, 2, 2);
+            let account = await getAccount(connection, customerUsdcAccount.address);
+            expect(account.delegate.toBase58()).to.equal(delegatePDA.toBase58());
+            expect(Number(account.delegatedAmount)).to.equal(30000);
+
+            // First cycle is due immediately
+            await charge(subscriptionId, 0);
+            account = await getAccount(connection, customerUsdcAccount.address);
+            expect(Number(account.amount)).to.equal(85000);
+
+            try {
+                await charge(subscriptionId, 1);
+                expect.fail("Should have thrown SubscriptionNotDue");
+            } catch (err: any) {
+                expect(err.toString()).to.include("SubscriptionNotDue");
+            }
+
+            await new Promise((resolve) => setTimeout(resolve, 3000));
+            await charge(subscriptionId, 1);
+            let subscription = await program.account.subscription.fetch(subscriptionPDA(subscriptionId));
+            expect(subscription.cyclesCharged).to.equal(2);
+            expect(subscription.status).to.deep.equal({ completed: {} });
+
+            // A second subscription adds to the remaining allowance; the merchant cancels it
+            const cancelledId = `sub_cancelled_${Date.now()}`;
+            await subscribe(cancelledId, 3600, 3);
+            account = await getAccount(connection, customerUsdcAccount.address);
+            expect(Number(account.delegatedAmount)).to.equal(45000);
+
+            await program.methods
+                .cancelSubscription()
+                .accountsStrict({
+                    signer: authority.publicKey,
+                    merchantAccount: merchantAccountPDA,
+                    subscription: subscriptionPDA(cancelledId),
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+            try {
+                await charge(cancelledId, 0);
+                expect.fail("Should have thrown SubscriptionNotActive");
+            } catch (err: any) {
+                expect(err.toString()).to.include("SubscriptionNotActive");
+            }
+        });
+    });
+
+    describe("Invoices", () => {
+        const invoicePDA = (invoiceId: string) =>
+            PublicKey.findProgramAddressSync(
+                [Buffer.from("invoice"), merchantAccountPDA.toBuffer(), idSeed(invoiceId)],
+                program.programId,
+            )[0];
+        const payInvoice = async (payer: Keypair, invoiceId: string) => {
+            const customerUsdc = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                payer.publicKey,
+            );
+            const merchantUsdc = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdc.address, authority.publicKey, 50000);
+            const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment"), merchantAccountPDA.toBuffer(), derivedSeed(invoicePDA(invoiceId), 0)],
+                program.programId,
+            );
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), payer.publicKey.toBuffer()],
+                program.programId,
+            );
+            await program.methods
+                .payInvoice(invoiceId)
+                .accountsStrict({
+                    payer: payer.publicKey,
+                    customer: payer.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    invoice: invoicePDA(invoiceId),
+                    paymentAccount: paymentAccountPDA,
+                    customerAccount: customerAccountPDA,
+                    usdcMint: usdcMint,
+                    customerUsdc: customerUsdc.address,
+                    merchantUsdc: merchantUsdc.address,
+                    platformTreasuryUsdc: platformTreasuryPDA,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .signers([payer])
+                .rpc();
+            return paymentAccountPDA;
+        };
+
+        it("should pay an invoice only as the designated payer, and void/reissue open ones", async () => {
+            const user = Keypair.generate();
+            const stranger = Keypair.generate();
+            for (const kp of [user, stranger]) {
+                const sig = await connection.requestAirdrop(kp.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+                await connection.confirmTransaction(sig);
+            }
+
+            const dueDate = new BN(Math.floor(Date.now() / 1000) + 7 * 24 * 60 * 60);
+            const invoiceId = `inv_${Date.now()}`;
+            await program.methods
+                .createInvoice(invoiceId, {
+                    amount: new BN(30000),
+                    payer: user.publicKey,
+                    dueDate,
+                    memo: "Consulting, March",
+                    terms: null,
+                })
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    invoice: invoicePDA(invoiceId),
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            // Only the designated payer can settle it
+            try {
+                await payInvoice(stranger, invoiceId);
+                expect.fail("Should have thrown InvoicePayerMismatch");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvoicePayerMismatch");
+            }
+
+            const paymentAccountPDA = await payInvoice(user, invoiceId);
+            let invoice = await program.account.invoice.fetch(invoicePDA(invoiceId));
+            expect(invoice.status).to.deep.equal({ paid: {} });
+            expect(invoice.payment.toBase58()).to.equal(paymentAccountPDA.toBase58());
+            const payment = await program.account.payment.fetch(paymentAccountPDA);
+            expect(payment.amount.toNumber()).to.equal(30000);
+            // Addressed from the invoice, so `process_payment` with the same ID can't pre-empt it
+            expect(payment.paymentId).to.equal(invoiceId);
+            expect(payment.parent.toBase58()).to.equal(invoicePDA(invoiceId).toBase58());
+
+            // A paid invoice can't be paid twice or voided
+            try {
+                await program.methods
+                    .voidInvoice()
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        merchantAccount: merchantAccountPDA,
+                        invoice: invoicePDA(invoiceId),
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+                expect.fail("Should have thrown InvoiceNotOpen");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvoiceNotOpen");
+            }
+
+            // Reissue an open invoice with a corrected amount and no payer restriction
+            const draftId = `inv_draft_${Date.now()}`;
+            const finalId = `inv_final_${Date.now()}`;
+            await program.methods
+                .createInvoice(draftId, { amount: new BN(45000), payer: null, dueDate, memo: "", terms: null })
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    invoice: invoicePDA(draftId),
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+            await program.methods
+                .reissueInvoice(finalId, { amount: new BN(40000), payer: null, dueDate, memo: "Corrected total", terms: null })
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    invoice: invoicePDA(draftId),
+                    newInvoice: invoicePDA(finalId),
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            const draft = await program.account.invoice.fetch(invoicePDA(draftId));
+            expect(draft.status).to.deep.equal({ voided: {} });
+            expect(draft.reissuedAs.toBase58()).to.equal(invoicePDA(finalId).toBase58());
+            invoice = await program.account.invoice.fetch(invoicePDA(finalId));
+            expect(invoice.reissuedFrom.toBase58()).to.equal(invoicePDA(draftId).toBase58());
+
+            // Voided invoices are closed to payment; the replacement is open to anyone
+            try {
+                await payInvoice(stranger, draftId);
+                expect.fail("Should have thrown InvoiceNotOpen");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvoiceNotOpen");
+            }
+            await payInvoice(stranger, finalId);
+        });
+
+        it("should apply early-payment discounts and late fees and record the breakdown", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+
+            const now = Math.floor(Date.now() / 1000);
+            const create = (invoiceId: string, dueDate: number, terms: any) =>
+                program.methods
+                    .createInvoice(invoiceId, { amount: new BN(40000), payer: null, dueDate: new BN(dueDate), memo: "", terms })
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        invoice: invoicePDA(invoiceId),
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+
+            // A discount window past the due date is rejected
+            try {
+                await create(`inv_bad_terms_${Date.now()}`, now + 3600, {
+                    discountBps: 200,
+                    discountDeadline: new BN(now + 7200),
+                    lateFee: null,
+                });
+                expect.fail("Should have thrown InvalidInvoiceTerms");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvalidInvoiceTerms");
+            }
+
+            // So is a discount that would take the amount below the platform minimum
+            try {
+                await create(`inv_deep_discount_${Date.now()}`, now + 3600, {
+                    discountBps: 7600,
+                    discountDeadline: new BN(now + 1800),
+                    lateFee: null,
+                });
+                expect.fail("Should have thrown PaymentBelowMinimum");
+            } catch (err: any) {
+                expect(err.toString()).to.include("PaymentBelowMinimum");
+            }
+
+            // "2/10 net 30": paid inside the discount window
+            const earlyId = `inv_early_${Date.now()}`;
+            await create(earlyId, now + 30 * 86400, {
+                discountBps: 200,
+                discountDeadline: new BN(now + 10 * 86400),
+                lateFee: { bps: [150] },
+            });
+            let payment = await program.account.payment.fetch(await payInvoice(user, earlyId));
+            expect(payment.amount.toNumber()).to.equal(39200);
+            expect(payment.invoiceBreakdown.baseAmount.toNumber()).to.equal(40000);
+            expect(payment.invoiceBreakdown.discountAmount.toNumber()).to.equal(800);
+            expect(payment.invoiceBreakdown.lateFeeAmount.toNumber()).to.equal(0);
+
+            // Paid after the due date: flat late fee, no discount
+            const lateId = `inv_late_${Date.now()}`;
+            await create(lateId, now + 2, {
+                discountBps: 0,
+                discountDeadline: new BN(0),
+                lateFee: { flat: [new BN(2500)] },
+            });
+            await new Promise((resolve) => setTimeout(resolve, 4000));
+            payment = await program.account.payment.fetch(await payInvoice(user, lateId));
+            expect(payment.amount.toNumber()).to.equal(42500);
+            expect(payment.invoiceBreakdown.lateFeeAmount.toNumber()).to.equal(2500);
+
+            // Without a late fee an overdue invoice can't be paid
+            const overdueId = `inv_overdue_${Date.now()}`;
+            await create(overdueId, Math.floor(Date.now() / 1000) + 2, null);
+            await new Promise((resolve) => setTimeout(resolve, 4000));
+            try {
+                await payInvoice(user, overdueId);
+                expect.fail("Should have thrown InvoiceOverdue");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvoiceOverdue");
+            }
+        });
+
+        it("should charge the invoiced amount exactly even for additive merchants", async () => {
+            const user = Keypair.generate();
+            const sig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+            await connection.confirmTransaction(sig);
+            const setFeeMode = (feeMode: any) =>
+                program.methods
+                    .updateMerchant({ settlementWallet: null, authority: null, isActive: null, feeMode })
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+
+            const invoiceId = `inv_additive_${Date.now()}`;
+            const dueDate = new BN(Math.floor(Date.now() / 1000) + 24 * 60 * 60);
+            await program.methods
+                .createInvoice(invoiceId, { amount: new BN(30000), payer: null, dueDate, memo: "", terms: null })
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    invoice: invoicePDA(invoiceId),
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            await setFeeMode({ additive: {} });
+            let paymentAccountPDA: PublicKey;
+            try {
+                paymentAccountPDA = await payInvoice(user, invoiceId);
+            } finally {
+                // Other tests assume the merchant absorbs the fee
+                await setFeeMode({ inclusive: {} });
+            }
+
+            // The fee comes out of the invoiced amount rather than on top of it
+            const payment = await program.account.payment.fetch(paymentAccountPDA);
+            expect(payment.feeMode).to.deep.equal({ inclusive: {} });
+            expect(payment.amount.toNumber()).to.equal(30000);
+            expect(payment.merchantAmount.toNumber()).to.equal(30000 - payment.feeAmount.toNumber());
+        });
+    });
+
+    describe("Gasless Payments", () => {
+        it("should let a relayer pay rent while the customer only signs for USDC", async () => {
+            // The customer holds USDC but no SOL
+            const user = Keypair.generate();
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 50000);
+
+            const paymentId = `pay_gasless_${Date.now()}`;
+            const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                program.programId,
+            );
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+
+            await program.methods
+                .processPayment(paymentId, new BN(20000), null)
+                .accountsStrict({
+                    payer: authority.publicKey,
+                    customer: user.publicKey,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: merchantAccountPDA,
+                    paymentAccount: paymentAccountPDA,
+                    customerAccount: customerAccountPDA,
+                    usdcMint: usdcMint,
+                    customerUsdc: customerUsdcAccount.address,
+                    merchantUsdc: merchantUsdcAccount.address,
+                    platformTreasuryUsdc: platformTreasuryPDA,
+                    sponsorUsdc: null,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .signers([user])
+                .rpc();
+
+            const payment = await program.account.payment.fetch(paymentAccountPDA);
+            expect(payment.customer.toBase58()).to.equal(user.publicKey.toBase58());
+            const customer = await program.account.customer.fetch(customerAccountPDA);
+            expect(customer.customer.toBase58()).to.equal(user.publicKey.toBase58());
+            expect(await connection.getBalance(user.publicKey)).to.equal(0);
+        });
+
+        it("should reimburse a registered sponsor out of the customer's transfer", async () => {
+            const user = Keypair.generate();
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            // The relayer sponsoring the transaction is paid back in USDC
+            const sponsorUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                authority.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 50000);
+
+            await program.methods
+                .addSponsor(authority.publicKey)
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+            await program.methods
+                .updatePlatformConfig({
+                    feeBps: null,
+                    minPaymentAmount: null,
+                    isActive: null,
+                    settlementWalletDelay: null,
+                    refundWindow: null,
+                    arbiter: null,
+                    escrowTimeout: null,
+                    authorizationTtl: null,
+                    maxSponsorFee: new BN(2000),
+                    disputeWindow: null,
+                })
+                .accountsStrict({
+                    authority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+            const pay = (paymentId: string, sponsorFee: number) => {
+                const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                    program.programId,
+                );
+                return program.methods
+                    .processPayment(paymentId, new BN(20000), new BN(sponsorFee))
+                    .accountsStrict({
+                        payer: authority.publicKey,
+                        customer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        paymentAccount: paymentAccountPDA,
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        sponsorUsdc: sponsorUsdcAccount.address,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc()
+                    .then(() => paymentAccountPDA);
+            };
+
+            try {
+                await pay(`pay_sponsor_cap_${Date.now()}`, 2001);
+                expect.fail("Should have thrown SponsorFeeTooHigh");
+            } catch (err: any) {
+                expect(err.toString()).to.include("SponsorFeeTooHigh");
+            }
+
+            const sponsorBefore = (await getAccount(connection, sponsorUsdcAccount.address)).amount;
+            const payment = await program.account.payment.fetch(await pay(`pay_sponsor_${Date.now()}`, 1500));
+            const sponsorAfter = (await getAccount(connection, sponsorUsdcAccount.address)).amount;
+
+            // Fee and merchant share are computed on what is left after the sponsor fee
+            expect(Number(sponsorAfter - sponsorBefore)).to.equal(1500);
+            expect(payment.sponsorFee.toNumber()).to.equal(1500);
+            expect(payment.sponsor.toBase58()).to.equal(authority.publicKey.toBase58());
+            expect(payment.amount.toNumber()).to.equal(18500);
+            expect(payment.feeAmount.toNumber() + payment.merchantAmount.toNumber()).to.equal(18500);
+            expect(Number((await getAccount(connection, customerUsdcAccount.address)).amount)).to.equal(30000);
+        });
+    });
+
+    describe("Fee-on-top Mode", () => {
+        it("should charge the fee on top for additive merchants and refund both legs", async () => {
+            const setFeeMode = (feeMode: any) =>
+                program.methods
+                    .updateMerchant({ settlementWallet: null, authority: null, isActive: null, feeMode })
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+
+            const user = Keypair.generate();
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+            // The merchant authority pulls the merchant leg back from the settlement wallet's ATA
+            await approve(
+                connection,
+                authority.payer,
+                merchantUsdcAccount.address,
+                authority.publicKey,
+                settlementWallet,
+                1_000_000,
+            );
+
+            const paymentId = `pay_additive_${Date.now()}`;
+            const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                program.programId,
+            );
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+
+            await setFeeMode({ additive: {} });
+            const merchantBefore = (await getAccount(connection, merchantUsdcAccount.address)).amount;
+            try {
+                await program.methods
+                    .processPayment(paymentId, new BN(50000), null)
+                    .accountsStrict({
+                        payer: authority.publicKey,
+                        customer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        paymentAccount: paymentAccountPDA,
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        sponsorUsdc: null,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+            } finally {
+                // Other tests assume the merchant absorbs the fee
+                await setFeeMode({ inclusive: {} });
+            }
+
+            // The merchant keeps its full price; the customer paid the fee on top
+            let payment = await program.account.payment.fetch(paymentAccountPDA);
+            const fee = payment.feeAmount.toNumber();
+            expect(fee).to.be.greaterThan(0);
+            expect(payment.feeMode).to.deep.equal({ additive: {} });
+            expect(payment.merchantAmount.toNumber()).to.equal(50000);
+            expect(payment.amount.toNumber()).to.equal(50000 + fee);
+            const merchantAfter = (await getAccount(connection, merchantUsdcAccount.address)).amount;
+            expect(Number(merchantAfter - merchantBefore)).to.equal(50000);
+            expect(Number((await getAccount(connection, customerUsdcAccount.address)).amount)).to.equal(50000 - fee);
+
+            // A full refund returns the price from the merchant and the surcharge from the treasury
+            await program.methods
+                .refundPayment(new BN(50000 + fee))
+                .accountsStrict({
+                    merchantAuthority: authority.publicKey,
+                    platformConfig: platformConfigPDA,
+                    paymentAccount: paymentAccountPDA,
+                    merchantAccount: merchantAccountPDA,
+                    customer: user.publicKey,
+                    usdcMint: usdcMint,
+                    customerUsdc: customerUsdcAccount.address,
+                    merchantUsdc: merchantUsdcAccount.address,
+                    platformTreasuryUsdc: platformTreasuryPDA,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            payment = await program.account.payment.fetch(paymentAccountPDA);
+            expect(payment.status).to.deep.equal({ refunded: {} });
+            expect(Number((await getAccount(connection, customerUsdcAccount.address)).amount)).to.equal(100000);
+            expect(Number((await getAccount(connection, merchantUsdcAccount.address)).amount)).to.equal(Number(merchantBefore));
+        });
+    });
+
+    describe("Signed Payment Intents", () => {
+        it("should execute a customer-signed intent submitted by a relayer, once", async () => {
+            // The customer signs off-chain and never pays SOL; the relayer submits
+            const user = Keypair.generate();
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+            await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+
+            const [delegatePDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment_delegate")],
+                program.programId,
+            );
+            // Approvals through the program add up rather than replace each other
+            const approveDelegate = (amount: number) =>
+                program.methods
+                    .approveDelegate(new BN(amount))
+                    .accountsStrict({
+                        customer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        delegate: delegatePDA,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+            await approveDelegate(60000);
+            await approveDelegate(40000);
+            const approved = await getAccount(connection, customerUsdcAccount.address);
+            expect(approved.delegate.toBase58()).to.equal(delegatePDA.toBase58());
+            expect(Number(approved.delegatedAmount)).to.equal(100000);
+
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), user.publicKey.toBuffer()],
+                program.programId,
+            );
+            const intentFor = (paymentId: string, nonce: number, expiresIn = 300) => ({
+                merchant: merchantAccountPDA,
+                amount: new BN(20000),
+                paymentId,
+                expiry: new BN(Math.floor(Date.now() / 1000) + expiresIn),
+                nonce: new BN(nonce),
+            });
+            // `signed` lets a test submit different terms than the customer signed
+            const submit = (intent: ReturnType<typeof intentFor>, signed = intent) => {
+                const message = Buffer.concat([
+                    Buffer.from("settlr:payment-intent:v1"),
+                    program.coder.types.encode("paymentIntent", signed),
+                ]);
+                const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(intent.paymentId)],
+                    program.programId,
+                );
+                return program.methods
+                    .processSignedPayment(intent)
+                    .accountsStrict({
+                        payer: authority.publicKey,
+                        customer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        merchantAccount: merchantAccountPDA,
+                        paymentAccount: paymentAccountPDA,
+                        customerAccount: customerAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        delegate: delegatePDA,
+                        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .preInstructions([
+                        Ed25519Program.createInstructionWithPrivateKey({ privateKey: user.secretKey, message }),
+                    ])
+                    .rpc()
+                    .then(() => paymentAccountPDA);
+            };
+
+            const intent = intentFor(`pay_intent_${Date.now()}`, 0);
+            try {
+                await submit({ ...intent, amount: new BN(90000) }, intent);
+                expect.fail("Should have thrown InvalidIntentSignature");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvalidIntentSignature");
+            }
+
+            const payment = await program.account.payment.fetch(await submit(intent));
+            expect(payment.customer.toBase58()).to.equal(user.publicKey.toBase58());
+            expect(payment.amount.toNumber()).to.equal(20000);
+            const customer = await program.account.customer.fetch(customerAccountPDA);
+            expect(customer.nextIntentNonce.toNumber()).to.equal(1);
+            expect(await connection.getBalance(user.publicKey)).to.equal(0);
+
+            // A second intent reusing nonce 0 is a replay
+            try {
+                await submit(intentFor(`pay_intent_replay_${Date.now()}`, 0));
+                expect.fail("Should have thrown InvalidIntentNonce");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvalidIntentNonce");
+            }
+
+            try {
+                await submit(intentFor(`pay_intent_expired_${Date.now()}`, 1, -60));
+                expect.fail("Should have thrown IntentExpired");
+            } catch (err: any) {
+                expect(err.toString()).to.include("IntentExpired");
+            }
+        });
+    });
+
+    describe("Layout migrations", () => {
+        // Loaded by Anchor.toml from tests/fixtures/legacy-customer.json: a customer
+        // account written before `next_intent_nonce` was appended (65 bytes)
+        const LEGACY_CUSTOMER = new PublicKey("Gpcehq57yVesgRZq85qTMJ8n2MdEVDTsrciEwRQ4Szgi");
+
+        it("Should grow a pre-upgrade customer account in place", async () => {
+            const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("customer"), LEGACY_CUSTOMER.toBuffer()],
+                program.programId,
+            );
+            expect((await connection.getAccountInfo(customerAccountPDA))!.data.length).to.equal(65);
+
+            const migrate = () => program.methods
+                .migrateCustomer()
+                .accountsStrict({
+                    payer: authority.publicKey,
+                    customer: LEGACY_CUSTOMER,
+                    customerAccount: customerAccountPDA,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+            await migrate();
+
+            const customer = await program.account.customer.fetch(customerAccountPDA);
+            expect(customer.customer.toBase58()).to.equal(LEGACY_CUSTOMER.toBase58());
+            expect(customer.totalSpent.toNumber()).to.equal(25_000_000);
+            expect(customer.transactionCount.toNumber()).to.equal(3);
+            expect(customer.nextIntentNonce.toNumber()).to.equal(0);
+
+            try {
+                await migrate();
+                expect.fail("Should have thrown InvalidLegacyAccount");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvalidLegacyAccount");
+            }
+        });
+
+        it("Should migrate a pre-upgrade merchant and then its payment", async () => {
+            // tests/fixtures/legacy-merchant.json and legacy-payment.json: merchant
+            // "legacy_merchant" at its raw-ID address, and a 0.1 USDC payment
+            // "legacy_pay_0001" from LEGACY_CUSTOMER made on 2025-01-01
+            const LEGACY_MERCHANT_ID = "legacy_merchant";
+            const LEGACY_PAYMENT_ID = "legacy_pay_0001";
+            const LEGACY_AUTHORITY = new PublicKey("EMUcwNmy7WmatqzpycoKZi8vbeCEiyrBr5tSjnqJf1gt");
+            const [legacyMerchantPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("merchant"), Buffer.from(LEGACY_MERCHANT_ID)],
+                program.programId,
+            );
+            const [legacyPaymentPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment"), Buffer.from(LEGACY_PAYMENT_ID)],
+                program.programId,
+            );
+            const [newMerchantPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("merchant"), idSeed(LEGACY_MERCHANT_ID)],
+                program.programId,
+            );
+            const [newPaymentPDA] = PublicKey.findProgramAddressSync(
+                [Buffer.from("payment"), newMerchantPDA.toBuffer(), idSeed(LEGACY_PAYMENT_ID)],
+                program.programId,
+            );
+
+            await program.methods
+                .migrateMerchant(LEGACY_MERCHANT_ID)
+                .accountsStrict({
+                    payer: authority.publicKey,
+                    legacyMerchant: legacyMerchantPDA,
+                    merchantAccount: newMerchantPDA,
+                    authority: LEGACY_AUTHORITY,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            const merchant = await program.account.merchant.fetch(newMerchantPDA);
+            expect(merchant.merchantId).to.equal(LEGACY_MERCHANT_ID);
+            expect(merchant.authority.toBase58()).to.equal(LEGACY_AUTHORITY.toBase58());
+            expect(merchant.volume.toNumber()).to.equal(97500);
+            expect(merchant.fee).to.equal(null);
+            expect(await connection.getAccountInfo(legacyMerchantPDA)).to.equal(null);
+
+            await program.methods
+                .migratePayment(LEGACY_PAYMENT_ID)
+                .accountsStrict({
+                    payer: authority.publicKey,
+                    legacyPayment: legacyPaymentPDA,
+                    platformConfig: platformConfigPDA,
+                    merchantAccount: newMerchantPDA,
+                    paymentAccount: newPaymentPDA,
+                    customer: LEGACY_CUSTOMER,
+                    systemProgram: SystemProgram.programId,
+                    eventAuthority: eventAuthorityPDA,
+                    program: program.programId,
+                })
+                .rpc();
+
+            const payment = await program.account.payment.fetch(newPaymentPDA);
+            expect(payment.merchant.toBase58()).to.equal(newMerchantPDA.toBase58());
+            expect(payment.customer.toBase58()).to.equal(LEGACY_CUSTOMER.toBase58());
+            expect(payment.amount.toNumber()).to.equal(100000);
+            expect(payment.feeAmount.toNumber()).to.equal(2500);
+            expect(payment.feeBps.toNumber()).to.equal(250);
+            // Its refund window closed long ago, so it lands finalized
+            expect(payment.status).to.deep.equal({ finalized: {} });
+            expect(await connection.getAccountInfo(legacyPaymentPDA)).to.equal(null);
+        });
+
+        it("Should reject migrating a platform config already in the current layout", async () => {
+            try {
+                await program.methods
+                    .migratePlatformConfig(new BN(0))
+                    .accountsStrict({
+                        authority: authority.publicKey,
+                        platformConfig: platformConfigPDA,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .rpc();
+                expect.fail("Should have thrown InvalidLegacyAccount");
+            } catch (err: any) {
+                expect(err.toString()).to.include("InvalidLegacyAccount");
+            }
+        });
+    });
+
+    describe("Sequential Payments", () => {
+        it("should handle multiple payments in sequence", async () => {
+            const user = Keypair.generate();
+            await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+
+            const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                user.publicKey,
+            );
+            const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+                connection,
+                authority.payer,
+                usdcMint,
+                settlementWallet.publicKey,
+            );
+
+            await mintTo(
+                connection,
+                authority.payer,
+                usdcMint,
+                customerUsdcAccount.address,
+                authority.publicKey,
+                1000000, // 1 USDC
+            );
+
+            for (let i = 0; i < 3; i++) {
+                const paymentId = `pay_seq_${Date.now()}_${i}`;
+                const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+                    program.programId,
+                );
+                const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+                    [Buffer.from("customer"), user.publicKey.toBuffer()],
+                    program.programId,
+                );
+
+                await program.methods
+                    .processPayment(paymentId, new BN(20000), null)
+                    .accountsStrict({
+                        payer: user.publicKey,
+                        customer: user.publicKey,
+                        platformConfig: platformConfigPDA,
+                        paymentAccount: paymentAccountPDA,
+                        customerAccount: customerAccountPDA,
+                        merchantAccount: merchantAccountPDA,
+                        usdcMint: usdcMint,
+                        customerUsdc: customerUsdcAccount.address,
+                        merchantUsdc: merchantUsdcAccount.address,
+                        platformTreasuryUsdc: platformTreasuryPDA,
+                        sponsorUsdc: null,
+                        tokenProgram: TOKEN_PROGRAM_ID,
+                        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+            }
+
+            // If we get here, all 3 payments succeeded
+            expect(true).to.be.true;
+        });
+    });
+
+    describe("Privacy - Private Receipt Edge Cases (MagicBlock PER)", () => {
+        const DELEGATION_PROGRAM_ID = new PublicKey('DELeGGvXpWV2fqJUhqcF5ZSYMS4JTLjteaAMARRSaeSh');
+
+        // Helper to derive private receipt PDA
+        function findPrivateReceiptPda(paymentId: string, merchant = settlementWallet.publicKey): [PublicKey, number] {
+            return PublicKey.findProgramAddressSync(
+                [Buffer.from('private_receipt'), merchant.toBuffer(), idSeed(paymentId)],
+                program.programId
+            );
+        }
+
+        it("should fail to issue private receipt with empty payment ID", async () => {
+            const user = Keypair.generate();
+            await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+
+            const emptyPaymentId = "";
+            const amount = new anchor.BN(100000);
+            const feeAmount = new anchor.BN(1000);
+
+            try {
+                const [privateReceiptPDA] = findPrivateReceiptPda(emptyPaymentId);
+
+                await program.methods
+                    .issuePrivateReceipt(emptyPaymentId, amount, feeAmount, "test")
+                    .accountsStrict({
+                        payer: user.publicKey,
+                        customer: user.publicKey,
+                        merchant: settlementWallet.publicKey,
+                        privateReceipt: privateReceiptPDA,
+                        systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
+                    })
+                    .signers([user])
+                    .rpc();
+                expect.fail("Should have thrown InvalidPaymentId");
+            } catch (err: any) {
+                // Should fail with InvalidPaymentId or validation error
+                expect(err.toString()).to.satisfy((msg: string) =>
+                    msg.includes("InvalidPaymentId") || msg.includes("Error")
+                );
+            }
+        });
+
+        it("should create private receipt and verify session status", async () => {
+            const user = Keypair.generate();
+            await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+
+            const paymentId = `pay_per_${Date.now()}`;
+            const amount = new anchor.BN(5_000_000); // 5 USDC
+            const feeAmount = new anchor.BN(50_000);  // 0.05 USDC
+            const [privateReceiptPDA] = findPrivateReceiptPda(paymentId);
+
+            try {
+                await program.methods
+                    .issuePrivateReceipt(paymentId, amount, feeAmount, "PER edge case test")
+                    .accountsStrict({
+                        payer: user.publicKey,
                         customer: user.publicKey,
                         merchant: settlementWallet.publicKey,
                         privateReceipt: privateReceiptPDA,
                         systemProgram: SystemProgram.programId,
+                        eventAuthority: eventAuthorityPDA,
+                        program: program.programId,
                     })
                     .signers([user])
                     .rpc();
@@ -20,6 +20,7 @@ import {
     LAMPORTS_PER_SOL,
 } from '@solana/web3.js';
 import * as fs from 'fs';
+import { createHash } from 'crypto';
 
 import type { X402HackPayment } from '../target/types/x402_hack_payment';
 
@@ -34,9 +35,12 @@ const TEE_VALIDATOR = new PublicKey('FnE6VJT5QNZdedZPnCoLsARgBwoE6DeJNjBs2H1gySX
 
 // ── PDA helpers ──
 
-function findPrivateReceiptPda(paymentId: string): [PublicKey, number] {
+// Receipts are scoped per merchant; IDs are hashed to fit the 32-byte seed limit (mirrors `Payment::id_seed`)
+const idSeed = (id: string): Buffer => createHash('sha256').update(id).digest();
+
+function findPrivateReceiptPda(merchant: PublicKey, paymentId: string): [PublicKey, number] {
     return PublicKey.findProgramAddressSync(
-        [Buffer.from('private_receipt'), Buffer.from(paymentId)],
+        [Buffer.from('private_receipt'), merchant.toBuffer(), idSeed(paymentId)],
         OFFBANK_PROGRAM_ID,
     );
 }
@@ -107,7 +111,7 @@ async function main() {
     const memo = 'Hackathon PER test payment';
     const merchantWallet = Keypair.generate().publicKey;
 
-    const [receiptPda, receiptBump] = findPrivateReceiptPda(paymentId);
+    const [receiptPda, receiptBump] = findPrivateReceiptPda(merchantWallet, paymentId);
     const [bufferPda]   = findDelegationBufferPda(receiptPda);
     const [recordPda]   = findDelegationRecordPda(receiptPda);
     const [metadataPda] = findDelegationMetadataPda(receiptPda);
@@ -127,6 +131,7 @@ async function main() {
         const sig = await program.methods
             .issuePrivateReceipt(paymentId, amount, feeAmount, memo)
             .accountsPartial({
+                payer: wallet.publicKey,
                 customer: wallet.publicKey,
                 merchant: merchantWallet,
                 privateReceipt: receiptPda,
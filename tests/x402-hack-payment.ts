@@ -8,23 +8,47 @@ import {
   TOKEN_PROGRAM_ID,
   ASSOCIATED_TOKEN_PROGRAM_ID,
 } from "@solana/spl-token";
+import { createHash } from "crypto";
 import { X402HackPayment } from "../target/types/x402_hack_payment";
 
+// PDA seeds hash IDs so they fit Solana's 32-byte seed limit (mirrors `Merchant::id_seed` / `Payment::id_seed`)
+const idSeed = (id: string): Buffer => createHash("sha256").update(id).digest();
+
 describe("x402-hack-payment", () => {
   // Configure the client to use the local cluster.
   const provider = anchor.AnchorProvider.env()
   anchor.setProvider(provider);
 
   const program = anchor.workspace.X402HackPayment as Program<X402HackPayment>;
+  // Signer for self-CPI event emission (`emit_cpi!`)
+  const [eventAuthorityPDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("__event_authority")],
+    program.programId,
+  );
   const connection = provider.connection;
 
   const authority = provider.wallet;
 
+  // Decode `emit_cpi!` events from a transaction's inner instructions (skipping the 8-byte CPI tag)
+  const fetchCpiEvents = async (signature: string) => {
+    await connection.confirmTransaction(signature, "confirmed");
+    const txInfo = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    return (txInfo?.meta?.innerInstructions ?? [])
+      .flatMap((inner) => inner.instructions)
+      .filter((ix) => txInfo!.transaction.message.staticAccountKeys[ix.programIdIndex].equals(program.programId))
+      .map((ix) => program.coder.events.decode(
+        anchor.utils.bytes.base64.encode(Buffer.from(anchor.utils.bytes.bs58.decode(ix.data)).subarray(8)),
+      ))
+      .filter((event) => event !== null);
+  };
+
   let platformConfigPDA: PublicKey;
   let platformTreasuryPDA: PublicKey;
   let usdcMint: PublicKey;
   let merchantAccountPDA: PublicKey;
-  let merchantBump: number;
   const settlementWallet = Keypair.generate();
 
   const MERCHANT_ID = "merchant_123";
@@ -44,7 +68,7 @@ describe("x402-hack-payment", () => {
     console.log(`\nPlatform Treasury PDA: ${platformTreasuryPDA.toBase58()}`);
 
     [merchantAccountPDA] = PublicKey.findProgramAddressSync(
-      [Buffer.from("merchant"), Buffer.from("merchant_123")],
+      [Buffer.from("merchant"), idSeed(MERCHANT_ID)],
       program.programId,
     );
     console.log(`\nMerchant Account PDA: ${merchantAccountPDA.toBase58()}`);
@@ -72,6 +96,8 @@ describe("x402-hack-payment", () => {
         usdcMint: usdcMint,
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
       })
       .rpc();
     console.log(`Transaction signature: ${tx}`);
@@ -86,13 +112,15 @@ describe("x402-hack-payment", () => {
   it("Merchant is initialized!", async () => {
     const merchantId = "merchant123";
     const tx = await program.methods
-      .initializeMerchant(MERCHANT_ID, merchantBump)
+      .initializeMerchant(MERCHANT_ID, 0)
       .accountsStrict({
         payer: authority.publicKey,
         merchantAccount: merchantAccountPDA,
         platformConfig: platformConfigPDA,
         settlementWallet: settlementWallet.publicKey,
         systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
       })
       .rpc();
     console.log(`\nTransaction signature: ${tx}`);
@@ -112,7 +140,7 @@ describe("x402-hack-payment", () => {
 
     // Derive payment account PDA
     const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
-      [Buffer.from("payment"), Buffer.from(paymentId)],
+      [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
       program.programId,
     );
     console.log(`\nPayment Account PDA: ${paymentAccountPDA.toBase58()}`);
@@ -152,12 +180,15 @@ describe("x402-hack-payment", () => {
     console.log(`\nCustomer USDC Account: ${customerUsdcAccount.address.toBase58()}`);
     console.log(`\nMerchant USDC Account: ${merchantUsdcAccount.address.toBase58()}`);
 
+    const pendingBefore = (await program.account.platform.fetch(platformConfigPDA)).pendingFees;
+
     // Process payment
     const paymentAmount = 20000; // 0.02 USDC
     const tx = await program.methods
-      .processPayment(paymentId, new BN(paymentAmount))
+      .processPayment(paymentId, new BN(paymentAmount), null)
       .accountsStrict({
         payer: user.publicKey,
+        customer: user.publicKey,
         platformConfig: platformConfigPDA,
         paymentAccount: paymentAccountPDA,
         customerAccount: customerAccountPDA,
@@ -166,9 +197,12 @@ describe("x402-hack-payment", () => {
         customerUsdc: customerUsdcAccount.address,
         merchantUsdc: merchantUsdcAccount.address,
         platformTreasuryUsdc: platformTreasuryPDA,
+        sponsorUsdc: null,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
       })
       .signers([user])
       .rpc();
@@ -182,6 +216,263 @@ describe("x402-hack-payment", () => {
 
     const platformTreasuryBalance = (await connection.getTokenAccountBalance(platformTreasuryPDA)).value.amount;
     console.log(`\nPlatform Treasury Balance: ${platformTreasuryBalance}`);
+
+    // The payment is reported as a typed event, not just a log line
+    const events = await fetchCpiEvents(tx);
+    const processed = events.find((event) => event.name === "paymentProcessed");
+    if (!processed || processed.data.paymentId !== paymentId || !processed.data.amount.eq(new BN(paymentAmount))) {
+      throw new Error(`Expected a PaymentProcessed event for ${paymentId}, got ${JSON.stringify(events)}`);
+    }
+
+    // The fee stays refundable until the payment is finalized, separate from payout escrow
+    const pendingAfter = (await program.account.platform.fetch(platformConfigPDA)).pendingFees;
+    if (!pendingAfter.sub(pendingBefore).eq(paymentAccount.feeAmount)) {
+      throw new Error(`Pending fees grew by ${pendingAfter.sub(pendingBefore)}, expected ${paymentAccount.feeAmount}`);
+    }
+  });
+
+  it("Applies the merchant fee override before the platform default", async () => {
+    await program.methods
+      .setMerchantFee(100) // 1% negotiated rate
+      .accountsStrict({
+        authority: authority.publicKey,
+        platformConfig: platformConfigPDA,
+        merchantAccount: merchantAccountPDA,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
+      })
+      .rpc();
+
+    const user = Keypair.generate();
+    const airdropSig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+    await connection.confirmTransaction(airdropSig);
+
+    const paymentId = `pay_override_${Date.now()}`;
+    const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
+      program.programId,
+    );
+    const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("customer"), user.publicKey.toBuffer()],
+      program.programId,
+    );
+    const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      authority.payer,
+      usdcMint,
+      user.publicKey,
+    );
+    const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      authority.payer,
+      usdcMint,
+      settlementWallet.publicKey,
+    );
+    await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+
+    await program.methods
+      .processPayment(paymentId, new BN(100000), null)
+      .accountsStrict({
+        payer: user.publicKey,
+        customer: user.publicKey,
+        platformConfig: platformConfigPDA,
+        paymentAccount: paymentAccountPDA,
+        customerAccount: customerAccountPDA,
+        merchantAccount: merchantAccountPDA,
+        usdcMint: usdcMint,
+        customerUsdc: customerUsdcAccount.address,
+        merchantUsdc: merchantUsdcAccount.address,
+        platformTreasuryUsdc: platformTreasuryPDA,
+        sponsorUsdc: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
+      })
+      .signers([user])
+      .rpc();
+
+    const payment = await program.account.payment.fetch(paymentAccountPDA);
+    if (payment.feeAmount.toNumber() !== 1000 || payment.feeBps.toNumber() !== 100 || !("merchantOverride" in payment.feeSource)) {
+      throw new Error(`Override not applied: ${JSON.stringify(payment)}`);
+    }
+
+    // Clear the override so later tests fall back to the platform default
+    await program.methods
+      .setMerchantFee(null)
+      .accountsStrict({
+        authority: authority.publicKey,
+        platformConfig: platformConfigPDA,
+        merchantAccount: merchantAccountPDA,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
+      })
+      .rpc();
+    const merchant = await program.account.merchant.fetch(merchantAccountPDA);
+    if (merchant.fee !== null) {
+      throw new Error("Fee override was not cleared");
+    }
+  });
+
+  it("Fails when non-authority sets a merchant fee override", async () => {
+    try {
+      await program.methods
+        .setMerchantFee(0)
+        .accountsStrict({
+          authority: settlementWallet.publicKey,
+          platformConfig: platformConfigPDA,
+          merchantAccount: merchantAccountPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .signers([settlementWallet])
+        .rpc();
+      throw new Error("Should have failed with Unauthorized");
+    } catch (err: any) {
+      console.log(`\nExpected unauthorized fee override error: ${err.message}`);
+      if (err.message.startsWith("Should have failed")) throw err;
+    }
+  });
+
+  it("Stages settlement wallet changes behind the platform timelock", async () => {
+    const newSettlementWallet = Keypair.generate();
+
+    await program.methods
+      .updateMerchant({ settlementWallet: newSettlementWallet.publicKey, authority: null, isActive: null, feeMode: null })
+      .accountsStrict({
+        authority: authority.publicKey,
+        platformConfig: platformConfigPDA,
+        merchantAccount: merchantAccountPDA,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
+      })
+      .rpc();
+
+    let merchant = await program.account.merchant.fetch(merchantAccountPDA);
+    if (!merchant.pendingSettlementWallet?.equals(newSettlementWallet.publicKey)
+      || !merchant.settlementWallet.equals(settlementWallet.publicKey)) {
+      throw new Error(`Settlement wallet was not staged: ${JSON.stringify(merchant)}`);
+    }
+
+    // Still inside the (default 24h) timelock
+    try {
+      await program.methods
+        .applySettlementWallet()
+        .accountsStrict({ merchantAccount: merchantAccountPDA, eventAuthority: eventAuthorityPDA, program: program.programId })
+        .rpc();
+      throw new Error("Should have failed with SettlementWalletTimelocked");
+    } catch (err: any) {
+      console.log(`\nExpected timelock error: ${err.message}`);
+      if (err.message.startsWith("Should have failed")) throw err;
+    }
+
+    await program.methods
+      .cancelSettlementWalletChange()
+      .accountsStrict({
+        authority: authority.publicKey,
+        platformConfig: platformConfigPDA,
+        merchantAccount: merchantAccountPDA,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
+      })
+      .rpc();
+
+    merchant = await program.account.merchant.fetch(merchantAccountPDA);
+    if (merchant.pendingSettlementWallet !== null) {
+      throw new Error("Staged settlement wallet was not cancelled");
+    }
+  });
+
+  it("Stages merchant authority changes behind the same timelock", async () => {
+    const newAuthority = Keypair.generate();
+    const newSettlementWallet = Keypair.generate();
+
+    // A compromised key stages both changes in one call; neither takes effect yet
+    await program.methods
+      .updateMerchant({
+        settlementWallet: newSettlementWallet.publicKey,
+        authority: newAuthority.publicKey,
+        isActive: null,
+        feeMode: null,
+      })
+      .accountsStrict({
+        authority: authority.publicKey,
+        platformConfig: platformConfigPDA,
+        merchantAccount: merchantAccountPDA,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
+      })
+      .rpc();
+
+    let merchant = await program.account.merchant.fetch(merchantAccountPDA);
+    if (!merchant.authority.equals(authority.publicKey) || !merchant.pendingAuthority?.equals(newAuthority.publicKey)) {
+      throw new Error(`Authority change was not staged: ${JSON.stringify(merchant)}`);
+    }
+
+    try {
+      await program.methods
+        .acceptMerchantAuthority()
+        .accountsStrict({
+          newAuthority: newAuthority.publicKey,
+          merchantAccount: merchantAccountPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .signers([newAuthority])
+        .rpc();
+      throw new Error("Should have failed with MerchantAuthorityTimelocked");
+    } catch (err: any) {
+      console.log(`\nExpected authority timelock error: ${err.message}`);
+      if (err.message.startsWith("Should have failed")) throw err;
+    }
+
+    // The owner still holds the authority during the timelock and cancels both
+    await program.methods
+      .cancelMerchantAuthorityTransfer()
+      .accountsStrict({
+        authority: authority.publicKey,
+        merchantAccount: merchantAccountPDA,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
+      })
+      .rpc();
+    await program.methods
+      .cancelSettlementWalletChange()
+      .accountsStrict({
+        authority: authority.publicKey,
+        platformConfig: platformConfigPDA,
+        merchantAccount: merchantAccountPDA,
+        eventAuthority: eventAuthorityPDA,
+        program: program.programId,
+      })
+      .rpc();
+
+    merchant = await program.account.merchant.fetch(merchantAccountPDA);
+    if (merchant.pendingAuthority !== null || merchant.pendingSettlementWallet !== null) {
+      throw new Error("Staged authority and wallet changes were not cancelled");
+    }
+  });
+
+  it("Fails when a non-authority updates the merchant", async () => {
+    const attacker = Keypair.generate();
+    try {
+      await program.methods
+        .updateMerchant({ settlementWallet: attacker.publicKey, authority: null, isActive: null, feeMode: null })
+        .accountsStrict({
+          authority: attacker.publicKey,
+          platformConfig: platformConfigPDA,
+          merchantAccount: merchantAccountPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .signers([attacker])
+        .rpc();
+      throw new Error("Should have failed with Unauthorized");
+    } catch (err: any) {
+      console.log(`\nExpected unauthorized merchant update error: ${err.message}`);
+      if (err.message.startsWith("Should have failed")) throw err;
+    }
   });
 
   // ============================================
@@ -196,7 +487,7 @@ describe("x402-hack-payment", () => {
 
       const paymentId = `pay_min_${Date.now()}`;
       const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("payment"), Buffer.from(paymentId)],
+        [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
         program.programId,
       );
       const [customerAccountPDA] = PublicKey.findProgramAddressSync(
@@ -231,9 +522,10 @@ describe("x402-hack-payment", () => {
 
       try {
         await program.methods
-          .processPayment(paymentId, new BN(belowMinAmount))
+          .processPayment(paymentId, new BN(belowMinAmount), null)
           .accountsStrict({
             payer: user.publicKey,
+            customer: user.publicKey,
             platformConfig: platformConfigPDA,
             paymentAccount: paymentAccountPDA,
             customerAccount: customerAccountPDA,
@@ -242,9 +534,12 @@ describe("x402-hack-payment", () => {
             customerUsdc: customerUsdcAccount.address,
             merchantUsdc: merchantUsdcAccount.address,
             platformTreasuryUsdc: platformTreasuryPDA,
+            sponsorUsdc: null,
             tokenProgram: TOKEN_PROGRAM_ID,
             associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
           })
           .signers([user])
           .rpc();
@@ -267,7 +562,7 @@ describe("x402-hack-payment", () => {
 
       const paymentId = `pay_insuf_${Date.now()}`;
       const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
-        [Buffer.from("payment"), Buffer.from(paymentId)],
+        [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
         program.programId,
       );
       const [customerAccountPDA] = PublicKey.findProgramAddressSync(
@@ -293,9 +588,10 @@ describe("x402-hack-payment", () => {
 
       try {
         await program.methods
-          .processPayment(paymentId, new BN(paymentAmount))
+          .processPayment(paymentId, new BN(paymentAmount), null)
           .accountsStrict({
             payer: user.publicKey,
+            customer: user.publicKey,
             platformConfig: platformConfigPDA,
             paymentAccount: paymentAccountPDA,
             customerAccount: customerAccountPDA,
@@ -304,9 +600,12 @@ describe("x402-hack-payment", () => {
             customerUsdc: customerUsdcAccount.address,
             merchantUsdc: merchantUsdcAccount.address,
             platformTreasuryUsdc: platformTreasuryPDA,
+            sponsorUsdc: null,
             tokenProgram: TOKEN_PROGRAM_ID,
             associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
           })
           .signers([user])
           .rpc();
@@ -332,7 +631,7 @@ describe("x402-hack-payment", () => {
 
       try {
         const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
-          [Buffer.from("payment"), Buffer.from(paymentId)],
+          [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
           program.programId,
         );
         const [customerAccountPDA] = PublicKey.findProgramAddressSync(
@@ -363,9 +662,10 @@ describe("x402-hack-payment", () => {
         );
 
         await program.methods
-          .processPayment(paymentId, new BN(20000))
+          .processPayment(paymentId, new BN(20000), null)
           .accountsStrict({
             payer: user.publicKey,
+            customer: user.publicKey,
             platformConfig: platformConfigPDA,
             paymentAccount: paymentAccountPDA,
             customerAccount: customerAccountPDA,
@@ -374,9 +674,12 @@ describe("x402-hack-payment", () => {
             customerUsdc: customerUsdcAccount.address,
             merchantUsdc: merchantUsdcAccount.address,
             platformTreasuryUsdc: platformTreasuryPDA,
+            sponsorUsdc: null,
             tokenProgram: TOKEN_PROGRAM_ID,
             associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
           })
           .signers([user])
           .rpc();
@@ -404,6 +707,8 @@ describe("x402-hack-payment", () => {
             usdcMint: usdcMint,
             systemProgram: SystemProgram.programId,
             tokenProgram: TOKEN_PROGRAM_ID,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
           })
           .signers([fakeAuthority])
           .rpc();
@@ -425,6 +730,8 @@ describe("x402-hack-payment", () => {
             usdcMint: usdcMint,
             systemProgram: SystemProgram.programId,
             tokenProgram: TOKEN_PROGRAM_ID,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
           })
           .rpc();
         throw new Error("Should have failed with InvalidFeeBps");
@@ -436,6 +743,208 @@ describe("x402-hack-payment", () => {
         }
       }
     });
+    it("Updates platform config fields independently", async () => {
+      await program.methods
+        .updatePlatformConfig({ feeBps: new BN(300), minPaymentAmount: null, isActive: null, settlementWalletDelay: null, refundWindow: null, arbiter: null, escrowTimeout: null, authorizationTtl: null, maxSponsorFee: null, disputeWindow: null })
+        .accountsStrict({
+          authority: authority.publicKey,
+          platformConfig: platformConfigPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .rpc();
+
+      let config = await program.account.platform.fetch(platformConfigPDA);
+      if (config.feeBps.toNumber() !== 300 || config.minPaymentAmount.toNumber() !== 10000) {
+        throw new Error(`Unexpected config after fee update: ${JSON.stringify(config)}`);
+      }
+
+      // Restore the original fee so later payment tests keep their expectations
+      await program.methods
+        .updatePlatformConfig({ feeBps: new BN(250), minPaymentAmount: null, isActive: null, settlementWalletDelay: null, refundWindow: null, arbiter: null, escrowTimeout: null, authorizationTtl: null, maxSponsorFee: null, disputeWindow: null })
+        .accountsStrict({
+          authority: authority.publicKey,
+          platformConfig: platformConfigPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .rpc();
+
+      config = await program.account.platform.fetch(platformConfigPDA);
+      console.log(`\nPlatform config after update: ${JSON.stringify(config)}`);
+    });
+
+    it("Fails when non-authority calls update_platform_config", async () => {
+      const fakeAuthority = Keypair.generate();
+
+      try {
+        await program.methods
+          .updatePlatformConfig({ feeBps: null, minPaymentAmount: null, isActive: false, settlementWalletDelay: null, refundWindow: null, arbiter: null, escrowTimeout: null, authorizationTtl: null, maxSponsorFee: null, disputeWindow: null })
+          .accountsStrict({
+            authority: fakeAuthority.publicKey,
+            platformConfig: platformConfigPDA,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
+          })
+          .signers([fakeAuthority])
+          .rpc();
+        throw new Error("Should have failed with Unauthorized");
+      } catch (err: any) {
+        console.log(`\nExpected unauthorized update error: ${err.message}`);
+        if (err.message.startsWith("Should have failed")) throw err;
+      }
+    });
+
+    it("Fails to update platform config with out-of-range values", async () => {
+      for (const args of [
+        { feeBps: new BN(1001), minPaymentAmount: null, isActive: null, settlementWalletDelay: null, refundWindow: null, arbiter: null, escrowTimeout: null, authorizationTtl: null, maxSponsorFee: null, disputeWindow: null },
+        { feeBps: null, minPaymentAmount: new BN(0), isActive: null, settlementWalletDelay: null, refundWindow: null, arbiter: null, escrowTimeout: null, authorizationTtl: null, maxSponsorFee: null, disputeWindow: null },
+        { feeBps: null, minPaymentAmount: null, isActive: null, settlementWalletDelay: null, refundWindow: new BN(181 * 86400), arbiter: null, escrowTimeout: null, authorizationTtl: null, maxSponsorFee: null, disputeWindow: null },
+        { feeBps: null, minPaymentAmount: null, isActive: null, settlementWalletDelay: null, refundWindow: null, arbiter: null, escrowTimeout: new BN(91 * 86400), authorizationTtl: null, maxSponsorFee: null, disputeWindow: null },
+        { feeBps: null, minPaymentAmount: null, isActive: null, settlementWalletDelay: null, refundWindow: null, arbiter: null, escrowTimeout: null, authorizationTtl: new BN(0), maxSponsorFee: null, disputeWindow: null },
+        { feeBps: null, minPaymentAmount: null, isActive: null, settlementWalletDelay: null, refundWindow: null, arbiter: null, escrowTimeout: null, authorizationTtl: null, maxSponsorFee: null, disputeWindow: new BN(181 * 86400) },
+      ]) {
+        try {
+          await program.methods
+            .updatePlatformConfig(args)
+            .accountsStrict({
+              authority: authority.publicKey,
+              platformConfig: platformConfigPDA,
+              eventAuthority: eventAuthorityPDA,
+              program: program.programId,
+            })
+            .rpc();
+          throw new Error("Should have failed with InvalidFeeBps / InvalidMinPaymentAmount / InvalidRefundWindow / InvalidEscrowTimeout / InvalidAuthorizationTtl / InvalidDisputeWindow");
+        } catch (err: any) {
+          console.log(`\nExpected bounds error: ${err.message}`);
+          if (err.message.startsWith("Should have failed")) throw err;
+        }
+      }
+    });
+  });
+
+  describe("Authority Handover", () => {
+    it("Only the nominated key can accept, and a nomination can be cancelled", async () => {
+      const nominee = Keypair.generate();
+      const impostor = Keypair.generate();
+
+      await program.methods
+        .proposeAuthority()
+        .accountsStrict({
+          authority: authority.publicKey,
+          newAuthority: nominee.publicKey,
+          platformConfig: platformConfigPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .rpc();
+
+      try {
+        await program.methods
+          .acceptAuthority()
+          .accountsStrict({
+            newAuthority: impostor.publicKey,
+            platformConfig: platformConfigPDA,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
+          })
+          .signers([impostor])
+          .rpc();
+        throw new Error("Should have failed with Unauthorized");
+      } catch (err: any) {
+        console.log(`\nExpected impostor accept error: ${err.message}`);
+        if (err.message.startsWith("Should have failed")) throw err;
+      }
+
+      await program.methods
+        .cancelAuthorityTransfer()
+        .accountsStrict({
+          authority: authority.publicKey,
+          platformConfig: platformConfigPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .rpc();
+
+      const config = await program.account.platform.fetch(platformConfigPDA);
+      if (config.pendingAuthority !== null || !config.authority.equals(authority.publicKey)) {
+        throw new Error(`Unexpected config after cancel: ${JSON.stringify(config)}`);
+      }
+
+      try {
+        await program.methods
+          .acceptAuthority()
+          .accountsStrict({
+            newAuthority: nominee.publicKey,
+            platformConfig: platformConfigPDA,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
+          })
+          .signers([nominee])
+          .rpc();
+        throw new Error("Should have failed with NoPendingAuthority");
+      } catch (err: any) {
+        console.log(`\nExpected accept-after-cancel error: ${err.message}`);
+        if (err.message.startsWith("Should have failed")) throw err;
+      }
+    });
+
+    it("Hands authority over and back via propose/accept", async () => {
+      const nominee = Keypair.generate();
+      const airdropSig = await connection.requestAirdrop(nominee.publicKey, anchor.web3.LAMPORTS_PER_SOL);
+      await connection.confirmTransaction(airdropSig);
+
+      await program.methods
+        .proposeAuthority()
+        .accountsStrict({
+          authority: authority.publicKey,
+          newAuthority: nominee.publicKey,
+          platformConfig: platformConfigPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .rpc();
+      await program.methods
+        .acceptAuthority()
+        .accountsStrict({
+          newAuthority: nominee.publicKey,
+          platformConfig: platformConfigPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .signers([nominee])
+        .rpc();
+
+      let config = await program.account.platform.fetch(platformConfigPDA);
+      if (!config.authority.equals(nominee.publicKey)) {
+        throw new Error("Authority was not handed over");
+      }
+
+      // Hand it back so the remaining tests keep using the provider wallet
+      await program.methods
+        .proposeAuthority()
+        .accountsStrict({
+          authority: nominee.publicKey,
+          newAuthority: authority.publicKey,
+          platformConfig: platformConfigPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .signers([nominee])
+        .rpc();
+      await program.methods
+        .acceptAuthority()
+        .accountsStrict({
+          newAuthority: authority.publicKey,
+          platformConfig: platformConfigPDA,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .rpc();
+
+      config = await program.account.platform.fetch(platformConfigPDA);
+      console.log(`\nPlatform authority after round trip: ${config.authority.toBase58()}`);
+    });
   });
 
   describe("Edge Cases - Merchant Operations", () => {
@@ -444,7 +953,7 @@ describe("x402-hack-payment", () => {
 
       try {
         const [invalidMerchantPDA] = PublicKey.findProgramAddressSync(
-          [Buffer.from("merchant"), Buffer.from(invalidMerchantId)],
+          [Buffer.from("merchant"), idSeed(invalidMerchantId)],
           program.programId,
         );
 
@@ -456,6 +965,8 @@ describe("x402-hack-payment", () => {
             platformConfig: platformConfigPDA,
             settlementWallet: settlementWallet.publicKey,
             systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
           })
           .rpc();
         throw new Error("Should have failed with InvalidMerchantId");
@@ -468,13 +979,15 @@ describe("x402-hack-payment", () => {
       try {
         // Try to initialize the same merchant again
         await program.methods
-          .initializeMerchant(MERCHANT_ID, merchantBump)
+          .initializeMerchant(MERCHANT_ID, 0)
           .accountsStrict({
             payer: authority.publicKey,
             merchantAccount: merchantAccountPDA,
             platformConfig: platformConfigPDA,
             settlementWallet: settlementWallet.publicKey,
             systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
           })
           .rpc();
         throw new Error("Should have failed - merchant already exists");
@@ -483,6 +996,32 @@ describe("x402-hack-payment", () => {
         // Should fail because account already exists
       }
     });
+
+    it("Initializes a merchant with a full-length 64-byte ID", async () => {
+      const longMerchantId = "m".repeat(64);
+      const [longMerchantPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("merchant"), idSeed(longMerchantId)],
+        program.programId,
+      );
+
+      await program.methods
+        .initializeMerchant(longMerchantId, 0)
+        .accountsStrict({
+          payer: authority.publicKey,
+          merchantAccount: longMerchantPDA,
+          platformConfig: platformConfigPDA,
+          settlementWallet: settlementWallet.publicKey,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPDA,
+          program: program.programId,
+        })
+        .rpc();
+
+      const merchant = await program.account.merchant.fetch(longMerchantPDA);
+      if (merchant.merchantId !== longMerchantId) {
+        throw new Error("Merchant ID was not stored in full");
+      }
+    });
   });
 
   describe("Edge Cases - Fee Claiming", () => {
@@ -504,9 +1043,13 @@ describe("x402-hack-payment", () => {
           .accountsStrict({
             authority: fakeAuthority.publicKey,
             platformConfig: platformConfigPDA,
-            platformTreasury: platformTreasuryPDA,
-            destination: fakeDestination.address,
+            platformTreasuryUsdc: platformTreasuryPDA,
+            authorityUsdc: fakeDestination.address,
+            usdcMint: usdcMint,
             tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
           })
           .signers([fakeAuthority])
           .rpc();
@@ -526,22 +1069,37 @@ describe("x402-hack-payment", () => {
 
       const treasuryBalanceBefore = (await connection.getTokenAccountBalance(platformTreasuryPDA)).value.amount;
       console.log(`\nTreasury balance before claim: ${treasuryBalanceBefore}`);
+      const { accruedFees } = await program.account.platform.fetch(platformConfigPDA);
 
-      if (parseInt(treasuryBalanceBefore) > 0) {
+      if (accruedFees.toNumber() > 0) {
         const tx = await program.methods
           .claimPlatformFees()
           .accountsStrict({
             authority: authority.publicKey,
             platformConfig: platformConfigPDA,
-            platformTreasury: platformTreasuryPDA,
-            destination: authorityDestination.address,
+            platformTreasuryUsdc: platformTreasuryPDA,
+            authorityUsdc: authorityDestination.address,
+            usdcMint: usdcMint,
             tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
           })
           .rpc();
         console.log(`\nClaim fees tx: ${tx}`);
 
         const treasuryBalanceAfter = (await connection.getTokenAccountBalance(platformTreasuryPDA)).value.amount;
         console.log(`\nTreasury balance after claim: ${treasuryBalanceAfter}`);
+
+        // Only accrued fees leave the treasury; escrowed payout deposits stay put
+        const claimed = BigInt(treasuryBalanceBefore) - BigInt(treasuryBalanceAfter);
+        if (claimed !== BigInt(accruedFees.toString())) {
+          throw new Error(`Claimed ${claimed}, expected exactly the accrued ${accruedFees}`);
+        }
+        const config = await program.account.platform.fetch(platformConfigPDA);
+        if (config.accruedFees.toNumber() !== 0) {
+          throw new Error("Accrued fees were not reset after claim");
+        }
       } else {
         console.log("\nNo fees to claim, skipping claim test");
       }
@@ -583,7 +1141,7 @@ describe("x402-hack-payment", () => {
       for (let i = 0; i < paymentCount; i++) {
         const paymentId = `pay_seq_${Date.now()}_${i}`;
         const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
-          [Buffer.from("payment"), Buffer.from(paymentId)],
+          [Buffer.from("payment"), merchantAccountPDA.toBuffer(), idSeed(paymentId)],
           program.programId,
         );
         const [customerAccountPDA] = PublicKey.findProgramAddressSync(
@@ -592,9 +1150,10 @@ describe("x402-hack-payment", () => {
         );
 
         const tx = await program.methods
-          .processPayment(paymentId, new BN(paymentAmount))
+          .processPayment(paymentId, new BN(paymentAmount), null)
           .accountsStrict({
             payer: user.publicKey,
+            customer: user.publicKey,
             platformConfig: platformConfigPDA,
             paymentAccount: paymentAccountPDA,
             customerAccount: customerAccountPDA,
@@ -603,9 +1162,12 @@ describe("x402-hack-payment", () => {
             customerUsdc: customerUsdcAccount.address,
             merchantUsdc: merchantUsdcAccount.address,
             platformTreasuryUsdc: platformTreasuryPDA,
+            sponsorUsdc: null,
             tokenProgram: TOKEN_PROGRAM_ID,
             associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
           })
           .signers([user])
           .rpc();
@@ -615,4 +1177,73 @@ describe("x402-hack-payment", () => {
       console.log(`\n✓ Successfully processed ${paymentCount} sequential payments`);
     });
   });
+
+  describe("Payment ID Namespacing", () => {
+    it("Lets two merchants use the same payment ID without colliding", async () => {
+      const user = Keypair.generate();
+      const airdropSig = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+      await connection.confirmTransaction(airdropSig);
+
+      const customerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+        connection,
+        authority.payer,
+        usdcMint,
+        user.publicKey,
+      );
+      const merchantUsdcAccount = await getOrCreateAssociatedTokenAccount(
+        connection,
+        authority.payer,
+        usdcMint,
+        settlementWallet.publicKey,
+      );
+      await mintTo(connection, authority.payer, usdcMint, customerUsdcAccount.address, authority.publicKey, 100000);
+
+      const [customerAccountPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("customer"), user.publicKey.toBuffer()],
+        program.programId,
+      );
+      // Second merchant registered in "Initializes a merchant with a full-length 64-byte ID"
+      const [otherMerchantPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("merchant"), idSeed("m".repeat(64))],
+        program.programId,
+      );
+
+      // The same checkout ID, used first against another merchant, must not block this merchant
+      const paymentId = `pay_shared_${Date.now()}`;
+      for (const merchantPDA of [otherMerchantPDA, merchantAccountPDA]) {
+        const [paymentAccountPDA] = PublicKey.findProgramAddressSync(
+          [Buffer.from("payment"), merchantPDA.toBuffer(), idSeed(paymentId)],
+          program.programId,
+        );
+
+        await program.methods
+          .processPayment(paymentId, new BN(20000), null)
+          .accountsStrict({
+            payer: user.publicKey,
+            customer: user.publicKey,
+            platformConfig: platformConfigPDA,
+            merchantAccount: merchantPDA,
+            paymentAccount: paymentAccountPDA,
+            customerAccount: customerAccountPDA,
+            usdcMint: usdcMint,
+            customerUsdc: customerUsdcAccount.address,
+            merchantUsdc: merchantUsdcAccount.address,
+            platformTreasuryUsdc: platformTreasuryPDA,
+            sponsorUsdc: null,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPDA,
+            program: program.programId,
+          })
+          .signers([user])
+          .rpc();
+
+        const payment = await program.account.payment.fetch(paymentAccountPDA);
+        if (!payment.merchant.equals(merchantPDA)) {
+          throw new Error("Payment recorded against the wrong merchant");
+        }
+      }
+    });
+  });
 });